@@ -0,0 +1,317 @@
+//! Admin Panel Component
+//!
+//! This page gathers the management operations reserved to admins. The users
+//! section lists the accounts and lets admins create and delete them, and the
+//! API keys section manages the keys used by scripts, with their quotas and usage.
+
+use crate::model::api_key::ApiKeyInfo;
+use crate::model::auth::{Role, UserInfo};
+use crate::server_functions::server_functions::{create_api_key, create_user, delete_user, list_api_keys, list_users, revoke_api_key};
+use dioxus::prelude::*;
+
+/// Page with the management operations reserved to admins
+#[component]
+pub fn AdminPanel() -> Element {
+    let user = use_context::<Signal<Option<UserInfo>>>();
+    if !user().is_some_and(|user| user.is_admin()) {
+        return rsx! {
+            div {
+                class: "w-full max-w-[80rem] mx-auto p-4 pt-16 text-red-400",
+                "This page requires the admin role"
+            }
+        };
+    }
+
+    rsx! {
+        div {
+            class: "w-full max-w-[80rem] mx-auto h-screen overflow-y-auto flex flex-col gap-6 p-4 pt-16 text-gray-300",
+            h1 { class: "text-xl font-semibold", "Administration" }
+            UserManagement {}
+            ApiKeyManagement {}
+        }
+    }
+}
+
+/// Section listing the user accounts with forms to create and delete them
+#[component]
+fn UserManagement() -> Element {
+    let mut users = use_resource(|| async move { list_users().await.map_err(|e| e.to_string()) });
+    let mut username = use_signal(String::new);
+    let mut password = use_signal(String::new);
+    let mut is_admin = use_signal(|| false);
+    let mut error = use_signal(|| None::<String>);
+
+    let create = move |event: FormEvent| {
+        event.prevent_default();
+        let role = if is_admin() { Role::Admin } else { Role::User };
+        spawn(async move {
+            match create_user(username(), password(), role).await {
+                Ok(_) => {
+                    username.set(String::new());
+                    password.set(String::new());
+                    is_admin.set(false);
+                    error.set(None);
+                    users.restart();
+                }
+                Err(e) => error.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    let remove = move |name: String| {
+        spawn(async move {
+            match delete_user(name).await {
+                Ok(()) => users.restart(),
+                Err(e) => error.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    rsx! {
+        section {
+            class: "flex flex-col gap-3",
+            h2 { class: "text-lg font-semibold", "Users" }
+            form {
+                class: "flex flex-wrap gap-2 items-center",
+                onsubmit: create,
+                input {
+                    class: "px-3 py-1 rounded-lg bg-gray-800 focus:outline-none",
+                    placeholder: "Username",
+                    value: "{username}",
+                    oninput: move |event| username.set(event.value()),
+                }
+                input {
+                    class: "px-3 py-1 rounded-lg bg-gray-800 focus:outline-none",
+                    r#type: "password",
+                    placeholder: "Password",
+                    autocomplete: "new-password",
+                    value: "{password}",
+                    oninput: move |event| password.set(event.value()),
+                }
+                label {
+                    class: "flex gap-1 items-center text-sm",
+                    input {
+                        r#type: "checkbox",
+                        checked: is_admin(),
+                        onchange: move |event| is_admin.set(event.checked()),
+                    }
+                    "Admin"
+                }
+                button {
+                    class: "px-3 py-1 rounded-lg bg-gray-700 hover:bg-gray-600",
+                    r#type: "submit",
+                    "Create user"
+                }
+            }
+            if let Some(e) = error() {
+                p { class: "text-sm text-red-400", "{e}" }
+            }
+            { match &*users.read() {
+                None => rsx! { p { class: "text-gray-500", "Loading users..." } },
+                Some(Err(e)) => rsx! { p { class: "text-red-400", "{e}" } },
+                Some(Ok(users)) => render_user_table(users, remove),
+            } }
+        }
+    }
+}
+
+/// Render the accounts with a delete button each
+fn render_user_table(users: &[UserInfo], remove: impl FnMut(String) + Clone + 'static) -> Element {
+    rsx! {
+        table {
+            class: "w-full text-sm text-left",
+            thead {
+                tr {
+                    class: "text-gray-500",
+                    th { class: "py-1", "Username" }
+                    th { class: "py-1", "Role" }
+                    th { class: "py-1" }
+                }
+            }
+            tbody {
+                for user in users.iter() {
+                    tr {
+                        key: "{user.username}",
+                        class: "border-t border-gray-800",
+                        td { class: "py-1", "{user.username}" }
+                        td { class: "py-1", if user.is_admin() { "admin" } else { "user" } }
+                        td {
+                            class: "py-1 text-right",
+                            button {
+                                class: "px-2 rounded bg-gray-800 hover:bg-red-900",
+                                onclick: {
+                                    let mut remove = remove.clone();
+                                    let name = user.username.clone();
+                                    move |_| remove(name.clone())
+                                },
+                                "Delete"
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Section listing the API keys with their usage and a form to create them
+#[component]
+fn ApiKeyManagement() -> Element {
+    let mut keys = use_resource(|| async move { list_api_keys().await.map_err(|e| e.to_string()) });
+    let mut name = use_signal(String::new);
+    let mut username = use_signal(String::new);
+    let mut requests_per_minute = use_signal(|| 30u32);
+    let mut tokens_per_day = use_signal(|| 100_000u64);
+    let mut created_key = use_signal(|| None::<String>);
+    let mut error = use_signal(|| None::<String>);
+
+    let create = move |event: FormEvent| {
+        event.prevent_default();
+        spawn(async move {
+            match create_api_key(name(), username(), requests_per_minute(), tokens_per_day()).await {
+                Ok(new_key) => {
+                    name.set(String::new());
+                    created_key.set(Some(new_key.key));
+                    error.set(None);
+                    keys.restart();
+                }
+                Err(e) => error.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    let revoke = move |id: String| {
+        spawn(async move {
+            match revoke_api_key(id).await {
+                Ok(()) => keys.restart(),
+                Err(e) => error.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    rsx! {
+        section {
+            class: "flex flex-col gap-3",
+            div {
+                class: "flex justify-between items-center",
+                h2 { class: "text-lg font-semibold", "API keys" }
+                button {
+                    class: "px-3 py-1 rounded-lg bg-gray-700 hover:bg-gray-600 text-sm",
+                    onclick: move |_| keys.restart(),
+                    "Refresh usage"
+                }
+            }
+            form {
+                class: "flex flex-wrap gap-2 items-center text-sm",
+                onsubmit: create,
+                input {
+                    class: "px-3 py-1 rounded-lg bg-gray-800 focus:outline-none",
+                    placeholder: "Name",
+                    value: "{name}",
+                    oninput: move |event| name.set(event.value()),
+                }
+                input {
+                    class: "px-3 py-1 rounded-lg bg-gray-800 focus:outline-none",
+                    placeholder: "Username",
+                    value: "{username}",
+                    oninput: move |event| username.set(event.value()),
+                }
+                label {
+                    class: "flex gap-1 items-center",
+                    "Requests/min"
+                    input {
+                        class: "w-20 px-2 py-1 rounded-lg bg-gray-800 focus:outline-none",
+                        r#type: "number",
+                        min: "0",
+                        value: "{requests_per_minute}",
+                        oninput: move |event| requests_per_minute.set(event.value().parse().unwrap_or(0)),
+                    }
+                }
+                label {
+                    class: "flex gap-1 items-center",
+                    "Tokens/day"
+                    input {
+                        class: "w-28 px-2 py-1 rounded-lg bg-gray-800 focus:outline-none",
+                        r#type: "number",
+                        min: "0",
+                        value: "{tokens_per_day}",
+                        oninput: move |event| tokens_per_day.set(event.value().parse().unwrap_or(0)),
+                    }
+                }
+                button {
+                    class: "px-3 py-1 rounded-lg bg-gray-700 hover:bg-gray-600",
+                    r#type: "submit",
+                    "Create key"
+                }
+            }
+            p { class: "text-xs text-gray-500", "Limits of 0 mean no limit" }
+            if let Some(key) = created_key() {
+                div {
+                    class: "p-3 rounded-lg bg-gray-800 text-sm",
+                    p { "Copy the new key now, it will not be shown again:" }
+                    code { class: "block mt-1 break-all text-green-400", "{key}" }
+                }
+            }
+            if let Some(e) = error() {
+                p { class: "text-sm text-red-400", "{e}" }
+            }
+            { match &*keys.read() {
+                None => rsx! { p { class: "text-gray-500", "Loading API keys..." } },
+                Some(Err(e)) => rsx! { p { class: "text-red-400", "{e}" } },
+                Some(Ok(keys)) if keys.is_empty() => rsx! { p { class: "text-gray-500", "No API keys" } },
+                Some(Ok(keys)) => render_api_key_table(keys, revoke),
+            } }
+        }
+    }
+}
+
+/// Render the API keys with their quotas, usage and a revoke button each
+fn render_api_key_table(keys: &[ApiKeyInfo], revoke: impl FnMut(String) + Clone + 'static) -> Element {
+    let limit = |value: u64| if value == 0 { "∞".to_string() } else { value.to_string() };
+
+    rsx! {
+        table {
+            class: "w-full text-sm text-left",
+            thead {
+                tr {
+                    class: "text-gray-500",
+                    th { class: "py-1", "Name" }
+                    th { class: "py-1", "User" }
+                    th { class: "py-1", "Key" }
+                    th { class: "py-1", "Requests (last minute)" }
+                    th { class: "py-1", "Tokens today" }
+                    th { class: "py-1", "Total requests" }
+                    th { class: "py-1", "Total tokens" }
+                    th { class: "py-1" }
+                }
+            }
+            tbody {
+                for api_key in keys.iter() {
+                    tr {
+                        key: "{api_key.id}",
+                        class: "border-t border-gray-800",
+                        td { class: "py-1", "{api_key.name}" }
+                        td { class: "py-1", "{api_key.username}" }
+                        td { class: "py-1 font-mono", "{api_key.prefix}…" }
+                        td { class: "py-1", "{api_key.requests_last_minute} / {limit(api_key.requests_per_minute as u64)}" }
+                        td { class: "py-1", "{api_key.tokens_today} / {limit(api_key.tokens_per_day)}" }
+                        td { class: "py-1", "{api_key.total_requests}" }
+                        td { class: "py-1", "{api_key.total_tokens}" }
+                        td {
+                            class: "py-1 text-right",
+                            button {
+                                class: "px-2 rounded bg-gray-800 hover:bg-red-900",
+                                onclick: {
+                                    let mut revoke = revoke.clone();
+                                    let id = api_key.id.clone();
+                                    move |_| revoke(id.clone())
+                                },
+                                "Revoke"
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
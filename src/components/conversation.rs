@@ -5,12 +5,22 @@
 
 use crate::components::Message;
 use crate::model::chat::{ChatMessage, ChatRole};
-use crate::server_functions::server_functions::{get_response, reset_chat, search_context, init_llm_model, init_embedding_model, init_db};
+use crate::model::document::{with_context, SearchOptions};
+use crate::model::status::InitProgress;
+use crate::model::tool::ToolStep;
+use crate::server_functions::server_functions::{auto_search_context, check_grounding, get_response, get_search_defaults, reset_chat, run_tools, search_context, init_progress};
 use dioxus::html::input_data::keyboard_types::Key;
+use dioxus::logger::tracing::{debug, error, warn};
 use dioxus::prelude::*;
 use futures::StreamExt;
 use wasm_bindgen::prelude::*;
 
+/// Number of previous messages used to rewrite follow-up questions
+const HISTORY_MESSAGES: usize = 6;
+
+/// Number of paraphrased queries generated when multi-query retrieval is enabled
+const MULTI_QUERY_PARAPHRASES: usize = 2;
+
 // Structure to keep application state organized
 #[derive(Clone)]
 struct ConversationState {
@@ -21,6 +31,11 @@ struct ConversationState {
     is_database_loading: bool,
     cancel_token: bool,
     use_context: bool,
+    auto_context: bool,
+    use_tools: bool,
+    verify_answers: bool,
+    search_options: SearchOptions,
+    init_progress: InitProgress,
 }
 
 /// Main conversation component that provides the chat interface
@@ -35,11 +50,21 @@ pub fn Conversation() -> Element {
         is_database_loading: true,
         cancel_token: false,
         use_context: false,
+        auto_context: false,
+        use_tools: false,
+        verify_answers: false,
+        search_options: SearchOptions::default(),
+        init_progress: InitProgress::default(),
+    });
+
+    // Follow the server initialization when the component loads
+    use_effect(move || {
+        watch_initialization(state.clone());
     });
 
-    // Initialize all systems when component loads
+    // Start from the search options set in the runtime settings
     use_effect(move || {
-        initialize_systems(state.clone());
+        load_search_defaults(state.clone());
     });
 
     // Auto-scroll when message history changes
@@ -57,7 +82,7 @@ pub fn Conversation() -> Element {
 
             // Loading screen during initialization
             if state.read().is_model_loading || state.read().is_database_loading {
-                { render_loading_screen() }
+                { render_loading_screen(&state.read().init_progress) }
             }
 
             // Message container with scrolling
@@ -69,91 +94,87 @@ pub fn Conversation() -> Element {
     }
 }
 
-/// Initialize all systems: LLM, database and embeddings model
-fn initialize_systems(state: Signal<ConversationState>) {
-    // Initialize LLM model
-    initialize_language_model(state.clone());
-    
-    // Initialize database
-    initialize_database(state.clone());
-    
-    // Initialize embeddings model
-    initialize_embedding_model();
-}
-
-/// Initialize the language model
-fn initialize_language_model(mut state: Signal<ConversationState>) {
+/// Follow the server initialization until the models and the knowledge base are ready
+///
+/// The server initializes itself when it starts; this only streams its progress
+/// into the state so the loading screen can display it.
+fn watch_initialization(mut state: Signal<ConversationState>) {
     spawn(async move {
-        match init_llm_model().await {
-            Ok(_) => {
-                let mut current_state = state.read().clone();
-                current_state.is_model_loading = false;
-                state.set(current_state);
-                println!("Model initialized successfully");
-            }
-            Err(e) => {
-                let mut current_state = state.read().clone();
-                current_state.is_model_loading = false;
-                current_state.message_history.push(ChatMessage {
-                    role: ChatRole::Assistant,
-                    content: format!("Error initializing model: {}", e),
-                });
-                state.set(current_state);
-                println!("Error initializing model: {}", e);
-            }
+        let result = match init_progress().await {
+            Ok(stream) => follow_progress(state, stream.into_inner()).await,
+            Err(e) => Err(e.to_string()),
+        };
+
+        let mut current_state = state.read().clone();
+        current_state.is_model_loading = false;
+        current_state.is_database_loading = false;
+        if let Err(e) = result {
+            current_state.message_history.push(ChatMessage::new(
+                ChatRole::Assistant,
+                format!("Error initializing server: {}", e),
+            ));
+            error!("Error initializing server: {}", e);
+        } else {
+            debug!("Server initialized");
         }
+        state.set(current_state);
     });
 }
 
-/// Initialize the database
-fn initialize_database(mut state: Signal<ConversationState>) {
+/// Load the default search options once, keeping the toggles already changed
+///
+/// The number of passages is a runtime setting admins may change; the options
+/// in the state are used as they are for every message afterwards.
+fn load_search_defaults(mut state: Signal<ConversationState>) {
     spawn(async move {
-        println!("Initializing database...");
-        match init_db().await {
-            Ok(_) => {
-                let mut current_state = state.read().clone();
-                current_state.is_database_loading = false;
-                state.set(current_state);
-                println!("Database initialized successfully");
-            }
-            Err(e) => {
-                let mut current_state = state.read().clone();
-                current_state.is_database_loading = false;
-                current_state.message_history.push(ChatMessage {
-                    role: ChatRole::Assistant,
-                    content: format!("Error initializing database: {}", e),
-                });
-                state.set(current_state);
-                println!("Error initializing database: {}", e);
+        match get_search_defaults().await {
+            Ok(defaults) => {
+                let mut current_state = state.write();
+                let current = current_state.search_options.clone();
+                current_state.search_options = SearchOptions {
+                    rerank: current.rerank,
+                    paraphrases: current.paraphrases,
+                    ..defaults
+                };
             }
+            Err(e) => warn!("Error loading search defaults: {}", e),
         }
     });
 }
 
-/// Initialize the embeddings model
-fn initialize_embedding_model() {
-    spawn(async move {
-        match init_embedding_model().await {
-            Ok(_) => println!("Embeddings model initialized successfully"),
-            Err(e) => println!("Error initializing embeddings model: {}", e),
+/// Read the progress updates, one JSON document per line, until initialization ends
+///
+/// Returns Ok(()) once the server is ready or the error that made initialization fail
+async fn follow_progress(
+    mut state: Signal<ConversationState>,
+    mut stream: impl futures::Stream<Item = Result<String, ServerFnError>> + Unpin,
+) -> Result<(), String> {
+    let mut buffer = String::new();
+    while let Some(chunk) = stream.next().await {
+        buffer.push_str(&chunk.map_err(|e| e.to_string())?);
+        while let Some(end) = buffer.find('\n') {
+            let line: String = buffer.drain(..=end).collect();
+            let progress: InitProgress = serde_json::from_str(line.trim()).map_err(|e| e.to_string())?;
+            if let Some(error) = &progress.error {
+                return Err(error.clone());
+            }
+            let ready = progress.ready;
+            state.write().init_progress = progress;
+            if ready {
+                return Ok(());
+            }
         }
-    });
+    }
+    Err("The server closed the progress stream".to_string())
 }
 
 /// Handle message sending and response reception
 async fn handle_message_send(mut state: Signal<ConversationState>) {
     let current_state = state.read().clone();
     
-    // Debug output to help diagnose issues
-    println!("handle_message_send called with: is_model_answering={}, is_model_loading={}, is_database_loading={}, message_empty={}", 
-             current_state.is_model_answering, 
-             current_state.is_model_loading,
-             current_state.is_database_loading,
-             current_state.input_message.trim().is_empty());
-    
     // Case 1: If model is answering, cancel the generation
     if current_state.is_model_answering {
-        println!("Canceling current response");
+        debug!("Canceling current response");
         let mut new_state = current_state.clone();
         new_state.cancel_token = true;
         new_state.is_model_answering = false;
@@ -163,22 +184,19 @@ async fn handle_message_send(mut state: Signal<ConversationState>) {
     
     // Case 2: Check if we can proceed with sending a message
     if current_state.is_model_loading {
-        println!("Cannot send: Model is still loading");
+        debug!("Cannot send: model is still loading");
         return;
     }
     
     if current_state.is_database_loading {
-        println!("Cannot send: Database is still loading");
+        debug!("Cannot send: database is still loading");
         return;
     }
     
     if current_state.input_message.trim().is_empty() {
-        println!("Cannot send: Message is empty");
         return;
     }
 
-    println!("Sending message: {}", current_state.input_message);
-    
     // Case 3: Prepare message and update state
     let mut new_state = current_state.clone();
     new_state.cancel_token = false;
@@ -187,16 +205,10 @@ async fn handle_message_send(mut state: Signal<ConversationState>) {
     let user_message = current_state.input_message.trim().to_string();
     
     // Add user message to history
-    new_state.message_history.push(ChatMessage { 
-        role: ChatRole::User, 
-        content: user_message.clone() 
-    });
+    new_state.message_history.push(ChatMessage::new(ChatRole::User, user_message.clone()));
     
     // Create empty assistant message that will be filled with streaming response
-    new_state.message_history.push(ChatMessage { 
-        role: ChatRole::Assistant, 
-        content: String::new() 
-    });
+    new_state.message_history.push(ChatMessage::new(ChatRole::Assistant, String::new()));
     
     // Clear input field
     new_state.input_message = String::new();
@@ -210,37 +222,68 @@ async fn handle_message_send(mut state: Signal<ConversationState>) {
 
 /// Process model response asynchronously
 fn process_response(mut state: Signal<ConversationState>, mut user_message: String) {
-    println!("Starting response processing for message: {}", user_message);
-    
     spawn(async move {
         let use_context_enabled = state.read().use_context;
+        let auto_context_enabled = state.read().auto_context;
+        let use_tools_enabled = state.read().use_tools;
+        let verify_answers_enabled = state.read().verify_answers;
+        let search_options = state.read().search_options.clone();
+        let history = recent_history(&state.read().message_history);
         
-        // Get relevant context when enabled
-        if use_context_enabled {
-            println!("Context search enabled, looking for relevant information");
-            match search_context(user_message.clone()).await {
-                Ok(context) => {
-                    let context_string = format!("\n\n[Potentially useful context:\n{}]", context);
-                    user_message.push_str(&context_string);
-                    println!("Added context to message");
+        // Get relevant context when enabled, letting the model decide in automatic mode
+        // The results of all the searches made by the model are merged into top_k chunks
+        if use_context_enabled || auto_context_enabled {
+            let search = if auto_context_enabled {
+                auto_search_context(user_message.clone(), history, search_options).await
+            } else {
+                search_context(user_message.clone(), history, search_options).await
+            };
+            match search {
+                Ok(search) => {
+                    user_message = with_context(&user_message, &search.results);
+
+                    // Attach the queries, sources and search trace to the pending assistant message
+                    let mut current_state = state.read().clone();
+                    if let Some(last_message) = current_state.message_history.last_mut() {
+                        last_message.queries = search.queries;
+                        last_message.sources = search.results;
+                        last_message.tool_steps = search.steps;
+                        state.set(current_state);
+                    }
                 },
-                Err(e) => println!("Error searching for context: {:?}", e)
+                Err(e) => warn!("Error searching for context: {}", e)
+            }
+        }
+
+        // Let the model call tools and pass their results along with the message
+        if use_tools_enabled {
+            match run_tools(user_message.clone()).await {
+                Ok(steps) => {
+                    if !steps.is_empty() {
+                        user_message.push_str(&format!("\n\n[Tool results:\n{}]", build_tool_context(&steps)));
+                    }
+
+                    // Attach the tool calls to the pending assistant message, after any search trace
+                    let mut current_state = state.read().clone();
+                    if let Some(last_message) = current_state.message_history.last_mut() {
+                        last_message.tool_steps.extend(steps);
+                        state.set(current_state);
+                    }
+                },
+                Err(e) => warn!("Error running tools: {}", e)
             }
         }
 
         // Get and process response stream
-        println!("Requesting response from model");
         match get_response(user_message).await {
             Ok(response) => {
                 let mut stream = response.into_inner();
-                println!("Got response stream, processing chunks");
-                
                 while let Some(result) = stream.next().await {
                     match result {
                         Ok(chunk) => {
                             // Check if response was canceled
                             if state.read().cancel_token {
-                                println!("Response generation was canceled");
+                                debug!("Response generation was canceled");
                                 break;
                             }
                             
@@ -251,24 +294,80 @@ fn process_response(mut state: Signal<ConversationState>, mut user_message: Stri
                                 state.set(current_state);
                             }
                         },
-                        Err(e) => println!("Error in stream chunk: {:?}", e)
+                        Err(e) => warn!("Error in response stream: {}", e)
                     }
                 }
-                println!("Finished processing response stream");
             },
-            Err(e) => println!("Error getting response: {:?}", e)
+            Err(e) => error!("Error getting response: {}", e)
+        }
+
+        // Check the answer against the sources it was generated from
+        if verify_answers_enabled && !state.read().cancel_token {
+            verify_last_answer(state).await;
         }
 
         // Finalize response state
         let mut current_state = state.read().clone();
         current_state.is_model_answering = false;
         state.set(current_state);
-        println!("Response completed, reset answering state");
     });
 }
 
+/// Check the sentences of the last answer against its sources and attach the result
+///
+/// Answers generated without sources are not checked.
+async fn verify_last_answer(mut state: Signal<ConversationState>) {
+    let Some(last_message) = state.read().message_history.last().cloned() else {
+        return;
+    };
+    if last_message.sources.is_empty() || last_message.content.is_empty() {
+        return;
+    }
+
+    debug!("Verifying the answer against {} sources", last_message.sources.len());
+    match check_grounding(last_message.content, last_message.sources, true).await {
+        Ok(grounding) => {
+            let mut current_state = state.read().clone();
+            if let Some(last_message) = current_state.message_history.last_mut() {
+                last_message.grounding = grounding;
+                state.set(current_state);
+            }
+        },
+        Err(e) => warn!("Error verifying answer: {}", e)
+    }
+}
+
+/// Collect the conversation preceding the message being answered
+///
+/// The history ends with the new user message and the pending assistant message,
+/// which are skipped. Only the text of the last `HISTORY_MESSAGES` messages is
+/// kept since it is sent to the server to rewrite follow-up questions.
+fn recent_history(message_history: &[ChatMessage]) -> Vec<ChatMessage> {
+    let previous = &message_history[..message_history.len().saturating_sub(2)];
+    previous[previous.len().saturating_sub(HISTORY_MESSAGES)..]
+        .iter()
+        .map(|message| ChatMessage::new(message.role.clone(), message.content.clone()))
+        .collect()
+}
+
+/// Format the tool calls and their outputs as the block sent to the model
+fn build_tool_context(steps: &[ToolStep]) -> String {
+    steps.iter()
+        .map(|step| {
+            format!(
+                "Tool: {}\nArguments: {}\n{}: {}\n",
+                step.tool,
+                step.arguments,
+                if step.is_error { "Error" } else { "Output" },
+                step.output
+            )
+        }).collect::<Vec<_>>().join("\n")
+}
+
 /// Render the loading screen
-fn render_loading_screen() -> Element {
+fn render_loading_screen(progress: &InitProgress) -> Element {
+    let percent = progress.fraction() * 100.0;
+
     rsx! {
         div {
             class: "w-screen h-screen flex flex-col items-center justify-center",
@@ -284,7 +383,18 @@ fn render_loading_screen() -> Element {
             }
             p {
                 class: "mt-12 text-gray-600 font-semibold",
-                "Initializing model..."
+                "{progress.description()}"
+            }
+            div {
+                class: "mt-4 w-80 h-2 bg-gray-800 rounded-full overflow-hidden",
+                div {
+                    class: "h-full bg-gray-500 transition-all",
+                    style: "width: {percent:.0}%",
+                }
+            }
+            p {
+                class: "mt-2 text-xs text-gray-600",
+                "{percent:.0}%"
             }
         }
     }
@@ -312,16 +422,38 @@ fn render_input_area(state: &Signal<ConversationState>) -> Element {
     rsx! {
         div {
             id: "input",
-            class: "w-full flex gap-4 p-4 justify-center items-end relative",
+            class: "w-full flex flex-col gap-2 p-4",
 
-            // Textarea for message input
-            { render_input_textarea(state) }
-            
-            // Toggle switch for context
-            { render_context_toggle(state) }
+            // Toolbar with the switches for context search, reranking, multi-query retrieval, tools, automatic search and verification
+            div {
+                class: "flex flex-wrap gap-4 items-center",
+                { render_toggle(state, "Context", |s| s.use_context, |s, value| s.use_context = value) }
+                { render_toggle(
+                    state,
+                    "Rerank",
+                    |s| s.search_options.rerank,
+                    |s, value| s.search_options.rerank = value,
+                ) }
+                { render_toggle(
+                    state,
+                    "Multi-query",
+                    |s| s.search_options.paraphrases > 0,
+                    |s, value| s.search_options.paraphrases = if value { MULTI_QUERY_PARAPHRASES } else { 0 },
+                ) }
+                { render_toggle(state, "Tools", |s| s.use_tools, |s, value| s.use_tools = value) }
+                { render_toggle(state, "Auto search", |s| s.auto_context, |s, value| s.auto_context = value) }
+                { render_toggle(state, "Verify", |s| s.verify_answers, |s, value| s.verify_answers = value) }
+            }
+
+            div {
+                class: "flex gap-4 justify-center items-end",
 
-            // Send/Cancel button with dynamic state
-            { render_send_button(state) }
+                // Textarea for message input
+                { render_input_textarea(state) }
+
+                // Send/Cancel button with dynamic state
+                { render_send_button(state) }
+            }
 
             // Button to reset conversation
             { render_reset_button(state_clone) }
@@ -359,18 +491,13 @@ fn render_input_textarea(state: &Signal<ConversationState>) -> Element {
                 state_clone.set(new_state);
             },
             onkeydown: move |event| {
-                println!("Key pressed: {:?}, shift: {}", event.key(), event.modifiers().shift());
-                
                 if event.key() == Key::Enter && !event.modifiers().shift() {
                     event.prevent_default();
                     let current = state_clone.read().clone(); // Clonar para pasar a spawn
                     
                     if !current.input_message.trim().is_empty() {
-                        println!("Enter key pressed, calling handle_message_send");
                         // Envolver la llamada a la función async en spawn
                         spawn(handle_message_send(state_clone.clone()));
-                    } else {
-                        println!("Enter pressed but message is empty");
                     }
                 }
             }
@@ -378,29 +505,34 @@ fn render_input_textarea(state: &Signal<ConversationState>) -> Element {
     }
 }
 
-/// Render the context toggle switch
-fn render_context_toggle(state: &Signal<ConversationState>) -> Element {
+/// Render a toggle switch bound to a boolean field of the conversation state
+///
+/// `get` reads the bound field and `set` writes it back when the switch changes.
+fn render_toggle(
+    state: &Signal<ConversationState>,
+    label: &'static str,
+    get: fn(&ConversationState) -> bool,
+    set: fn(&mut ConversationState, bool),
+) -> Element {
     let current_state = state.read();
     let is_disabled = current_state.is_model_loading || 
                       current_state.is_database_loading || 
                       current_state.is_model_answering;
     let opacity_class = if is_disabled { "" } else { " hover:opacity-100" };
+    let is_checked = get(&current_state);
     let mut state_clone = state.clone();
     
     rsx! {
         label {
-            class: format!(
-                "absolute left-5 bottom-5 inline-flex items-center cursor-pointer opacity-70{}", 
-                opacity_class
-            ),
+            class: format!("inline-flex items-center cursor-pointer opacity-70{}", opacity_class),
             input {
                 disabled: is_disabled,
                 r#type: "checkbox",
                 class: "sr-only peer",
-                checked: "{current_state.use_context}",
+                checked: "{is_checked}",
                 onchange: move |e| {
                     let mut new_state = state_clone.read().clone();
-                    new_state.use_context = e.value().parse::<bool>().unwrap_or(false);
+                    set(&mut new_state, e.value().parse::<bool>().unwrap_or(false));
                     state_clone.set(new_state);
                 },
             }
@@ -420,7 +552,7 @@ fn render_context_toggle(state: &Signal<ConversationState>) -> Element {
             }
             span {
                 class: "ml-1 text-[10px] text-gray-400 bg-transparent px-1 py-0 rounded select-none pointer-events-none",
-                "Context"
+                "{label}"
             }
         }
     }
@@ -458,16 +590,10 @@ fn render_send_button(state: &Signal<ConversationState>) -> Element {
                 let is_loading_at_click = current_state_at_click.is_model_loading || current_state_at_click.is_database_loading;
                 let is_empty_at_click = current_state_at_click.input_message.trim().is_empty();
 
-                println!("Send button clicked. (Live state) is_answering={}, is_loading={}, is_empty={}", 
-                         is_answering_at_click, is_loading_at_click, is_empty_at_click);
-                
                 // Usar el estado actual para la condición
                 if is_answering_at_click || (!is_loading_at_click && !is_empty_at_click) {
-                    println!("Conditions met (live state), calling handle_message_send");
                     // Envolver la llamada a la función async en spawn
                     spawn(handle_message_send(state_for_handler.clone()));
-                } else {
-                    println!("Button clicked but conditions not met for sending (live state)");
                 }
             },
             "{button_text}"
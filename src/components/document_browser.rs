@@ -0,0 +1,212 @@
+//! Document Browser Components
+//!
+//! These components let users inspect the knowledge base without involving the
+//! language model. The browser page lists the ingested documents with their
+//! source file, chunk count and ingestion time, and runs ad-hoc searches showing
+//! the retrieval scores of every result. The document page shows the chunks a
+//! single document was split into.
+
+use crate::components::SourceItem;
+use crate::model::document::{DocumentDetails, DocumentInfo, SearchMode, SearchOptions, SimpleDocumentResult};
+use crate::server_functions::server_functions::{get_document, list_documents, search_documents};
+use crate::Route;
+use dioxus::html::input_data::keyboard_types::Key;
+use dioxus::prelude::*;
+
+/// Number of results of an ad-hoc search
+const SEARCH_RESULTS: usize = 5;
+
+/// Page listing the documents of the knowledge base with an ad-hoc search form
+#[component]
+pub fn DocumentBrowser() -> Element {
+    let documents = use_resource(|| async move { list_documents().await.map_err(|e| e.to_string()) });
+
+    rsx! {
+        div {
+            class: "w-full max-w-[80rem] mx-auto h-screen overflow-y-auto flex flex-col gap-6 p-4 pt-16 text-gray-300",
+            h1 { class: "text-xl font-semibold", "Knowledge base" }
+            SearchForm {}
+            { match &*documents.read() {
+                None => rsx! { p { class: "text-gray-500", "Loading documents..." } },
+                Some(Err(e)) => rsx! { p { class: "text-red-400", "{e}" } },
+                Some(Ok(documents)) => render_document_table(documents),
+            } }
+        }
+    }
+}
+
+/// Form running searches against the document table and listing the scored results
+#[component]
+fn SearchForm() -> Element {
+    let mut query = use_signal(String::new);
+    let mut mode = use_signal(SearchMode::default);
+    let mut results = use_signal(|| None::<Result<Vec<SimpleDocumentResult>, String>>);
+    let mut is_searching = use_signal(|| false);
+
+    let run_search = move || {
+        let query = query().trim().to_string();
+        if query.is_empty() {
+            return;
+        }
+        let options = SearchOptions {
+            mode: mode(),
+            top_k: SEARCH_RESULTS,
+            ..SearchOptions::default()
+        };
+        spawn(async move {
+            is_searching.set(true);
+            let found = search_documents(query, options).await.map_err(|e| e.to_string());
+            results.set(Some(found));
+            is_searching.set(false);
+        });
+    };
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-2",
+            div {
+                class: "flex gap-2",
+                input {
+                    class: "flex-grow px-3 py-1 rounded-lg bg-gray-800 text-gray-200",
+                    placeholder: "Search the knowledge base...",
+                    value: "{query}",
+                    oninput: move |event| query.set(event.value()),
+                    onkeydown: move |event| {
+                        if event.key() == Key::Enter {
+                            run_search();
+                        }
+                    },
+                }
+                select {
+                    class: "px-2 py-1 rounded-lg bg-gray-800 text-gray-200",
+                    onchange: move |event| mode.set(parse_mode(&event.value())),
+                    for option_mode in [SearchMode::Vector, SearchMode::Keyword, SearchMode::Hybrid] {
+                        option {
+                            value: "{option_mode:?}",
+                            selected: mode() == option_mode,
+                            "{option_mode:?}"
+                        }
+                    }
+                }
+                button {
+                    class: "px-3 py-1 rounded-lg bg-gray-700 hover:bg-gray-600 disabled:opacity-50",
+                    disabled: is_searching(),
+                    onclick: move |_| run_search(),
+                    if is_searching() { "Searching..." } else { "Search" }
+                }
+            }
+            { match &*results.read() {
+                None => rsx! {},
+                Some(Err(e)) => rsx! { p { class: "text-red-400", "{e}" } },
+                Some(Ok(found)) if found.is_empty() => rsx! { p { class: "text-gray-500", "No results" } },
+                Some(Ok(found)) => rsx! {
+                    div {
+                        class: "flex flex-col gap-2 text-xs text-gray-400",
+                        for result in found.iter() {
+                            SourceItem { source: result.clone() }
+                        }
+                    }
+                },
+            } }
+        }
+    }
+}
+
+/// Page showing a single document and the chunks it was split into
+///
+/// The identifier is read through a signal so the document is reloaded when the
+/// route changes to another document while the page stays mounted.
+#[component]
+pub fn DocumentView(id: ReadOnlySignal<usize>) -> Element {
+    let document = use_resource(move || {
+        let id = id();
+        async move { get_document(id).await.map_err(|e| e.to_string()) }
+    });
+
+    rsx! {
+        div {
+            class: "w-full max-w-[80rem] mx-auto h-screen overflow-y-auto flex flex-col gap-4 p-4 pt-16 text-gray-300",
+            Link {
+                class: "text-sm text-blue-400 hover:text-blue-300",
+                to: Route::DocumentBrowser {},
+                "← All documents"
+            }
+            { match &*document.read() {
+                None => rsx! { p { class: "text-gray-500", "Loading document..." } },
+                Some(Err(e)) => rsx! { p { class: "text-red-400", "{e}" } },
+                Some(Ok(details)) => render_document(details),
+            } }
+        }
+    }
+}
+
+/// Render the table of documents
+fn render_document_table(documents: &[DocumentInfo]) -> Element {
+    if documents.is_empty() {
+        return rsx! { p { class: "text-gray-500", "No documents have been ingested" } };
+    }
+
+    rsx! {
+        table {
+            class: "w-full text-sm text-left",
+            thead {
+                tr {
+                    class: "text-gray-500 border-b border-gray-700",
+                    th { class: "py-2", "Title" }
+                    th { "Source" }
+                    th { "Collection" }
+                    th { "Chunks" }
+                    th { "Ingested" }
+                }
+            }
+            tbody {
+                for document in documents.iter() {
+                    tr {
+                        class: "border-b border-gray-800",
+                        td {
+                            class: "py-2",
+                            Link {
+                                class: "text-blue-400 hover:text-blue-300",
+                                to: Route::DocumentView { id: document.id },
+                                "{document.title}"
+                            }
+                        }
+                        td { class: "font-mono text-xs text-gray-400", "{document.source}" }
+                        td { class: "text-gray-400", "{document.collection}" }
+                        td { class: "font-mono", "{document.chunk_count}" }
+                        td { class: "font-mono text-xs text-gray-400", "{document.ingested_at}" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Render a document with its metadata and chunks
+fn render_document(details: &DocumentDetails) -> Element {
+    let info = &details.info;
+
+    rsx! {
+        h1 { class: "text-xl font-semibold", "{info.title}" }
+        p {
+            class: "text-xs font-mono text-gray-500",
+            "{info.source} · {info.chunk_count} chunks · ingested {info.ingested_at}"
+        }
+        for (position, chunk) in details.chunks.iter().enumerate() {
+            div {
+                class: "flex flex-col gap-1 p-3 border border-gray-700 rounded-lg text-xs",
+                span { class: "font-mono text-gray-500", "Chunk {position + 1}" }
+                p { class: "whitespace-pre-wrap text-gray-400", "{chunk}" }
+            }
+        }
+    }
+}
+
+/// Parse the search mode selected in the form
+fn parse_mode(value: &str) -> SearchMode {
+    match value {
+        "Keyword" => SearchMode::Keyword,
+        "Hybrid" => SearchMode::Hybrid,
+        _ => SearchMode::Vector,
+    }
+}
@@ -0,0 +1,70 @@
+//! Login Component
+//!
+//! This component renders the login form shown to visitors without a session.
+//! On success the server sets the session cookie and the logged in user is
+//! stored in the shared user signal, which reveals the rest of the application.
+
+use crate::model::auth::UserInfo;
+use crate::server_functions::server_functions::login;
+use dioxus::prelude::*;
+
+/// Form logging in with a username and password
+#[component]
+pub fn LoginForm() -> Element {
+    let mut user = use_context::<Signal<Option<UserInfo>>>();
+    let mut username = use_signal(String::new);
+    let mut password = use_signal(String::new);
+    let mut error = use_signal(|| None::<String>);
+    let mut is_submitting = use_signal(|| false);
+
+    let submit = move |event: FormEvent| {
+        event.prevent_default();
+        spawn(async move {
+            is_submitting.set(true);
+            match login(username(), password()).await {
+                Ok(logged_in) => {
+                    password.set(String::new());
+                    error.set(None);
+                    user.set(Some(logged_in));
+                }
+                Err(e) => error.set(Some(e.to_string())),
+            }
+            is_submitting.set(false);
+        });
+    };
+
+    rsx! {
+        div {
+            class: "w-screen h-screen flex items-center justify-center",
+            form {
+                class: "w-80 flex flex-col gap-3 p-6 rounded-xl bg-gray-800 text-gray-300",
+                onsubmit: submit,
+                h1 { class: "text-xl font-semibold text-center", "🤖 Rusty Bot" }
+                input {
+                    class: "px-3 py-2 rounded-lg bg-gray-700 focus:outline-none",
+                    placeholder: "Username",
+                    autocomplete: "username",
+                    value: "{username}",
+                    oninput: move |event| username.set(event.value()),
+                }
+                input {
+                    class: "px-3 py-2 rounded-lg bg-gray-700 focus:outline-none",
+                    r#type: "password",
+                    placeholder: "Password",
+                    autocomplete: "current-password",
+                    value: "{password}",
+                    oninput: move |event| password.set(event.value()),
+                }
+                if let Some(e) = error() {
+                    p { class: "text-sm text-red-400", "{e}" }
+                }
+                button {
+                    class: "px-3 py-2 rounded-lg bg-gray-600 hover:bg-gray-500 disabled:opacity-50",
+                    r#type: "submit",
+                    disabled: is_submitting() || username().trim().is_empty() || password().is_empty(),
+                    if is_submitting() { "Logging in..." } else { "Log in" }
+                }
+            }
+        }
+    }
+}
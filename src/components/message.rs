@@ -6,7 +6,9 @@
 
 use comrak::{markdown_to_html_with_plugins, ExtensionOptions, Plugins, RenderOptions, RenderPlugins};
 use comrak::plugins::syntect::SyntectAdapterBuilder;
+use crate::components::{SourcePanel, ToolSteps};
 use crate::model::chat::{ChatMessage, ChatRole};
+use crate::model::grounding::ClaimCheck;
 use dioxus::prelude::*;
 
 /// Message component for rendering individual chat messages
@@ -25,7 +27,7 @@ pub fn Message(msg: ReadOnlySignal<ChatMessage>) -> Element {
     // Process markdown content to HTML with syntax highlighting
     let content = use_memo(move || {
         let msg = msg();
-        let msg_content = &msg.content;
+        let msg_content = &annotate_unsupported(&msg.content, &msg.grounding);
         
         // Configure syntax highlighter with dark theme
         let syntec_adapter = SyntectAdapterBuilder::new()
@@ -88,6 +90,11 @@ pub fn Message(msg: ReadOnlySignal<ChatMessage>) -> Element {
                 "text-gray-400"
             },
 
+            // Show the tools called before answering
+            if !msg.read().tool_steps.is_empty() {
+                ToolSteps { steps: msg.read().tool_steps.clone() }
+            }
+
             // Show loading animation for empty assistant messages (waiting for response)
             if assistant_placeholder() {
                 div {
@@ -111,6 +118,75 @@ pub fn Message(msg: ReadOnlySignal<ChatMessage>) -> Element {
                     dangerous_inner_html: content
                 }
             }
+
+            // Warn about the sentences the sources do not support
+            if msg.read().grounding.iter().any(|check| !check.supported) {
+                { render_grounding_warning(&msg.read().grounding) }
+            }
+
+            // Show the search queries and context sources used to generate the message
+            if !msg.read().sources.is_empty() || !msg.read().queries.is_empty() {
+                SourcePanel {
+                    queries: msg.read().queries.clone(),
+                    sources: msg.read().sources.clone(),
+                }
+            }
         }
     }
 }
+
+/// Marker appended to the sentences the sources do not support
+const UNSUPPORTED_MARKER: &str = " ⚠️";
+
+/// Append a warning marker after every unsupported sentence of the content
+///
+/// The checks refer to byte ranges of the content, so markers are inserted
+/// from the end of the content to keep the earlier offsets valid.
+fn annotate_unsupported(content: &str, grounding: &[ClaimCheck]) -> String {
+    let mut annotated = content.to_string();
+    for check in grounding.iter().rev().filter(|check| !check.supported) {
+        if content.is_char_boundary(check.end) {
+            annotated.insert_str(check.end, UNSUPPORTED_MARKER);
+        }
+    }
+    annotated
+}
+
+/// Render the list of sentences the sources do not support
+fn render_grounding_warning(grounding: &[ClaimCheck]) -> Element {
+    let unsupported: Vec<&ClaimCheck> = grounding.iter().filter(|check| !check.supported).collect();
+
+    rsx! {
+        details {
+            class: "mt-3 text-xs text-yellow-400 border border-yellow-700 rounded-lg",
+            summary {
+                class: "cursor-pointer select-none px-3 py-2",
+                "⚠️ {unsupported.len()} of {grounding.len()} sentences may not be supported by the sources"
+            }
+            ul {
+                class: "flex flex-col gap-1 px-3 pb-3 list-disc list-inside",
+                for check in unsupported {
+                    li {
+                        "{check.sentence}"
+                        span {
+                            class: "ml-2 font-mono text-gray-500",
+                            { describe_check(check) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Describe why a sentence was reported as unsupported
+fn describe_check(check: &ClaimCheck) -> String {
+    let mut description = format!("similarity {:.2}", check.similarity);
+    if let Some(source) = &check.best_source {
+        description.push_str(&format!(" to {}", source));
+    }
+    if check.judged {
+        description.push_str(" · judged unsupported");
+    }
+    description
+}
@@ -2,3 +2,21 @@ pub mod conversation;
 pub use conversation::Conversation;
 pub mod message;
 pub use message::Message;
+pub mod source_panel;
+pub use source_panel::{SourceItem, SourcePanel};
+pub mod topic_overview;
+pub use topic_overview::TopicOverview;
+pub mod document_browser;
+pub use document_browser::{DocumentBrowser, DocumentView};
+pub mod tool_steps;
+pub use tool_steps::ToolSteps;
+pub mod structured_output;
+pub use structured_output::StructuredOutput;
+pub mod login;
+pub use login::LoginForm;
+pub mod admin;
+pub use admin::AdminPanel;
+pub mod usage;
+pub use usage::UsagePage;
+pub mod settings;
+pub use settings::SettingsPage;
@@ -0,0 +1,193 @@
+//! Settings Page Component
+//!
+//! This page lets admins change the runtime settings of the server: the persona
+//! of new conversations, the number of passages retrieved for a chat message
+//! and the token quotas of every role. Changes are saved on the server and
+//! apply without a restart. The page also shows the configuration the server
+//! was started with, which can only be changed in the configuration file or the
+//! environment.
+
+use crate::model::auth::UserInfo;
+use crate::model::settings::{RuntimeSettings, ServerConfig};
+use crate::model::usage::UsageQuota;
+use crate::server_functions::server_functions::{get_settings, set_runtime_settings};
+use dioxus::prelude::*;
+
+/// Page with the runtime settings and the configuration of the server
+#[component]
+pub fn SettingsPage() -> Element {
+    let user = use_context::<Signal<Option<UserInfo>>>();
+    let overview = use_resource(|| async move { get_settings().await.map_err(|e| e.to_string()) });
+    if !user().is_some_and(|user| user.is_admin()) {
+        return rsx! {
+            div {
+                class: "w-full max-w-[80rem] mx-auto p-4 pt-16 text-red-400",
+                "This page requires the admin role"
+            }
+        };
+    }
+
+    rsx! {
+        div {
+            class: "w-full max-w-[80rem] mx-auto h-screen overflow-y-auto flex flex-col gap-6 p-4 pt-16 text-gray-300",
+            h1 { class: "text-xl font-semibold", "Settings" }
+            { match &*overview.read() {
+                None => rsx! { p { class: "text-gray-500", "Loading settings..." } },
+                Some(Err(e)) => rsx! { p { class: "text-red-400", "{e}" } },
+                Some(Ok(overview)) => rsx! {
+                    RuntimeSettingsForm { initial: overview.runtime.clone() }
+                    { render_config(overview.config_file.as_deref(), &overview.config) }
+                },
+            } }
+        }
+    }
+}
+
+/// Form editing the runtime settings
+#[component]
+fn RuntimeSettingsForm(initial: RuntimeSettings) -> Element {
+    let mut settings = use_signal(|| initial);
+    let mut message = use_signal(|| None::<String>);
+
+    let save = move |event: FormEvent| {
+        event.prevent_default();
+        spawn(async move {
+            match set_runtime_settings(settings()).await {
+                Ok(()) => message.set(Some("Settings saved".to_string())),
+                Err(e) => message.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    let current = settings();
+    rsx! {
+        section {
+            class: "flex flex-col gap-3",
+            h2 { class: "text-lg font-semibold", "Runtime settings" }
+            form {
+                class: "flex flex-col gap-4 text-sm",
+                onsubmit: save,
+                label {
+                    class: "flex flex-col gap-1",
+                    span { class: "font-semibold", "Default persona" }
+                    span { class: "text-xs text-gray-500", "System prompt of new conversations; empty for none. Applies from the next conversation." }
+                    textarea {
+                        class: "h-24 px-3 py-2 rounded-lg bg-gray-800 focus:outline-none",
+                        value: "{current.persona}",
+                        oninput: move |event| settings.with_mut(|settings| settings.persona = event.value()),
+                    }
+                }
+                label {
+                    class: "flex gap-2 items-center",
+                    span { class: "font-semibold", "Retrieval top-k" }
+                    input {
+                        class: "w-20 px-2 py-1 rounded-lg bg-gray-800 focus:outline-none",
+                        r#type: "number",
+                        min: "1",
+                        value: "{current.retrieval_top_k}",
+                        oninput: move |event| settings.with_mut(|settings| {
+                            settings.retrieval_top_k = event.value().parse().unwrap_or(1);
+                        }),
+                    }
+                    span { class: "text-xs text-gray-500", "Passages added to a chat message when the context search is enabled" }
+                }
+                div {
+                    class: "flex flex-col gap-2",
+                    span { class: "font-semibold", "Quotas" }
+                    { render_quota_inputs("Users", current.quotas.user, move |quota| settings.with_mut(|settings| settings.quotas.user = quota)) }
+                    { render_quota_inputs("Admins", current.quotas.admin, move |quota| settings.with_mut(|settings| settings.quotas.admin = quota)) }
+                    span { class: "text-xs text-gray-500", "Prompt and generated tokens count against the quotas. Limits of 0 mean no limit." }
+                }
+                div {
+                    class: "flex gap-2 items-center",
+                    button {
+                        class: "px-3 py-1 rounded-lg bg-gray-700 hover:bg-gray-600",
+                        r#type: "submit",
+                        "Save settings"
+                    }
+                    if let Some(message) = message() {
+                        span { class: "text-gray-400", "{message}" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Render the daily and monthly limit inputs of a role
+fn render_quota_inputs(role: &str, quota: UsageQuota, mut on_change: impl FnMut(UsageQuota) + Clone + 'static) -> Element {
+    let mut on_month_change = on_change.clone();
+
+    rsx! {
+        div {
+            class: "flex flex-wrap gap-3 items-center",
+            span { class: "w-20", "{role}" }
+            label {
+                class: "flex gap-1 items-center",
+                "Tokens/day"
+                input {
+                    class: "w-28 px-2 py-1 rounded-lg bg-gray-800 focus:outline-none",
+                    r#type: "number",
+                    min: "0",
+                    value: "{quota.tokens_per_day}",
+                    oninput: move |event| on_change(UsageQuota {
+                        tokens_per_day: event.value().parse().unwrap_or(0),
+                        ..quota
+                    }),
+                }
+            }
+            label {
+                class: "flex gap-1 items-center",
+                "Tokens/month"
+                input {
+                    class: "w-32 px-2 py-1 rounded-lg bg-gray-800 focus:outline-none",
+                    r#type: "number",
+                    min: "0",
+                    value: "{quota.tokens_per_month}",
+                    oninput: move |event| on_month_change(UsageQuota {
+                        tokens_per_month: event.value().parse().unwrap_or(0),
+                        ..quota
+                    }),
+                }
+            }
+        }
+    }
+}
+
+/// Render the configuration the server was started with
+fn render_config(config_file: Option<&str>, config: &ServerConfig) -> Element {
+    let source = config_file.map_or("defaults and environment".to_string(), |file| file.to_string());
+    let rows = [
+        ("Document database", format!("{} ({}/{})", config.database.path, config.database.namespace, config.database.database)),
+        ("Application database", format!("{} ({}/{})", config.app_database.path, config.app_database.namespace, config.app_database.database)),
+        ("Context folder", config.knowledge_base.context_folder.clone()),
+        ("Backup folder", config.knowledge_base.backup_folder.clone()),
+        ("Import folder", config.knowledge_base.import_folder.clone()),
+        ("Ingestion", format!("{} chunks per batch, {} at a time", config.ingestion.batch_size, config.ingestion.concurrency)),
+        ("Language model", config.model.source.id().to_string()),
+        ("Embedding model", config.model.embedding.id().to_string()),
+        ("Temperature", config.sampling.temperature.to_string()),
+        ("Top-p", config.sampling.top_p.to_string()),
+        ("Maximum response length", format!("{} tokens", config.sampling.max_length)),
+    ];
+
+    rsx! {
+        section {
+            class: "flex flex-col gap-3",
+            h2 { class: "text-lg font-semibold", "Configuration" }
+            p { class: "text-xs text-gray-500", "Read from {source}. Changes require a restart." }
+            table {
+                class: "w-full text-sm text-left",
+                tbody {
+                    for (name, value) in rows {
+                        tr {
+                            class: "border-t border-gray-800",
+                            td { class: "py-1 text-gray-500", "{name}" }
+                            td { class: "py-1 font-mono", "{value}" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
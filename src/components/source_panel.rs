@@ -0,0 +1,132 @@
+//! Source Panel Component
+//!
+//! This component renders the document chunks that were injected as context for
+//! an assistant message. It is collapsed by default and shows the standalone
+//! queries the search was performed with and, for each source, the document
+//! title, the chunk text and the retrieval scores used to rank it. Each source
+//! offers a "Find similar" action listing the other documents closest to it.
+
+use crate::model::document::SimpleDocumentResult;
+use crate::server_functions::server_functions::find_similar_documents;
+use dioxus::prelude::*;
+
+/// Number of similar documents listed for a source
+const SIMILAR_DOCUMENTS: usize = 3;
+
+/// Collapsible panel listing the search queries and context sources of a message
+#[component]
+pub fn SourcePanel(queries: Vec<String>, sources: Vec<SimpleDocumentResult>) -> Element {
+    rsx! {
+        details {
+            class: "mt-3 text-xs text-gray-400 border border-gray-700 rounded-lg",
+            summary {
+                class: "cursor-pointer select-none px-3 py-2",
+                "Sources ({sources.len()})"
+            }
+            div {
+                class: "flex flex-col gap-2 px-3 pb-3",
+                if !queries.is_empty() {
+                    div {
+                        class: "flex flex-col gap-1",
+                        span { class: "font-semibold text-gray-300", "Search queries" }
+                        for query in queries.iter() {
+                            span { class: "font-mono text-gray-400", "“{query}”" }
+                        }
+                    }
+                }
+                for source in sources.iter() {
+                    SourceItem { source: source.clone() }
+                }
+            }
+        }
+    }
+}
+
+/// A single source with its title, scores, chunk text and similar documents
+#[component]
+pub fn SourceItem(source: SimpleDocumentResult) -> Element {
+    let mut similar = use_signal(|| None::<Result<Vec<SimpleDocumentResult>, String>>);
+    let mut is_searching = use_signal(|| false);
+    let source_for_search = source.clone();
+
+    rsx! {
+        div {
+            class: "border-t border-gray-700 pt-2",
+            div {
+                class: "flex justify-between gap-4",
+                span { class: "font-semibold text-gray-300", "{source.title}" }
+                span { class: "font-mono", "score {source.score:.4}" }
+            }
+            p {
+                class: "font-mono text-[10px] text-gray-500",
+                { format_signals(&source) }
+            }
+            p {
+                class: "whitespace-pre-wrap text-gray-400 max-h-40 overflow-y-auto",
+                "{source.body}"
+            }
+            button {
+                class: "mt-1 text-blue-400 hover:text-blue-300 disabled:opacity-50",
+                disabled: is_searching(),
+                onclick: move |_| {
+                    let source = source_for_search.clone();
+                    spawn(async move {
+                        is_searching.set(true);
+                        let result = find_similar_documents(source.body, source.title, SIMILAR_DOCUMENTS)
+                            .await
+                            .map_err(|e| e.to_string());
+                        similar.set(Some(result));
+                        is_searching.set(false);
+                    });
+                },
+                if is_searching() { "Searching..." } else { "Find similar" }
+            }
+            { render_similar_documents(similar.read().as_ref()) }
+        }
+    }
+}
+
+/// Render the documents found by the "Find similar" action
+fn render_similar_documents(similar: Option<&Result<Vec<SimpleDocumentResult>, String>>) -> Element {
+    match similar {
+        None => rsx! {},
+        Some(Err(e)) => rsx! {
+            p { class: "text-red-400", "Error finding similar documents: {e}" }
+        },
+        Some(Ok(documents)) if documents.is_empty() => rsx! {
+            p { class: "text-gray-500", "No similar documents found" }
+        },
+        Some(Ok(documents)) => rsx! {
+            ul {
+                class: "mt-1 ml-3 flex flex-col gap-1 list-disc",
+                for document in documents.iter() {
+                    li {
+                        span { class: "text-gray-300", "{document.title}" }
+                        span { class: "font-mono text-gray-500", " · score {document.score:.4}" }
+                    }
+                }
+            }
+        },
+    }
+}
+
+/// Describe the individual retrieval signals of a source for debugging
+fn format_signals(source: &SimpleDocumentResult) -> String {
+    let signals = &source.signals;
+    let mut parts = Vec::new();
+
+    if let (Some(rank), Some(distance)) = (signals.vector_rank, signals.vector_distance) {
+        parts.push(format!("vector #{} (distance {:.4})", rank + 1, distance));
+    }
+    if let (Some(rank), Some(score)) = (signals.keyword_rank, signals.keyword_score) {
+        parts.push(format!("keyword #{} (bm25 {:.4})", rank + 1, score));
+    }
+    if let Some(score) = signals.rerank_score {
+        parts.push(format!("rerank {:.2}", score));
+    }
+    if let Some(score) = signals.mmr_score {
+        parts.push(format!("mmr {:.4}", score));
+    }
+
+    parts.join(" · ")
+}
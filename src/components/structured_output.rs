@@ -0,0 +1,102 @@
+//! Structured Output Component
+//!
+//! This component lets users generate JSON documents following a JSON Schema,
+//! for instance to fill a ticket template from a free text description. The
+//! result is rendered as a formatted JSON block that can be copied.
+
+use crate::server_functions::server_functions::generate_structured;
+use dioxus::prelude::*;
+
+/// Schema shown when the page is opened, as an example of the expected input
+const EXAMPLE_SCHEMA: &str = r#"{
+  "type": "object",
+  "properties": {
+    "title": { "type": "string", "maxLength": 80 },
+    "priority": { "enum": ["low", "medium", "high"] },
+    "component": { "type": "string" },
+    "steps_to_reproduce": { "type": "array", "items": { "type": "string" }, "maxItems": 5 }
+  }
+}"#;
+
+/// Page generating JSON documents from a schema and a prompt
+#[component]
+pub fn StructuredOutput() -> Element {
+    let mut schema = use_signal(|| EXAMPLE_SCHEMA.to_string());
+    let mut prompt = use_signal(String::new);
+    let mut result = use_signal(|| None::<Result<String, String>>);
+    let mut is_generating = use_signal(|| false);
+
+    let generate = move |_| {
+        let schema = schema();
+        let prompt = prompt();
+        spawn(async move {
+            is_generating.set(true);
+            let output = generate_structured(schema, prompt)
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|value| serde_json::to_string_pretty(&value).map_err(|e| e.to_string()));
+            result.set(Some(output));
+            is_generating.set(false);
+        });
+    };
+
+    rsx! {
+        div {
+            class: "w-full max-w-[80rem] mx-auto h-screen overflow-y-auto flex flex-col gap-4 p-4 pt-16 text-gray-300",
+            h1 { class: "text-xl font-semibold", "Structured output" }
+            label { class: "text-sm text-gray-400", "JSON Schema" }
+            textarea {
+                class: "h-48 p-2 rounded-lg bg-gray-800 text-gray-200 font-mono text-xs",
+                value: "{schema}",
+                oninput: move |event| schema.set(event.value()),
+            }
+            label { class: "text-sm text-gray-400", "Text to extract the data from" }
+            textarea {
+                class: "h-32 p-2 rounded-lg bg-gray-800 text-gray-200",
+                placeholder: "Describe the ticket, order, contact...",
+                value: "{prompt}",
+                oninput: move |event| prompt.set(event.value()),
+            }
+            button {
+                class: "self-start px-3 py-1 rounded-lg bg-gray-700 hover:bg-gray-600 disabled:opacity-50",
+                disabled: is_generating() || prompt().trim().is_empty(),
+                onclick: generate,
+                if is_generating() { "Generating..." } else { "Generate" }
+            }
+            { render_result(result.read().as_ref()) }
+        }
+    }
+}
+
+/// Render the generated JSON with a copy button, or the error
+fn render_result(result: Option<&Result<String, String>>) -> Element {
+    match result {
+        None => rsx! {},
+        Some(Err(e)) => rsx! {
+            p { class: "text-red-400", "{e}" }
+        },
+        Some(Ok(json)) => {
+            let json_to_copy = json.clone();
+            rsx! {
+                div {
+                    class: "relative",
+                    button {
+                        class: "absolute top-2 right-2 px-2 py-1 rounded bg-gray-700 hover:bg-gray-600 text-xs",
+                        onclick: move |_| copy_to_clipboard(&json_to_copy),
+                        "Copy"
+                    }
+                    pre {
+                        class: "p-4 rounded-lg bg-gray-900 border border-gray-700 font-mono text-xs whitespace-pre-wrap",
+                        "{json}"
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Copy a text to the clipboard of the browser
+fn copy_to_clipboard(text: &str) {
+    let text = serde_json::to_string(text).unwrap_or_default();
+    document::eval(&format!("navigator.clipboard.writeText({});", text));
+}
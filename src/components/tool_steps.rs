@@ -0,0 +1,45 @@
+//! Tool Steps Component
+//!
+//! This component renders the tools the assistant called before answering a
+//! message. Each call is a collapsed step showing the tool name, which expands
+//! to the arguments of the call and the output of the tool.
+
+use crate::model::tool::ToolStep;
+use dioxus::prelude::*;
+
+/// List of collapsible tool calls made for a message
+#[component]
+pub fn ToolSteps(steps: Vec<ToolStep>) -> Element {
+    rsx! {
+        div {
+            class: "mb-3 flex flex-col gap-1 text-xs text-gray-400",
+            for (index, step) in steps.iter().enumerate() {
+                { render_step(index, step) }
+            }
+        }
+    }
+}
+
+/// Render a single tool call with its arguments and output
+fn render_step(index: usize, step: &ToolStep) -> Element {
+    let status_class = if step.is_error { "text-red-400" } else { "text-green-400" };
+
+    rsx! {
+        details {
+            class: "border border-gray-700 rounded-lg",
+            summary {
+                class: "cursor-pointer select-none px-3 py-1",
+                span { class: "font-mono", "Step {index + 1}: " }
+                span { class: "font-semibold text-gray-300", "{step.tool}" }
+                span { class: "ml-2 {status_class}", if step.is_error { "failed" } else { "done" } }
+            }
+            div {
+                class: "flex flex-col gap-1 px-3 pb-2",
+                span { class: "font-semibold text-gray-300", "Arguments" }
+                pre { class: "font-mono whitespace-pre-wrap", "{step.arguments}" }
+                span { class: "font-semibold text-gray-300", if step.is_error { "Error" } else { "Output" } }
+                pre { class: "font-mono whitespace-pre-wrap max-h-40 overflow-y-auto", "{step.output}" }
+            }
+        }
+    }
+}
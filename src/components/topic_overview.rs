@@ -0,0 +1,114 @@
+//! Topic Overview Component
+//!
+//! This component renders the topic map of the knowledge base: the topics found
+//! by clustering the chunk embeddings, each with its label, the number of chunks
+//! and documents it covers and a few example chunks. Only admins may rebuild
+//! the map.
+
+use crate::model::auth::UserInfo;
+use crate::model::topic::{Topic, TopicMap};
+use crate::server_functions::server_functions::get_topic_map;
+use dioxus::prelude::*;
+
+/// Page listing the topics covered by the knowledge base
+#[component]
+pub fn TopicOverview() -> Element {
+    let mut topic_map = use_signal(|| None::<Result<TopicMap, String>>);
+    let mut is_loading = use_signal(|| false);
+    let user = use_context::<Signal<Option<UserInfo>>>();
+    let is_admin = user().is_some_and(|user| user.is_admin());
+
+    // Load the topics when the page is first shown, reusing the last map built on the server
+    use_effect(move || {
+        load_topics(topic_map, is_loading, false);
+    });
+
+    rsx! {
+        div {
+            class: "w-full max-w-[80rem] mx-auto h-screen overflow-y-auto flex flex-col gap-4 p-4 pt-16 text-gray-300",
+            div {
+                class: "flex justify-between items-center",
+                h1 { class: "text-xl font-semibold", "Knowledge base topics" }
+                if is_admin {
+                    button {
+                        class: "px-3 py-1 rounded-lg bg-gray-700 hover:bg-gray-600 disabled:opacity-50",
+                        disabled: is_loading(),
+                        onclick: move |_| load_topics(topic_map, is_loading, true),
+                        if is_loading() { "Clustering..." } else { "Rebuild" }
+                    }
+                }
+            }
+            { render_topic_map(topic_map.read().as_ref(), is_loading()) }
+        }
+    }
+}
+
+/// Request the topic map from the server
+fn load_topics(
+    mut topic_map: Signal<Option<Result<TopicMap, String>>>,
+    mut is_loading: Signal<bool>,
+    refresh: bool,
+) {
+    spawn(async move {
+        is_loading.set(true);
+        let result = get_topic_map(None, refresh).await.map_err(|e| e.to_string());
+        topic_map.set(Some(result));
+        is_loading.set(false);
+    });
+}
+
+/// Render the topics, or the loading and error states
+fn render_topic_map(topic_map: Option<&Result<TopicMap, String>>, is_loading: bool) -> Element {
+    match topic_map {
+        None if is_loading => rsx! {
+            p { class: "text-gray-500", "Clustering the knowledge base and labelling topics..." }
+        },
+        None => rsx! {},
+        Some(Err(e)) => rsx! {
+            p { class: "text-red-400", "{e}" }
+        },
+        Some(Ok(map)) if map.topics.is_empty() => rsx! {
+            p { class: "text-gray-500", "The knowledge base is empty" }
+        },
+        Some(Ok(map)) => rsx! {
+            p { class: "text-sm text-gray-500", "{map.topics.len()} topics over {map.chunk_count} chunks" }
+            div {
+                class: "grid grid-cols-1 md:grid-cols-2 gap-4",
+                for topic in map.topics.iter() {
+                    { render_topic(topic) }
+                }
+            }
+        },
+    }
+}
+
+/// Render a single topic with its counts, documents and example chunks
+fn render_topic(topic: &Topic) -> Element {
+    rsx! {
+        div {
+            class: "flex flex-col gap-2 p-4 border border-gray-700 rounded-lg",
+            div {
+                class: "flex justify-between gap-4",
+                h2 { class: "font-semibold text-gray-200", "{topic.label}" }
+                span {
+                    class: "text-xs font-mono text-gray-500",
+                    "{topic.document_count()} docs · {topic.chunk_count} chunks"
+                }
+            }
+            p {
+                class: "text-xs text-gray-400",
+                { topic.documents.join(", ") }
+            }
+            for example in topic.examples.iter() {
+                div {
+                    class: "text-xs border-t border-gray-700 pt-2",
+                    span { class: "font-semibold text-gray-300", "{example.title}" }
+                    p {
+                        class: "whitespace-pre-wrap text-gray-400 max-h-24 overflow-y-auto",
+                        "{example.text}"
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Usage Page Component
+//!
+//! This page shows the tokens the current user consumed today, this month and
+//! per conversation, with the allowance left under the quotas of their role.
+//! Admins also see the consumption of every account; the quotas are set on
+//! the settings page.
+
+use crate::model::auth::UserInfo;
+use crate::model::usage::UserUsage;
+use crate::server_functions::server_functions::{get_my_usage, list_usage};
+use dioxus::prelude::*;
+
+/// Page with the token consumption of the current user
+#[component]
+pub fn UsagePage() -> Element {
+    let user = use_context::<Signal<Option<UserInfo>>>();
+    let mut usage = use_resource(|| async move { get_my_usage().await.map_err(|e| e.to_string()) });
+    let is_admin = user().is_some_and(|user| user.is_admin());
+
+    rsx! {
+        div {
+            class: "w-full max-w-[80rem] mx-auto h-screen overflow-y-auto flex flex-col gap-6 p-4 pt-16 text-gray-300",
+            div {
+                class: "flex justify-between items-center",
+                h1 { class: "text-xl font-semibold", "Usage" }
+                button {
+                    class: "px-3 py-1 rounded-lg bg-gray-700 hover:bg-gray-600 text-sm",
+                    onclick: move |_| usage.restart(),
+                    "Refresh"
+                }
+            }
+            { match &*usage.read() {
+                None => rsx! { p { class: "text-gray-500", "Loading usage..." } },
+                Some(Err(e)) => rsx! { p { class: "text-red-400", "{e}" } },
+                Some(Ok(usage)) => render_user_usage(usage),
+            } }
+            if is_admin {
+                AllUsage {}
+            }
+        }
+    }
+}
+
+/// Render the allowance left and the conversations of a user
+fn render_user_usage(usage: &UserUsage) -> Element {
+    rsx! {
+        section {
+            class: "flex flex-col gap-4",
+            div {
+                class: "grid grid-cols-1 md:grid-cols-2 gap-4",
+                { render_allowance("Today", usage.tokens_today, usage.quota.tokens_per_day, usage.remaining_today()) }
+                { render_allowance("This month", usage.tokens_this_month, usage.quota.tokens_per_month, usage.remaining_this_month()) }
+            }
+            p {
+                class: "text-sm text-gray-500",
+                "All time: {usage.total_tokens()} tokens, {usage.total_prompt_tokens} in prompts and {usage.total_completion_tokens} generated"
+            }
+            h2 { class: "text-lg font-semibold", "Recent conversations" }
+            if usage.conversations.is_empty() {
+                p { class: "text-gray-500", "No requests made yet" }
+            } else {
+                table {
+                    class: "w-full text-sm text-left",
+                    thead {
+                        tr {
+                            class: "text-gray-500",
+                            th { class: "py-1", "Conversation" }
+                            th { class: "py-1", "Requests" }
+                            th { class: "py-1", "Prompt tokens" }
+                            th { class: "py-1", "Generated tokens" }
+                        }
+                    }
+                    tbody {
+                        for conversation in usage.conversations.iter() {
+                            tr {
+                                class: "border-t border-gray-800",
+                                td {
+                                    class: "py-1",
+                                    { match conversation.conversation {
+                                        Some(id) => format!("#{}", id),
+                                        None => "Outside conversations".to_string(),
+                                    } }
+                                }
+                                td { class: "py-1", "{conversation.responses}" }
+                                td { class: "py-1", "{conversation.prompt_tokens}" }
+                                td { class: "py-1", "{conversation.completion_tokens}" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Render the tokens used over a period with a bar showing the share of the quota
+fn render_allowance(period: &str, used: u64, limit: u64, remaining: Option<u64>) -> Element {
+    let percent = if limit > 0 { (used as f64 / limit as f64 * 100.0).min(100.0) } else { 0.0 };
+    let bar_class = if remaining == Some(0) { "h-full bg-red-700" } else { "h-full bg-gray-500" };
+
+    rsx! {
+        div {
+            class: "p-3 rounded-lg bg-gray-800/50 flex flex-col gap-2",
+            div {
+                class: "flex justify-between text-sm",
+                span { class: "font-semibold", "{period}" }
+                { match remaining {
+                    Some(remaining) => rsx! { span { class: "text-gray-400", "{remaining} tokens left" } },
+                    None => rsx! { span { class: "text-gray-400", "No limit" } },
+                } }
+            }
+            div {
+                class: "w-full h-2 bg-gray-800 rounded-full overflow-hidden",
+                div { class: bar_class, style: "width: {percent:.0}%" }
+            }
+            p {
+                class: "text-xs text-gray-500",
+                if limit > 0 { "{used} of {limit} tokens used" } else { "{used} tokens used" }
+            }
+        }
+    }
+}
+
+/// Section listing the consumption of every account
+#[component]
+fn AllUsage() -> Element {
+    let mut usage = use_resource(|| async move { list_usage().await.map_err(|e| e.to_string()) });
+
+    rsx! {
+        section {
+            class: "flex flex-col gap-3",
+            div {
+                class: "flex justify-between items-center",
+                h2 { class: "text-lg font-semibold", "All users" }
+                button {
+                    class: "px-3 py-1 rounded-lg bg-gray-700 hover:bg-gray-600 text-sm",
+                    onclick: move |_| usage.restart(),
+                    "Refresh"
+                }
+            }
+            { match &*usage.read() {
+                None => rsx! { p { class: "text-gray-500", "Loading usage..." } },
+                Some(Err(e)) => rsx! { p { class: "text-red-400", "{e}" } },
+                Some(Ok(usage)) => render_usage_table(usage),
+            } }
+        }
+    }
+}
+
+/// Render the consumption and remaining allowance of every account
+fn render_usage_table(usage: &[UserUsage]) -> Element {
+    let remaining = |value: Option<u64>| value.map_or("∞".to_string(), |value| value.to_string());
+
+    rsx! {
+        table {
+            class: "w-full text-sm text-left",
+            thead {
+                tr {
+                    class: "text-gray-500",
+                    th { class: "py-1", "Username" }
+                    th { class: "py-1", "Today" }
+                    th { class: "py-1", "Left today" }
+                    th { class: "py-1", "This month" }
+                    th { class: "py-1", "Left this month" }
+                    th { class: "py-1", "All time" }
+                }
+            }
+            tbody {
+                for user in usage.iter() {
+                    tr {
+                        key: "{user.username}",
+                        class: "border-t border-gray-800",
+                        td { class: "py-1", "{user.username}" }
+                        td { class: "py-1", "{user.tokens_today}" }
+                        td { class: "py-1", "{remaining(user.remaining_today())}" }
+                        td { class: "py-1", "{user.tokens_this_month}" }
+                        td { class: "py-1", "{remaining(user.remaining_this_month())}" }
+                        td { class: "py-1", "{user.total_tokens()}" }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -5,7 +5,9 @@
 //!     - Author: Alejandro López Martínez
 
 use dioxus::prelude::*;
-use components::{Conversation};
+use components::{AdminPanel, Conversation, DocumentBrowser, DocumentView, LoginForm, SettingsPage, StructuredOutput, TopicOverview, UsagePage};
+use model::auth::UserInfo;
+use server_functions::server_functions::{current_user, logout};
 
 /// Module containing the UI components of the application
 mod components;
@@ -24,17 +26,69 @@ const TAILWIND_CSS: Asset = asset!("/assets/tailwind.css");
 
 /// Main function that launches the Dioxus application
 /// with the App component as the root.
+///
+/// On the server, the configuration is loaded first (see `server::config`),
+/// maintenance commands given on the command line (see `server::cli`) are
+/// run instead, and the application is served together with the HTTP
+/// endpoints of `server::http`.
 fn main() {
+    #[cfg(feature = "server")]
+    {
+        server::logging::init();
+        if let Err(e) = server::config::init() {
+            eprintln!("Invalid configuration: {}", e);
+            std::process::exit(1);
+        }
+        if let Some(code) = server::cli::run_from_args() {
+            std::process::exit(code);
+        }
+        server::http::launch(App);
+    }
+
+    #[cfg(not(feature = "server"))]
     dioxus::launch(App);
 }
 
+/// Routes of the application
+///
+/// Every route is rendered inside `AppLayout`, which provides the navigation bar.
+#[derive(Routable, Clone, PartialEq)]
+#[rustfmt::skip]
+enum Route {
+    #[layout(AppLayout)]
+        /// The chat interface
+        #[route("/")]
+        Chat {},
+        /// The topic map of the knowledge base
+        #[route("/topics")]
+        TopicOverview {},
+        /// The list of documents of the knowledge base with ad-hoc search
+        #[route("/documents")]
+        DocumentBrowser {},
+        /// A single document with its chunks
+        #[route("/documents/:id")]
+        DocumentView { id: usize },
+        /// JSON generation following a schema
+        #[route("/structured")]
+        StructuredOutput {},
+        /// Token consumption and remaining allowance of the user
+        #[route("/usage")]
+        UsagePage {},
+        /// Management operations reserved to admins
+        #[route("/admin")]
+        AdminPanel {},
+        /// Runtime settings and configuration, reserved to admins
+        #[route("/settings")]
+        SettingsPage {},
+}
+
 /// Root component of the application.
 /// 
 /// This component defines the basic structure of the HTML document,
 /// including:
 /// - Links to resources such as favicon and CSS styles
 /// - Page body with dark background
-/// - The Conversation component that handles the main interface
+/// - The router rendering the page of the current route
 #[component]
 fn App() -> Element {
     rsx! {
@@ -42,8 +96,116 @@ fn App() -> Element {
         document::Link { rel: "stylesheet", href: TAILWIND_CSS }
         body {
             class: "bg-[#0f1116]", // Dark background
+            Router::<Route> {}
+        }
+    }
+}
+
+/// Layout shared by every route
+///
+/// Visitors without a session only see the login form. The logged in user is
+/// shared with every page through a context signal.
+///
+/// The Conversation component is rendered here rather than by the chat route so
+/// it stays mounted while other pages are open: its state survives navigation.
+#[component]
+fn AppLayout() -> Element {
+    let route = use_route::<Route>();
+    let is_chat = route == Route::Chat {};
+    let mut user = use_context_provider(|| Signal::new(None::<UserInfo>));
+    let session = use_resource(|| async move { current_user().await.ok().flatten() });
+
+    // Restore the user of an existing session cookie
+    use_effect(move || {
+        if let Some(Some(found)) = &*session.read() {
+            user.set(Some(found.clone()));
+        }
+    });
+
+    if session.read().is_none() {
+        return rsx! {};
+    }
+    let Some(current) = user() else {
+        return rsx! { LoginForm {} };
+    };
+
+    rsx! {
+        { render_navigation(&route, &current, user) }
+        div {
+            class: if is_chat { "" } else { "hidden" },
             Conversation {}
         }
+        Outlet::<Route> {}
     }
 }
 
+/// Chat route, whose interface is rendered by `AppLayout`
+#[component]
+fn Chat() -> Element {
+    rsx! {}
+}
+
+/// Render the navigation bar with a link per page, the user name and a logout button
+fn render_navigation(route: &Route, current: &UserInfo, mut user: Signal<Option<UserInfo>>) -> Element {
+    let link_class = |active: bool| {
+        if active {
+            "px-3 py-1 rounded-lg bg-gray-600 text-gray-100"
+        } else {
+            "px-3 py-1 rounded-lg bg-gray-800 text-gray-400 hover:bg-gray-700"
+        }
+    };
+
+    rsx! {
+        nav {
+            class: "fixed top-4 right-4 z-10 flex gap-2 text-sm",
+            Link {
+                class: link_class(matches!(route, Route::Chat {})),
+                to: Route::Chat {},
+                "Chat"
+            }
+            Link {
+                class: link_class(matches!(route, Route::DocumentBrowser {} | Route::DocumentView { .. })),
+                to: Route::DocumentBrowser {},
+                "Documents"
+            }
+            Link {
+                class: link_class(matches!(route, Route::TopicOverview {})),
+                to: Route::TopicOverview {},
+                "Topics"
+            }
+            Link {
+                class: link_class(matches!(route, Route::StructuredOutput {})),
+                to: Route::StructuredOutput {},
+                "JSON"
+            }
+            Link {
+                class: link_class(matches!(route, Route::UsagePage {})),
+                to: Route::UsagePage {},
+                "Usage"
+            }
+            if current.is_admin() {
+                Link {
+                    class: link_class(matches!(route, Route::AdminPanel {})),
+                    to: Route::AdminPanel {},
+                    "Admin"
+                }
+                Link {
+                    class: link_class(matches!(route, Route::SettingsPage {})),
+                    to: Route::SettingsPage {},
+                    "Settings"
+                }
+            }
+            span { class: "px-3 py-1 text-gray-500", "{current.username}" }
+            button {
+                class: link_class(false),
+                onclick: move |_| async move {
+                    if let Err(e) = logout().await {
+                        dioxus::logger::tracing::warn!("Error logging out: {}", e);
+                    }
+                    user.set(None);
+                },
+                "Log out"
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! API Key Model Definitions
+//!
+//! This module defines the structures describing the API keys used by scripts
+//! to call the server without a browser session, with their quotas and usage.
+
+use serde::{Deserialize, Serialize};
+
+/// An API key as listed in the admin panel, without its secret
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeyInfo {
+    /// Identifier of the key, a hash of the secret
+    pub id: String,
+
+    /// Name describing what the key is used for
+    pub name: String,
+
+    /// The account the requests made with the key are attributed to
+    pub username: String,
+
+    /// First characters of the secret, to recognize the key
+    pub prefix: String,
+
+    /// Creation time in seconds since the Unix epoch
+    pub created_at: u64,
+
+    /// Maximum number of generation and embedding requests per minute, 0 for no limit
+    pub requests_per_minute: u32,
+
+    /// Maximum number of tokens processed per day, 0 for no limit
+    pub tokens_per_day: u64,
+
+    /// Generation and embedding requests made in the last minute
+    pub requests_last_minute: usize,
+
+    /// Tokens processed today, in UTC
+    pub tokens_today: u64,
+
+    /// Generation and embedding requests made since the key was created
+    pub total_requests: u64,
+
+    /// Tokens processed since the key was created
+    pub total_tokens: u64,
+}
+
+/// A newly created API key together with its secret
+///
+/// The secret is only returned once, when the key is created.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewApiKey {
+    /// The stored key
+    pub info: ApiKeyInfo,
+
+    /// The secret to send as a bearer token
+    pub key: String,
+}
@@ -0,0 +1,34 @@
+//! Authentication Model Definitions
+//!
+//! This module defines the structures describing user accounts and their roles,
+//! shared by the login interface and the server-side access checks.
+
+use serde::{Deserialize, Serialize};
+
+/// Role of a user account
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Can chat and search the knowledge base
+    #[default]
+    User,
+    /// Can also manage the knowledge base, the models and the accounts
+    Admin,
+}
+
+/// The account a request is made by
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserInfo {
+    /// The name the user logs in with
+    pub username: String,
+
+    /// The role of the account
+    pub role: Role,
+}
+
+impl UserInfo {
+    /// Whether the user can run management operations
+    pub fn is_admin(&self) -> bool {
+        self.role == Role::Admin
+    }
+}
@@ -0,0 +1,30 @@
+//! Backup Model Definitions
+//!
+//! This module defines the data structures describing knowledge base archives,
+//! the portable files used to move an embedded corpus between servers.
+
+use serde::{Deserialize, Serialize};
+
+/// Summary of a knowledge base archive that was exported or imported
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveSummary {
+    /// Path of the archive on the server
+    pub path: String,
+
+    /// Identifier of the embedding model the vectors were computed with
+    pub model_id: String,
+
+    /// Number of dimensions of the embedding vectors
+    pub dimensions: usize,
+
+    /// Number of documents in the archive
+    pub documents: usize,
+
+    /// Number of chunks, each with its vector, in the archive
+    pub chunks: usize,
+
+    /// Whether the chunks were embedded again because the archive was embedded
+    /// with another model than the running one
+    #[serde(default)]
+    pub reembedded: bool,
+}
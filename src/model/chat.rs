@@ -4,12 +4,17 @@
 //! and conversation participants. It defines the fundamental types used throughout
 //! the chat interface components.
 
+use crate::model::document::SimpleDocumentResult;
+use crate::model::grounding::ClaimCheck;
+use crate::model::tool::ToolStep;
+use serde::{Deserialize, Serialize};
+
 /// Represents the role of a participant in the conversation
 ///
 /// Two roles are supported:
 /// - `Assistant`: Messages from the AI assistant
 /// - `User`: Messages from the human user
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum ChatRole {
     /// Represents messages generated by the AI assistant
     Assistant,
@@ -22,13 +27,40 @@ pub enum ChatRole {
 /// Each message contains:
 /// - The role of the sender (Assistant or User)
 /// - The content of the message as a string
+/// - The context sources retrieved for the message, if any
+/// - The search queries the sources were retrieved with, if any
+/// - The tools called before answering, if any
+/// - The verification of its sentences against the sources, if any
 ///
 /// The content string may contain markdown formatting which gets rendered
 /// by the Message component.
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
     /// The role of the message sender (Assistant or User)
     pub(crate) role: ChatRole,
     /// The content of the message, may contain markdown
     pub(crate) content: String,
+    /// The document chunks injected as context when generating the message
+    pub(crate) sources: Vec<SimpleDocumentResult>,
+    /// The standalone search queries the context was retrieved with
+    pub(crate) queries: Vec<String>,
+    /// The tool calls made before generating the message
+    pub(crate) tool_steps: Vec<ToolStep>,
+    /// The checks of the sentences of the message against its sources
+    pub(crate) grounding: Vec<ClaimCheck>,
+}
+
+impl ChatMessage {
+    /// Creates a message with the given role and content and no sources, tool calls or checks
+    pub fn new(role: ChatRole, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            sources: Vec::new(),
+            queries: Vec::new(),
+            tool_steps: Vec::new(),
+            grounding: Vec::new(),
+        }
+    }
 }
+
@@ -0,0 +1,74 @@
+//! Collection Model Definitions
+//!
+//! This module defines the configuration of document collections. A collection
+//! groups the documents of a subfolder of the context folder, the files placed
+//! directly in it forming the `documents` collection, and its configuration
+//! decides how the documents ingested into it are split into chunks before
+//! being embedded and indexed.
+
+use serde::{Deserialize, Serialize};
+
+/// Strategy used to split documents into chunks
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum ChunkingStrategy {
+    /// Split where the meaning of consecutive sentences changes, using embeddings
+    #[default]
+    Semantic,
+    /// Windows of a fixed number of words, overlapping by `overlap` words
+    FixedSize {
+        /// Number of words in each chunk
+        chunk_size: usize,
+        /// Number of words shared by consecutive chunks
+        overlap: usize,
+    },
+    /// Groups of consecutive sentences, overlapping by `overlap` sentences
+    Sentence {
+        /// Number of sentences in each chunk
+        sentences_per_chunk: usize,
+        /// Number of sentences shared by consecutive chunks
+        overlap: usize,
+    },
+    /// Markdown sections split at headings, each chunk keeping the breadcrumb
+    /// of the headings it belongs to
+    MarkdownHeadings {
+        /// Sections longer than this many characters are split at paragraph boundaries
+        max_chunk_chars: usize,
+    },
+}
+
+/// Configuration of a document collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollectionConfig {
+    /// The name of the collection, which is also the name of the subfolder of
+    /// the context folder holding its documents
+    pub name: String,
+
+    /// The strategy used to chunk documents ingested into the collection
+    pub chunking: ChunkingStrategy,
+}
+
+impl CollectionConfig {
+    /// Creates the default configuration of a collection
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            chunking: ChunkingStrategy::default(),
+        }
+    }
+}
+
+/// A chunk of a document as it would be produced by a chunking strategy
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkPreview {
+    /// Byte offset of the start of the chunk in the document
+    pub start: usize,
+
+    /// Byte offset of the end of the chunk in the document
+    pub end: usize,
+
+    /// The headings the chunk is nested under, joined with " > "
+    pub breadcrumb: Option<String>,
+
+    /// The text of the chunk
+    pub text: String,
+}
@@ -5,26 +5,245 @@
 //! the database when providing context for conversations.
 
 use serde::{Deserialize, Serialize};
+use crate::model::tool::ToolStep;
+
+/// Largest number of passages a search can return
+pub const MAX_RETRIEVAL_TOP_K: usize = 20;
+
+/// Largest number of candidates retrieved for the reranking and MMR stages
+/// Reranking asks the language model about every candidate, so this bounds its calls
+pub const MAX_RERANK_CANDIDATES: usize = 50;
+
+/// Largest number of paraphrased queries a search can request
+/// Every paraphrase lengthens the rewriting answer and adds a retrieval
+pub const MAX_PARAPHRASES: usize = 5;
+
+/// Attaches retrieved sources to a chat message as the context block sent to the model
+///
+/// The message is returned unchanged when there are no sources.
+pub fn with_context(message: &str, sources: &[SimpleDocumentResult]) -> String {
+    if sources.is_empty() {
+        return message.to_string();
+    }
+    let context = sources.iter()
+        .map(|document| format!("Title: {}\nBody: {}\n", document.title, document.body))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("{}\n\n[Potentially useful context:\n{}]", message, context)
+}
 
 /// Represents a simplified document search result
 ///
 /// This structure contains the essential information of a document retrieved
 /// during context search operations, including:
 /// - The document title
-/// - The document body text
-/// - A relevance score indicating how well the document matches the search query
+/// - The text of the matching chunk
+/// - A relevance score indicating how well the chunk matches the search query
+/// - The individual retrieval signals the score was computed from
 ///
 /// The score is used to rank and filter documents based on their relevance to
 /// the current conversation context.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SimpleDocumentResult {
     /// The title of the document
     pub title: String,
 
-    /// The main text content of the document
+    /// The text of the matching chunk of the document
     pub body: String,
 
     /// A floating-point score representing the document's relevance
     /// Higher values indicate greater relevance to the search query
     pub score: f32,
+
+    /// Identifier of the chunk in the knowledge base, formatted as `<document id>:<position>`
+    /// Results of different retrieval signals for the same chunk share it
+    #[serde(default)]
+    pub chunk_id: Option<String>,
+
+    /// The per-signal scores that produced `score`, kept for debugging
+    #[serde(default)]
+    pub signals: ScoreSignals,
+}
+
+/// Individual retrieval signals behind a search result
+///
+/// Each field is `None` when the corresponding signal did not return the chunk.
+/// Ranks are zero-based positions in the ranking produced by that signal.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScoreSignals {
+    /// Distance between the query embedding and the chunk embedding
+    pub vector_distance: Option<f32>,
+
+    /// Position of the chunk in the embedding ranking
+    pub vector_rank: Option<usize>,
+
+    /// BM25 score of the chunk in the full-text index
+    pub keyword_score: Option<f32>,
+
+    /// Position of the chunk in the full-text ranking
+    pub keyword_rank: Option<usize>,
+
+    /// Relevance judged by the reranking stage, between 0.0 and 1.0
+    pub rerank_score: Option<f32>,
+
+    /// Marginal relevance of the chunk when it was picked by MMR selection
+    pub mmr_score: Option<f32>,
+}
+
+/// Retrieval strategy used when searching the document table
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum SearchMode {
+    /// Nearest-neighbour search over the chunk embeddings
+    #[default]
+    Vector,
+    /// BM25 full-text search over the chunk text
+    Keyword,
+    /// Reciprocal rank fusion of the embedding and full-text rankings
+    Hybrid,
+}
+
+/// Options controlling how a context search is performed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchOptions {
+    /// The retrieval strategy to use
+    pub mode: SearchMode,
+
+    /// Maximum number of results to return
+    pub top_k: usize,
+
+    /// Weight of the full-text ranking in hybrid mode, between 0.0 and 1.0
+    /// The embedding ranking receives the remaining weight
+    pub keyword_weight: f32,
+
+    /// Whether the candidates are reordered by the language model before being returned
+    pub rerank: bool,
+
+    /// Trade-off between relevance and diversity for maximal marginal relevance
+    /// `None` disables MMR selection, `1.0` is pure relevance and `0.0` pure diversity
+    pub mmr_lambda: Option<f32>,
+
+    /// Number of candidates retrieved for the reranking and MMR stages
+    pub candidates: usize,
+
+    /// Whether the latest message is rewritten into a standalone query using
+    /// the recent conversation history before searching
+    pub rewrite_query: bool,
+
+    /// Number of additional paraphrased queries whose results are merged
+    pub paraphrases: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            mode: SearchMode::default(),
+            top_k: 1,
+            keyword_weight: 0.5,
+            rerank: false,
+            mmr_lambda: None,
+            candidates: 10,
+            rewrite_query: true,
+            paraphrases: 0,
+        }
+    }
+}
+
+impl SearchOptions {
+    /// Returns the options with every value brought into its valid range
+    ///
+    /// The options come from clients, so the server clamps them before searching.
+    pub fn clamped(self) -> Self {
+        Self {
+            top_k: self.top_k.clamp(1, MAX_RETRIEVAL_TOP_K),
+            keyword_weight: self.keyword_weight.clamp(0.0, 1.0),
+            mmr_lambda: self.mmr_lambda.map(|lambda| lambda.clamp(0.0, 1.0)),
+            candidates: self.candidates.clamp(1, MAX_RERANK_CANDIDATES),
+            paraphrases: self.paraphrases.min(MAX_PARAPHRASES),
+            ..self
+        }
+    }
+
+    /// Number of standalone queries requested from the query rewriting step
+    pub fn query_count(&self) -> usize {
+        1 + self.paraphrases.min(MAX_PARAPHRASES)
+    }
+
+    /// Number of results the retrieval stage must produce for the later stages
+    pub fn candidate_count(&self) -> usize {
+        if self.rerank || self.mmr_lambda.is_some() {
+            self.candidates.max(self.top_k)
+        } else {
+            self.top_k
+        }
+    }
+}
+
+/// Outcome of a context search for a chat message
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContextSearch {
+    /// The standalone queries the search was performed with
+    pub queries: Vec<String>,
+
+    /// The merged results of all the queries, best first
+    pub results: Vec<SimpleDocumentResult>,
+
+    /// The searches decided by the model in automatic mode, empty otherwise
+    #[serde(default)]
+    pub steps: Vec<ToolStep>,
+}
+
+/// A chunk of a document stored in the knowledge base
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentChunk {
+    /// The title of the document the chunk belongs to
+    pub title: String,
+
+    /// The text of the chunk
+    pub text: String,
+}
+
+/// Two chunks of the knowledge base whose embeddings are nearly identical
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicatePair {
+    /// The first chunk of the pair
+    pub first: DocumentChunk,
+
+    /// The second chunk of the pair
+    pub second: DocumentChunk,
+
+    /// Cosine similarity between the embeddings of both chunks
+    pub similarity: f32,
+}
+
+/// Summary of a document ingested into the knowledge base
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentInfo {
+    /// Identifier of the document, assigned in ingestion order
+    pub id: usize,
+
+    /// The title of the document
+    pub title: String,
+
+    /// Path of the file the document was loaded from
+    pub source: String,
+
+    /// Name of the collection the document belongs to
+    #[serde(default)]
+    pub collection: String,
+
+    /// Number of chunks the document was split into
+    pub chunk_count: usize,
+
+    /// Time at which the document was ingested, in RFC 3339 format
+    pub ingested_at: String,
+}
+
+/// A document of the knowledge base together with its chunks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentDetails {
+    /// Summary of the document
+    pub info: DocumentInfo,
+
+    /// The text of the chunks, in document order
+    pub chunks: Vec<String>,
 }
@@ -0,0 +1,55 @@
+//! Embedding Model Definitions
+//!
+//! This module defines data structures describing the state of the embedding
+//! pipeline, such as the statistics of the embedding cache and the throughput
+//! of document ingestion.
+
+use serde::{Deserialize, Serialize};
+
+/// Statistics of the persistent embedding cache
+///
+/// Hit and miss counters are kept in memory and start from zero every time the
+/// server starts, while the number of entries reflects the persistent store.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingCacheStats {
+    /// Identifier of the embedding model the cached vectors belong to
+    pub model_id: String,
+
+    /// Number of vectors stored in the cache
+    pub entries: usize,
+
+    /// Number of embeddings served from the cache since the server started
+    pub hits: u64,
+
+    /// Number of embeddings computed by the model since the server started
+    pub misses: u64,
+}
+
+/// Summary of a document ingestion run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IngestionReport {
+    /// Number of documents inserted
+    pub documents: usize,
+
+    /// Number of chunks embedded and indexed
+    pub chunks: usize,
+
+    /// Wall-clock duration of the ingestion in milliseconds
+    pub elapsed_ms: u64,
+
+    /// Files that could not be read, with the reason they were skipped
+    #[serde(default)]
+    pub skipped: Vec<String>,
+}
+
+impl IngestionReport {
+    /// Number of documents ingested per second
+    pub fn documents_per_second(&self) -> f64 {
+        self.documents as f64 * 1000.0 / self.elapsed_ms.max(1) as f64
+    }
+
+    /// Number of chunks embedded per second
+    pub fn chunks_per_second(&self) -> f64 {
+        self.chunks as f64 * 1000.0 / self.elapsed_ms.max(1) as f64
+    }
+}
@@ -0,0 +1,31 @@
+//! Grounding Model Definitions
+//!
+//! This module defines the data structures describing how well the sentences
+//! of an answer are supported by the sources it was generated from.
+
+use serde::{Deserialize, Serialize};
+
+/// Verification of a sentence of an answer against the retrieved sources
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClaimCheck {
+    /// Byte offset where the sentence starts in the answer
+    pub start: usize,
+
+    /// Byte offset where the sentence ends in the answer
+    pub end: usize,
+
+    /// The text of the sentence
+    pub sentence: String,
+
+    /// Whether the sentence is supported by the sources
+    pub supported: bool,
+
+    /// Highest cosine similarity between the sentence and a source chunk
+    pub similarity: f32,
+
+    /// Title of the most similar source
+    pub best_source: Option<String>,
+
+    /// Whether the language model judged the sentence because the similarity was inconclusive
+    pub judged: bool,
+}
@@ -0,0 +1,60 @@
+//! Metrics Model Definitions
+//!
+//! This module defines the data structures describing the performance of the
+//! language model: the measurements of each generated response and their
+//! distribution over many responses.
+
+use serde::{Deserialize, Serialize};
+
+/// Measurements of a single generated response
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationMetrics {
+    /// Time between the request and the first generated token, in milliseconds
+    pub time_to_first_token_ms: f64,
+
+    /// Time between the request and the last generated token, in milliseconds
+    pub total_ms: f64,
+
+    /// Number of tokens of the prompt
+    pub prompt_tokens: usize,
+
+    /// Number of generated tokens
+    pub completion_tokens: usize,
+}
+
+impl GenerationMetrics {
+    /// Generation speed after the first token, in tokens per second
+    pub fn tokens_per_second(&self) -> f64 {
+        let generation_ms = self.total_ms - self.time_to_first_token_ms;
+        if self.completion_tokens <= 1 || generation_ms <= 0.0 {
+            return 0.0;
+        }
+        (self.completion_tokens - 1) as f64 * 1000.0 / generation_ms
+    }
+}
+
+/// Median and 95th percentile of a measurement
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Percentiles {
+    pub p50: f64,
+    pub p95: f64,
+}
+
+/// Distribution of the measurements of many responses
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationSummary {
+    /// Number of responses measured
+    pub responses: usize,
+
+    /// Time to first token, in milliseconds
+    pub time_to_first_token_ms: Percentiles,
+
+    /// Generation speed, in tokens per second
+    pub tokens_per_second: Percentiles,
+
+    /// Number of prompt tokens
+    pub prompt_tokens: Percentiles,
+
+    /// Number of generated tokens
+    pub completion_tokens: Percentiles,
+}
@@ -1,2 +1,14 @@
+pub mod api_key;
+pub mod auth;
+pub mod backup;
 pub mod chat;
-pub mod document;
\ No newline at end of file
+pub mod collection;
+pub mod document;
+pub mod embedding;
+pub mod grounding;
+pub mod metrics;
+pub mod settings;
+pub mod status;
+pub mod tool;
+pub mod topic;
+pub mod usage;
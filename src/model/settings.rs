@@ -0,0 +1,288 @@
+//! Settings Model Definitions
+//!
+//! This module defines the typed server configuration, read from the
+//! configuration file and the environment when the server starts, and the
+//! runtime settings admins change from the settings page without a restart.
+
+use serde::{Deserialize, Serialize};
+use crate::model::usage::UsageQuotas;
+
+/// Configuration of the server, fixed while it runs
+///
+/// Every field has a default, so the configuration file only needs the values
+/// that differ from it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// Database holding the document table, rebuilt at every start
+    pub database: DatabaseConfig,
+
+    /// Persistent application database holding accounts, usage and settings
+    pub app_database: AppDatabaseConfig,
+
+    /// Knowledge base sources
+    pub knowledge_base: KnowledgeBaseConfig,
+
+    /// Document ingestion pipeline
+    pub ingestion: IngestionConfig,
+
+    /// Language model
+    pub model: ModelConfig,
+
+    /// Sampling parameters of the chat responses
+    pub sampling: SamplingConfig,
+
+    /// Initial runtime settings, used until admins change them
+    pub settings: RuntimeSettings,
+}
+
+/// Location of the document database
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    /// Directory the database files are stored in
+    pub path: String,
+
+    /// SurrealDB namespace
+    pub namespace: String,
+
+    /// SurrealDB database
+    pub database: String,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            path: "./db".to_string(),
+            namespace: "test".to_string(),
+            database: "test".to_string(),
+        }
+    }
+}
+
+/// Location of the application database
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppDatabaseConfig {
+    /// File the database is stored in
+    pub path: String,
+
+    /// SurrealDB namespace
+    pub namespace: String,
+
+    /// SurrealDB database
+    pub database: String,
+}
+
+impl Default for AppDatabaseConfig {
+    fn default() -> Self {
+        Self {
+            path: "./data/app.db".to_string(),
+            namespace: "rusty_bot".to_string(),
+            database: "app".to_string(),
+        }
+    }
+}
+
+/// Knowledge base sources
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KnowledgeBaseConfig {
+    /// Folder the documents are ingested from
+    pub context_folder: String,
+
+    /// Folder exported archives are written to
+    pub backup_folder: String,
+
+    /// Folder imported archives are kept in and restored from at every start
+    pub import_folder: String,
+}
+
+impl Default for KnowledgeBaseConfig {
+    fn default() -> Self {
+        Self {
+            context_folder: "./context".to_string(),
+            backup_folder: "./backups".to_string(),
+            import_folder: "./backups/imported".to_string(),
+        }
+    }
+}
+
+/// Document ingestion pipeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IngestionConfig {
+    /// Number of chunks embedded together in a single model call
+    /// Only applies to collections that do not use semantic chunking
+    pub batch_size: usize,
+
+    /// Maximum number of batches or documents processed at the same time
+    pub concurrency: usize,
+}
+
+impl Default for IngestionConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 32,
+            concurrency: 4,
+        }
+    }
+}
+
+/// Language and embedding models
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelConfig {
+    /// Model the chat sessions are created from
+    pub source: ModelSource,
+
+    /// Model the chunks and queries are embedded with
+    pub embedding: EmbeddingModelSource,
+}
+
+/// Language models the server can load
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ModelSource {
+    /// Qwen 2.5 7B Instruct
+    #[default]
+    #[serde(rename = "qwen-2.5-7b-instruct")]
+    Qwen25_7bInstruct,
+    /// Qwen 2.5 3B Instruct, for machines with less memory
+    #[serde(rename = "qwen-2.5-3b-instruct")]
+    Qwen25_3bInstruct,
+    /// Llama 3.1 8B Chat
+    #[serde(rename = "llama-3.1-8b-chat")]
+    Llama31_8bChat,
+    /// Phi 3.5 Mini Instruct
+    #[serde(rename = "phi-3.5-mini-instruct")]
+    Phi35MiniInstruct,
+}
+
+impl ModelSource {
+    /// Every supported model
+    pub const ALL: [ModelSource; 4] = [
+        ModelSource::Qwen25_7bInstruct,
+        ModelSource::Qwen25_3bInstruct,
+        ModelSource::Llama31_8bChat,
+        ModelSource::Phi35MiniInstruct,
+    ];
+
+    /// Identifier of the model, used in the configuration and the status endpoints
+    pub fn id(&self) -> &'static str {
+        match self {
+            ModelSource::Qwen25_7bInstruct => "qwen-2.5-7b-instruct",
+            ModelSource::Qwen25_3bInstruct => "qwen-2.5-3b-instruct",
+            ModelSource::Llama31_8bChat => "llama-3.1-8b-chat",
+            ModelSource::Phi35MiniInstruct => "phi-3.5-mini-instruct",
+        }
+    }
+
+    /// Finds the model with an identifier
+    pub fn from_id(id: &str) -> Option<ModelSource> {
+        Self::ALL.into_iter().find(|source| source.id() == id)
+    }
+}
+
+/// Embedding models the server can load
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EmbeddingModelSource {
+    /// Snowflake Arctic Embed XS
+    #[serde(rename = "snowflake-arctic-embed-xs")]
+    SnowflakeArcticEmbedExtraSmall,
+    /// Snowflake Arctic Embed S
+    #[default]
+    #[serde(rename = "snowflake-arctic-embed-s")]
+    SnowflakeArcticEmbedSmall,
+    /// Snowflake Arctic Embed M
+    #[serde(rename = "snowflake-arctic-embed-m")]
+    SnowflakeArcticEmbedMedium,
+    /// Snowflake Arctic Embed L
+    #[serde(rename = "snowflake-arctic-embed-l")]
+    SnowflakeArcticEmbedLarge,
+}
+
+impl EmbeddingModelSource {
+    /// Every supported embedding model
+    pub const ALL: [EmbeddingModelSource; 4] = [
+        EmbeddingModelSource::SnowflakeArcticEmbedExtraSmall,
+        EmbeddingModelSource::SnowflakeArcticEmbedSmall,
+        EmbeddingModelSource::SnowflakeArcticEmbedMedium,
+        EmbeddingModelSource::SnowflakeArcticEmbedLarge,
+    ];
+
+    /// Identifier of the model, stored with cached and exported vectors
+    pub fn id(&self) -> &'static str {
+        match self {
+            EmbeddingModelSource::SnowflakeArcticEmbedExtraSmall => "snowflake-arctic-embed-xs",
+            EmbeddingModelSource::SnowflakeArcticEmbedSmall => "snowflake-arctic-embed-s",
+            EmbeddingModelSource::SnowflakeArcticEmbedMedium => "snowflake-arctic-embed-m",
+            EmbeddingModelSource::SnowflakeArcticEmbedLarge => "snowflake-arctic-embed-l",
+        }
+    }
+
+    /// Finds the embedding model with an identifier
+    pub fn from_id(id: &str) -> Option<EmbeddingModelSource> {
+        Self::ALL.into_iter().find(|source| source.id() == id)
+    }
+}
+
+/// Sampling parameters of the chat responses
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SamplingConfig {
+    /// Controls randomness, higher is more random
+    pub temperature: f32,
+
+    /// Nucleus sampling parameter, higher is more diverse
+    pub top_p: f64,
+
+    /// Maximum response length in tokens
+    pub max_length: u32,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            temperature: 0.7,
+            top_p: 0.9,
+            max_length: 600,
+        }
+    }
+}
+
+/// Settings admins can change while the server runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuntimeSettings {
+    /// System prompt of new conversations, empty for none
+    pub persona: String,
+
+    /// Number of passages retrieved for a chat message by default
+    pub retrieval_top_k: usize,
+
+    /// Token quotas of every role
+    pub quotas: UsageQuotas,
+}
+
+impl Default for RuntimeSettings {
+    fn default() -> Self {
+        Self {
+            persona: String::new(),
+            retrieval_top_k: 1,
+            quotas: UsageQuotas::default(),
+        }
+    }
+}
+
+/// Configuration and runtime settings shown on the settings page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SettingsOverview {
+    /// File the configuration was read from, if it exists
+    pub config_file: Option<String>,
+
+    /// The configuration the server was started with
+    pub config: ServerConfig,
+
+    /// The current runtime settings
+    pub runtime: RuntimeSettings,
+}
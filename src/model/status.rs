@@ -0,0 +1,150 @@
+//! System Status Model Definitions
+//!
+//! This module defines the structures describing the state of the server
+//! components that must be initialized before the chatbot can answer: the
+//! language model, the chat session, the embedding model, the database
+//! connection and the document table.
+
+use serde::{Deserialize, Serialize};
+
+/// Initialization state of a server component
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ComponentState {
+    /// Initialization has not started
+    #[default]
+    Uninitialized,
+    /// Initialization is in progress
+    Loading,
+    /// The component can be used
+    Ready,
+    /// Initialization failed
+    Failed {
+        /// The error that made initialization fail
+        error: String,
+    },
+}
+
+impl ComponentState {
+    /// Whether the component can be used
+    pub fn is_ready(&self) -> bool {
+        matches!(self, ComponentState::Ready)
+    }
+}
+
+/// State of a single server component
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComponentStatus {
+    /// Name of the component, such as `MODEL` or `DB_CONN`
+    pub name: String,
+
+    /// Initialization state of the component
+    #[serde(flatten)]
+    pub state: ComponentState,
+}
+
+/// State of the server as reported by the readiness endpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SystemStatus {
+    /// Whether every component is ready, so chat requests can be served
+    pub ready: bool,
+
+    /// State of every component
+    pub components: Vec<ComponentStatus>,
+
+    /// Number of documents in the knowledge base, once the database is connected
+    pub document_count: Option<usize>,
+
+    /// Identifier of the language model
+    pub chat_model: String,
+
+    /// Identifier of the embedding model
+    pub embedding_model: String,
+}
+
+/// Loading stage of a model during startup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum LoadStage {
+    /// Loading has not started
+    #[default]
+    Pending,
+    /// The model files are being downloaded
+    Downloading,
+    /// The model is being loaded into memory
+    Loading,
+    /// The model is loaded
+    Ready,
+}
+
+/// Progress of the initialization run when the server starts
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InitProgress {
+    /// Loading stage of the language model
+    pub model_stage: LoadStage,
+
+    /// Progress of the current stage of the language model, between 0.0 and 1.0
+    pub model_progress: f32,
+
+    /// Loading stage of the embedding model
+    pub embedding_stage: LoadStage,
+
+    /// Progress of the current stage of the embedding model, between 0.0 and 1.0
+    pub embedding_progress: f32,
+
+    /// Number of documents ingested into the knowledge base so far
+    pub documents_ingested: usize,
+
+    /// Number of documents to ingest, once the context folder has been read
+    pub documents_total: Option<usize>,
+
+    /// Whether initialization has completed successfully
+    pub ready: bool,
+
+    /// The error that made initialization fail, if any
+    pub error: Option<String>,
+}
+
+impl InitProgress {
+    /// Overall progress between 0.0 and 1.0
+    ///
+    /// The language model dominates startup time, so it is weighted the most.
+    pub fn fraction(&self) -> f32 {
+        if self.ready {
+            return 1.0;
+        }
+        let stage_fraction = |stage: LoadStage, progress: f32| match stage {
+            LoadStage::Pending => 0.0,
+            LoadStage::Downloading => 0.8 * progress,
+            LoadStage::Loading => 0.8 + 0.2 * progress,
+            LoadStage::Ready => 1.0,
+        };
+        let documents = match self.documents_total {
+            Some(0) => 1.0,
+            Some(total) => self.documents_ingested as f32 / total as f32,
+            None => 0.0,
+        };
+
+        0.7 * stage_fraction(self.model_stage, self.model_progress)
+            + 0.1 * stage_fraction(self.embedding_stage, self.embedding_progress)
+            + 0.2 * documents
+    }
+
+    /// Describes what is currently being initialized
+    pub fn description(&self) -> String {
+        if let Some(error) = &self.error {
+            return format!("Initialization failed: {}", error);
+        }
+        let describe = |name: &str, stage: LoadStage, progress: f32| match stage {
+            LoadStage::Downloading => Some(format!("Downloading {} ({:.0}%)", name, progress * 100.0)),
+            LoadStage::Loading => Some(format!("Loading {}...", name)),
+            LoadStage::Pending | LoadStage::Ready => None,
+        };
+
+        describe("language model", self.model_stage, self.model_progress)
+            .or_else(|| describe("embedding model", self.embedding_stage, self.embedding_progress))
+            .or_else(|| self.documents_total.map(|total| {
+                format!("Indexing documents ({}/{})", self.documents_ingested, total)
+            }))
+            .unwrap_or_else(|| "Initializing...".to_string())
+    }
+}
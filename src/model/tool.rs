@@ -0,0 +1,22 @@
+//! Tool Model Definitions
+//!
+//! This module defines the data structures describing the tool calls made by
+//! the assistant before answering a message.
+
+use serde::{Deserialize, Serialize};
+
+/// A tool invocation made by the assistant and its result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolStep {
+    /// Name of the tool that was called
+    pub tool: String,
+
+    /// Arguments of the call, as a JSON object
+    pub arguments: String,
+
+    /// Output of the tool, or the error it returned
+    pub output: String,
+
+    /// Whether the call failed
+    pub is_error: bool,
+}
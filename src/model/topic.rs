@@ -0,0 +1,40 @@
+//! Topic Model Definitions
+//!
+//! This module defines the data structures describing the topics found by
+//! clustering the chunks of the knowledge base.
+
+use serde::{Deserialize, Serialize};
+use crate::model::document::DocumentChunk;
+
+/// A group of semantically related chunks labelled by the language model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Topic {
+    /// Short label describing the topic
+    pub label: String,
+
+    /// Number of chunks assigned to the topic
+    pub chunk_count: usize,
+
+    /// Titles of the documents with at least one chunk in the topic
+    pub documents: Vec<String>,
+
+    /// Chunks closest to the center of the topic
+    pub examples: Vec<DocumentChunk>,
+}
+
+impl Topic {
+    /// Number of documents covering the topic
+    pub fn document_count(&self) -> usize {
+        self.documents.len()
+    }
+}
+
+/// Overview of the topics covered by the knowledge base
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TopicMap {
+    /// Topics ordered from the largest to the smallest
+    pub topics: Vec<Topic>,
+
+    /// Total number of chunks that were clustered
+    pub chunk_count: usize,
+}
@@ -0,0 +1,119 @@
+//! Usage Model Definitions
+//!
+//! This module defines the structures describing the tokens consumed by every
+//! user, per conversation, and the daily and monthly quotas of each role.
+
+use serde::{Deserialize, Serialize};
+use crate::model::auth::Role;
+
+/// Token allowance of the accounts of a role
+///
+/// Prompt and generated tokens both count against the quotas.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageQuota {
+    /// Maximum number of tokens per UTC day, 0 for no limit
+    pub tokens_per_day: u64,
+
+    /// Maximum number of tokens per UTC calendar month, 0 for no limit
+    pub tokens_per_month: u64,
+}
+
+/// Quotas of every role
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageQuotas {
+    /// Quota of regular users
+    pub user: UsageQuota,
+
+    /// Quota of admins
+    pub admin: UsageQuota,
+}
+
+impl Default for UsageQuotas {
+    fn default() -> Self {
+        Self {
+            user: UsageQuota { tokens_per_day: 100_000, tokens_per_month: 2_000_000 },
+            admin: UsageQuota::default(),
+        }
+    }
+}
+
+impl UsageQuotas {
+    /// Quota applied to the accounts of a role
+    pub fn for_role(&self, role: Role) -> UsageQuota {
+        match role {
+            Role::User => self.user,
+            Role::Admin => self.admin,
+        }
+    }
+}
+
+/// Tokens consumed in a conversation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversationUsage {
+    /// Identifier of the conversation, `None` for calls made outside conversations
+    pub conversation: Option<u64>,
+
+    /// Number of calls to the language model
+    pub responses: u64,
+
+    /// Tokens of the prompts
+    pub prompt_tokens: u64,
+
+    /// Generated tokens
+    pub completion_tokens: u64,
+
+    /// Time of the last call in seconds since the Unix epoch
+    pub last_used_at: u64,
+}
+
+/// Consumption and remaining allowance of a user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserUsage {
+    /// The account the usage belongs to
+    pub username: String,
+
+    /// The role of the account, which sets its quota
+    pub role: Role,
+
+    /// The quota of the account
+    pub quota: UsageQuota,
+
+    /// Tokens consumed today, in UTC
+    pub tokens_today: u64,
+
+    /// Tokens consumed in the current month, in UTC
+    pub tokens_this_month: u64,
+
+    /// Prompt tokens consumed since the account was created
+    pub total_prompt_tokens: u64,
+
+    /// Generated tokens consumed since the account was created
+    pub total_completion_tokens: u64,
+
+    /// Usage of the most recent conversations, most recent first
+    pub conversations: Vec<ConversationUsage>,
+}
+
+impl UserUsage {
+    /// Tokens consumed since the account was created
+    pub fn total_tokens(&self) -> u64 {
+        self.total_prompt_tokens + self.total_completion_tokens
+    }
+
+    /// Tokens left today, `None` when there is no daily limit
+    pub fn remaining_today(&self) -> Option<u64> {
+        remaining(self.quota.tokens_per_day, self.tokens_today)
+    }
+
+    /// Tokens left this month, `None` when there is no monthly limit
+    pub fn remaining_this_month(&self) -> Option<u64> {
+        remaining(self.quota.tokens_per_month, self.tokens_this_month)
+    }
+}
+
+/// Tokens left under a limit, `None` for a limit of 0
+fn remaining(limit: u64, used: u64) -> Option<u64> {
+    (limit > 0).then(|| limit.saturating_sub(used))
+}
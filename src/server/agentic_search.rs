@@ -0,0 +1,148 @@
+//! Agentic Retrieval
+//!
+//! This module implements the automatic context mode, where the language model
+//! decides whether the knowledge base should be searched and with which queries.
+//! At each step the model sees the conversation, the latest message and the
+//! searches made so far, and its output is constrained to either a new search or
+//! the decision to answer. Searches stop after a fixed number of steps.
+//!
+//! Every search is recorded as a tool step so the user can see what the model
+//! looked for and what it found.
+
+use serde::Deserialize;
+use serde_json::json;
+use crate::model::chat::ChatMessage;
+use crate::model::document::{ContextSearch, SearchOptions, SimpleDocumentResult};
+use crate::model::tool::ToolStep;
+use crate::server::database_impl;
+use crate::server::llm::{complete_constrained, TokenUsage};
+use crate::server::query_rewrite::format_conversation;
+use crate::server::ranking::merge_query_results;
+use crate::server::logging;
+use tracing::info;
+
+/// Maximum number of searches made for a single message
+pub const MAX_SEARCH_STEPS: usize = 3;
+
+/// Name under which the searches appear in the trace
+const SEARCH_TOOL: &str = "search_knowledge_base";
+
+/// Maximum number of characters of each result shown to the model between steps
+const RESULT_PREVIEW_CHARS: usize = 300;
+
+/// Regular expression constraining the decision taken at each step
+const DECISION_PATTERN: &str = r#"\{"action": "search", "query": "[^"\\\n]{1,200}"\}|\{"action": "answer"\}"#;
+
+/// Instructions given to the model at each step
+const DECISION_SYSTEM_PROMPT: &str = "You decide whether a document search engine must be \
+    queried before answering the latest message of a conversation. Search when the answer \
+    depends on documents of the knowledge base; answer directly for greetings, small talk or \
+    general knowledge. If previous searches missed the information, search again with a \
+    different standalone query. Answer with {\"action\": \"search\", \"query\": \"...\"} or \
+    {\"action\": \"answer\"}.";
+
+/// A decision taken by the model
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum Decision {
+    Search { query: String },
+    Answer,
+}
+
+/// Lets the model search the knowledge base as many times as it needs, up to a limit
+///
+/// # Parameters
+/// * `history` - The recent conversation preceding the message, oldest first
+/// * `message` - The latest user message
+/// * `options` - The search options applied to every search
+/// * `max_steps` - Maximum number of searches
+/// * `usage` - Accumulates the tokens of the decisions and of the searches
+///
+/// # Returns
+/// * `Result<ContextSearch, String>` - The queries issued, the merged results and
+///   the trace of the searches, or an error message
+pub async fn agentic_search(
+    history: &[ChatMessage],
+    message: &str,
+    options: &SearchOptions,
+    max_steps: usize,
+    usage: &mut TokenUsage,
+) -> Result<ContextSearch, String> {
+    let mut queries: Vec<String> = Vec::new();
+    let mut result_sets: Vec<Vec<SimpleDocumentResult>> = Vec::new();
+    let mut steps = Vec::new();
+
+    while queries.len() < max_steps {
+        let prompt = build_decision_prompt(history, message, &queries, &result_sets);
+        let (output, decision_usage) = complete_constrained(DECISION_SYSTEM_PROMPT, &prompt, DECISION_PATTERN).await?;
+        *usage += decision_usage;
+        let query = match serde_json::from_str(&output) {
+            Ok(Decision::Search { query }) => query,
+            Ok(Decision::Answer) => break,
+            Err(e) => return Err(format!("Invalid decision {}: {}", output, e)),
+        };
+        // Searching the same query again would return the same results
+        if queries.contains(&query) {
+            break;
+        }
+
+        info!(query = %logging::content(&query), "Model searching the knowledge base");
+        let result = database_impl::query(&query, options, usage).await;
+        steps.push(ToolStep {
+            tool: SEARCH_TOOL.to_string(),
+            arguments: json!({ "query": query }).to_string(),
+            output: match &result {
+                Ok(results) => summarize_results(results),
+                Err(e) => e.clone(),
+            },
+            is_error: result.is_err(),
+        });
+        queries.push(query);
+        result_sets.push(result?);
+    }
+
+    Ok(ContextSearch {
+        queries,
+        results: merge_query_results(result_sets, options.top_k),
+        steps,
+    })
+}
+
+/// Builds the prompt of a step from the conversation and the searches made so far
+fn build_decision_prompt(
+    history: &[ChatMessage],
+    message: &str,
+    queries: &[String],
+    result_sets: &[Vec<SimpleDocumentResult>],
+) -> String {
+    let mut prompt = String::new();
+    if !history.is_empty() {
+        prompt.push_str(&format!("Conversation:\n{}\n\n", format_conversation(history)));
+    }
+    prompt.push_str(&format!("Latest message: {}\n", message));
+
+    for (query, results) in queries.iter().zip(result_sets) {
+        prompt.push_str(&format!("\nSearched for \"{}\":\n", query));
+        if results.is_empty() {
+            prompt.push_str("No results\n");
+        }
+        for result in results {
+            let preview: String = result.body.chars().take(RESULT_PREVIEW_CHARS).collect();
+            prompt.push_str(&format!("- {}: {}\n", result.title, preview));
+        }
+    }
+
+    prompt.push_str("\nNext action:");
+    prompt
+}
+
+/// Lists the titles and scores of the results of a search for the trace
+fn summarize_results(results: &[SimpleDocumentResult]) -> String {
+    if results.is_empty() {
+        return "No results".to_string();
+    }
+    results.iter()
+        .map(|result| format!("{} (score {:.4})", result.title, result.score))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,387 @@
+//! API Keys
+//!
+//! This module manages the API keys scripts use to call the server without a
+//! browser session. Keys are created by admins for an existing account, sent as
+//! bearer tokens and stored, like sessions, as a hash of their secret.
+//!
+//! Every key has two quotas, enforced in front of every server function calling
+//! the language or embedding model: requests per minute, counted over a sliding
+//! window kept in memory, and tokens per day, stored with the key so restarts do
+//! not reset it.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use axum::http::{header, HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+use crate::model::api_key::{ApiKeyInfo, NewApiKey};
+use crate::model::auth::UserInfo;
+use crate::server::app_store::get_app_db;
+use crate::server::auth::{self, now_secs, random_token, token_id};
+
+/// Name of the table holding the API keys
+const API_KEY_TABLE: &str = "api_key";
+
+/// Prefix of every API key secret, to recognize them in configuration files
+const KEY_PREFIX: &str = "rb_";
+
+/// Number of characters of the secret shown when listing the keys
+const DISPLAYED_PREFIX_LENGTH: usize = 10;
+
+/// Length of the window requests per minute are counted over
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Seconds in a day, used to find the current UTC day
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Time of the recent rate-limited requests of every key, by key id
+static RECENT_REQUESTS: Mutex<BTreeMap<String, VecDeque<Instant>>> = Mutex::new(BTreeMap::new());
+
+/// Reason a request made with an API key is rejected
+#[derive(Debug)]
+pub enum QuotaError {
+    /// The key is unknown or was revoked
+    InvalidKey,
+    /// A quota of the key is used up
+    Exceeded(String),
+    /// The usage of the key could not be read or stored
+    Internal(String),
+}
+
+impl QuotaError {
+    /// HTTP status of the rejected request
+    pub fn status(&self) -> StatusCode {
+        match self {
+            QuotaError::InvalidKey => StatusCode::UNAUTHORIZED,
+            QuotaError::Exceeded(_) => StatusCode::TOO_MANY_REQUESTS,
+            QuotaError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl fmt::Display for QuotaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotaError::InvalidKey => write!(f, "API key does not exist or was revoked"),
+            QuotaError::Exceeded(reason) => write!(f, "Rate limit exceeded: {}", reason),
+            QuotaError::Internal(e) => write!(f, "Error checking API key quotas: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for QuotaError {}
+
+/// A stored API key, keyed by the hash of its secret
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ApiKeyRecord {
+    name: String,
+    username: String,
+    prefix: String,
+    created_at: u64,
+    requests_per_minute: u32,
+    tokens_per_day: u64,
+    /// Day, in days since the Unix epoch, `tokens_today` was counted on
+    usage_day: u64,
+    tokens_today: u64,
+    total_requests: u64,
+    total_tokens: u64,
+}
+
+/// A stored API key together with its id
+#[derive(Debug, Deserialize)]
+struct ApiKeyRow {
+    id: String,
+    #[serde(flatten)]
+    record: ApiKeyRecord,
+}
+
+impl ApiKeyRecord {
+    /// Tokens counted for the current day
+    fn tokens_today(&self) -> u64 {
+        if self.usage_day == today() { self.tokens_today } else { 0 }
+    }
+
+    /// Converts the record into the description listed in the admin panel
+    fn into_info(self, id: String) -> ApiKeyInfo {
+        ApiKeyInfo {
+            requests_last_minute: recent_request_count(&id),
+            tokens_today: self.tokens_today(),
+            id,
+            name: self.name,
+            username: self.username,
+            prefix: self.prefix,
+            created_at: self.created_at,
+            requests_per_minute: self.requests_per_minute,
+            tokens_per_day: self.tokens_per_day,
+            total_requests: self.total_requests,
+            total_tokens: self.total_tokens,
+        }
+    }
+}
+
+/// Creates an API key for an account
+///
+/// # Parameters
+/// * `name` - Name describing what the key is used for
+/// * `username` - The account the requests made with the key are attributed to
+/// * `requests_per_minute` - Maximum generation and embedding requests per minute, 0 for no limit
+/// * `tokens_per_day` - Maximum tokens processed per day, 0 for no limit
+///
+/// # Returns
+/// * `Result<NewApiKey, String>` - The key with its secret, or an error message
+pub async fn create_key(name: &str, username: &str, requests_per_minute: u32, tokens_per_day: u64) -> Result<NewApiKey, String> {
+    if name.trim().is_empty() {
+        return Err("API keys need a name".to_string());
+    }
+    if auth::get_user(username).await?.is_none() {
+        return Err(format!("User {} does not exist", username));
+    }
+
+    let key = format!("{}{}", KEY_PREFIX, random_token());
+    let id = token_id(&key);
+    let record = ApiKeyRecord {
+        name: name.trim().to_string(),
+        username: username.to_string(),
+        prefix: key[..DISPLAYED_PREFIX_LENGTH].to_string(),
+        created_at: now_secs(),
+        requests_per_minute,
+        tokens_per_day,
+        usage_day: today(),
+        tokens_today: 0,
+        total_requests: 0,
+        total_tokens: 0,
+    };
+
+    let db = get_app_db().await?;
+    let _: Option<ApiKeyRecord> = db
+        .create((API_KEY_TABLE, id.as_str()))
+        .content(record.clone())
+        .await
+        .map_err(|e| {
+            error!("Error creating API key: {}", e);
+            e.to_string()
+        })?;
+
+    info!(name = %record.name, username, "API key created");
+    Ok(NewApiKey { info: record.into_info(id), key })
+}
+
+/// Lists the API keys with their quotas and usage
+///
+/// # Returns
+/// * `Result<Vec<ApiKeyInfo>, String>` - The keys, oldest first, or an error message
+pub async fn list_keys() -> Result<Vec<ApiKeyInfo>, String> {
+    let db = get_app_db().await?;
+    let rows: Vec<ApiKeyRow> = db
+        .query(format!(
+            "SELECT record::id(id) AS id, name, username, prefix, created_at, requests_per_minute, tokens_per_day, \
+                usage_day, tokens_today, total_requests, total_tokens FROM {} ORDER BY created_at",
+            API_KEY_TABLE
+        ))
+        .await
+        .and_then(|mut response| response.take(0))
+        .map_err(|e| {
+            error!("Error listing API keys: {}", e);
+            e.to_string()
+        })?;
+    Ok(rows.into_iter().map(|row| row.record.into_info(row.id)).collect())
+}
+
+/// Revokes an API key
+///
+/// # Parameters
+/// * `id` - Identifier of the key
+///
+/// # Returns
+/// * `Result<(), String>` - Success or an error message
+pub async fn revoke_key(id: &str) -> Result<(), String> {
+    let db = get_app_db().await?;
+    let deleted: Option<ApiKeyRecord> = db
+        .delete((API_KEY_TABLE, id))
+        .await
+        .map_err(|e| e.to_string())?;
+    if deleted.is_none() {
+        return Err("API key does not exist".to_string());
+    }
+    if let Ok(mut recent) = RECENT_REQUESTS.lock() {
+        recent.remove(id);
+    }
+    info!("API key revoked");
+    Ok(())
+}
+
+/// Revokes every API key of an account
+///
+/// # Parameters
+/// * `username` - The account whose keys are revoked
+///
+/// # Returns
+/// * `Result<(), String>` - Success or an error message
+pub async fn revoke_user_keys(username: &str) -> Result<(), String> {
+    let db = get_app_db().await?;
+    db.query(format!("DELETE {} WHERE username = $username", API_KEY_TABLE))
+        .bind(("username", username.to_string()))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Reads the bearer token of the `Authorization` header of a request
+pub fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    headers.get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(|token| token.trim().to_string())
+}
+
+/// Identifies the account an API key belongs to
+///
+/// # Parameters
+/// * `key` - The secret sent as a bearer token
+///
+/// # Returns
+/// * `Result<Option<UserInfo>, String>` - The account, `None` when the key is unknown, or an error message
+pub async fn authenticate_key(key: &str) -> Result<Option<UserInfo>, String> {
+    match load_key(&token_id(key)).await? {
+        Some(record) => auth::get_user(&record.username).await,
+        None => {
+            warn!("Request with an unknown API key");
+            Ok(None)
+        }
+    }
+}
+
+/// Checks that an API key has token allowance left for a request using `tokens` tokens
+///
+/// The request is rejected when the key is invalid or when the tokens would
+/// exceed its daily quota. Nothing is recorded, so the other quotas of the
+/// request can be checked before it is counted with `try_acquire`, which also
+/// enforces the request rate.
+///
+/// # Parameters
+/// * `key` - The secret sent as a bearer token
+/// * `tokens` - Tokens known to be processed by the request, such as the prompt
+///
+/// # Returns
+/// * `Result<(), QuotaError>` - Success, or the reason the request is rejected
+pub async fn check_quota(key: &str, tokens: u64) -> Result<(), QuotaError> {
+    let id = token_id(key);
+    let record = load_key(&id)
+        .await
+        .map_err(QuotaError::Internal)?
+        .ok_or(QuotaError::InvalidKey)?;
+
+    if record.tokens_per_day > 0 && record.tokens_today() + tokens > record.tokens_per_day {
+        warn!(key = %record.name, "Daily token quota exceeded");
+        return Err(QuotaError::Exceeded(format!(
+            "daily quota of {} tokens used ({} tokens today)",
+            record.tokens_per_day,
+            record.tokens_today()
+        )));
+    }
+    Ok(())
+}
+
+/// Counts a request made with an API key, using `tokens` tokens, unless the
+/// key has made `requests_per_minute` requests in the last minute
+///
+/// The rate is checked and the request added to the window under a single
+/// lock, so concurrent requests cannot exceed the rate together. Called once
+/// the other quotas of the request have been checked.
+///
+/// # Parameters
+/// * `key` - The secret sent as a bearer token
+/// * `tokens` - Tokens known to be processed by the request, such as the prompt
+///
+/// # Returns
+/// * `Result<(), QuotaError>` - Success, or the reason the request is rejected
+pub async fn try_acquire(key: &str, tokens: u64) -> Result<(), QuotaError> {
+    let id = token_id(key);
+    let record = load_key(&id)
+        .await
+        .map_err(QuotaError::Internal)?
+        .ok_or(QuotaError::InvalidKey)?;
+
+    {
+        let mut recent = RECENT_REQUESTS
+            .lock()
+            .map_err(|_| QuotaError::Internal("Error locking rate limiter".to_string()))?;
+        let requests = recent.entry(id.clone()).or_default();
+        let now = Instant::now();
+        while requests.front().is_some_and(|time| now.duration_since(*time) >= RATE_WINDOW) {
+            requests.pop_front();
+        }
+        if record.requests_per_minute > 0 && requests.len() >= record.requests_per_minute as usize {
+            warn!(key = %record.name, "Request rate limit exceeded");
+            return Err(QuotaError::Exceeded(format!(
+                "{} requests per minute",
+                record.requests_per_minute
+            )));
+        }
+        requests.push_back(now);
+    }
+
+    record_usage(&id, 1, tokens).await.map_err(QuotaError::Internal)
+}
+
+/// Adds tokens processed after a request was accepted, such as generated tokens
+///
+/// # Parameters
+/// * `key` - The secret sent as a bearer token
+/// * `tokens` - The tokens processed
+///
+/// # Returns
+/// * `Result<(), String>` - Success or an error message
+pub async fn charge_tokens(key: &str, tokens: u64) -> Result<(), String> {
+    record_usage(&token_id(key), 0, tokens).await
+}
+
+/// Adds requests and tokens to the usage stored with a key
+async fn record_usage(id: &str, requests: u64, tokens: u64) -> Result<(), String> {
+    let db = get_app_db().await?;
+    db.query(
+        "UPDATE type::thing($table, $id) SET \
+            tokens_today = IF usage_day = $day THEN tokens_today + $tokens ELSE $tokens END, \
+            usage_day = $day, \
+            total_requests += $requests, \
+            total_tokens += $tokens",
+    )
+        .bind(("table", API_KEY_TABLE))
+        .bind(("id", id.to_string()))
+        .bind(("day", today()))
+        .bind(("requests", requests))
+        .bind(("tokens", tokens))
+        .await
+        .map_err(|e| {
+            error!("Error recording API key usage: {}", e);
+            e.to_string()
+        })?;
+    Ok(())
+}
+
+/// Loads a stored API key
+async fn load_key(id: &str) -> Result<Option<ApiKeyRecord>, String> {
+    let db = get_app_db().await?;
+    db.select((API_KEY_TABLE, id))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Number of rate-limited requests made with a key in the last minute
+fn recent_request_count(id: &str) -> usize {
+    let now = Instant::now();
+    RECENT_REQUESTS.lock()
+        .ok()
+        .and_then(|recent| recent.get(id).map(|requests| {
+            requests.iter().filter(|time| now.duration_since(**time) < RATE_WINDOW).count()
+        }))
+        .unwrap_or(0)
+}
+
+/// Current UTC day in days since the Unix epoch
+fn today() -> u64 {
+    now_secs() / SECS_PER_DAY
+}
@@ -0,0 +1,40 @@
+//! Application Store Implementation
+//!
+//! This module manages the persistent application database. Unlike the document
+//! database, which is rebuilt from the context folder every time the server
+//! starts, the application database survives restarts and holds state that must
+//! not be lost, such as the configuration of the document collections.
+
+use surrealdb::Surreal;
+use surrealdb::engine::local::{Db, SurrealKv};
+use tokio::sync::OnceCell;
+use tracing::{error, info};
+use crate::server::config;
+
+/// Global singleton for the application database connection
+/// The connection is opened lazily the first time it is needed
+static APP_DB: OnceCell<Surreal<Db>> = OnceCell::const_new();
+
+/// Gets a handle to the application database, opening it on first use
+///
+/// Returns the connection on success or an error message on failure
+pub async fn get_app_db() -> Result<Surreal<Db>, String> {
+    APP_DB
+        .get_or_try_init(|| async {
+            let settings = &config::get().app_database;
+            info!(path = %settings.path, "Opening application database...");
+            let db = Surreal::new::<SurrealKv>(settings.path.as_str())
+                .await
+                .map_err(|e| e.to_string())?;
+            db.use_ns(&settings.namespace).use_db(&settings.database)
+                .await
+                .map_err(|e| {
+                    error!("Error using namespace and database: {}", e);
+                    e.to_string()
+                })?;
+            info!("Application database opened successfully");
+            Ok(db)
+        })
+        .await
+        .cloned()
+}
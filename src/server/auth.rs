@@ -0,0 +1,350 @@
+//! Authentication
+//!
+//! This module manages the local user accounts and their login sessions, both
+//! stored in the persistent application database. Passwords are hashed with
+//! Argon2 and sessions are identified by a random token sent to the browser in
+//! an HTTP-only cookie; only a hash of the token is stored, so a copy of the
+//! database cannot be used to hijack sessions. Expired sessions are deleted
+//! when users log in.
+
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::http::{header, HeaderMap};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{error, info, warn};
+use crate::model::auth::{Role, UserInfo};
+use crate::server::api_keys;
+use crate::server::app_store::get_app_db;
+
+/// Name of the table holding the user accounts
+const ACCOUNT_TABLE: &str = "account";
+
+/// Name of the table holding the login sessions
+const SESSION_TABLE: &str = "session";
+
+/// Name of the cookie holding the session token
+pub const SESSION_COOKIE: &str = "rusty_bot_session";
+
+/// Time a session stays valid after logging in, in seconds
+pub const SESSION_DURATION_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Minimum length of a password
+const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Hash of a random password, checked for unknown usernames so that logging in
+/// takes as long whether or not the account exists
+static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+
+/// A stored user account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AccountRecord {
+    username: String,
+    /// Argon2 hash of the password in PHC string format
+    password_hash: String,
+    role: Role,
+    /// Creation time in seconds since the Unix epoch
+    created_at: u64,
+}
+
+/// A stored login session, keyed by the hash of its token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SessionRecord {
+    username: String,
+    /// Expiration time in seconds since the Unix epoch
+    expires_at: u64,
+}
+
+/// Creates a user account
+///
+/// # Parameters
+/// * `username` - The name the user logs in with
+/// * `password` - The password of the account
+/// * `role` - The role of the account
+///
+/// # Returns
+/// * `Result<UserInfo, String>` - The new account or an error message
+pub async fn create_user(username: &str, password: &str, role: Role) -> Result<UserInfo, String> {
+    let username = username.trim();
+    if username.is_empty() || !username.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.') {
+        return Err("Usernames may only contain letters, digits, '.', '-' and '_'".to_string());
+    }
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(format!("Passwords must have at least {} characters", MIN_PASSWORD_LENGTH));
+    }
+
+    let db = get_app_db().await?;
+    let existing: Option<AccountRecord> = db
+        .select((ACCOUNT_TABLE, username))
+        .await
+        .map_err(|e| e.to_string())?;
+    if existing.is_some() {
+        return Err(format!("User {} already exists", username));
+    }
+
+    let record = AccountRecord {
+        username: username.to_string(),
+        password_hash: hash_password(password)?,
+        role,
+        created_at: now_secs(),
+    };
+    let _: Option<AccountRecord> = db
+        .create((ACCOUNT_TABLE, username))
+        .content(record)
+        .await
+        .map_err(|e| {
+            error!("Error creating user: {}", e);
+            e.to_string()
+        })?;
+
+    info!(username, ?role, "User created");
+    Ok(UserInfo { username: username.to_string(), role })
+}
+
+/// Lists the user accounts
+///
+/// # Returns
+/// * `Result<Vec<UserInfo>, String>` - The accounts sorted by name or an error message
+pub async fn list_users() -> Result<Vec<UserInfo>, String> {
+    let db = get_app_db().await?;
+    let records: Vec<AccountRecord> = db.select(ACCOUNT_TABLE).await.map_err(|e| e.to_string())?;
+    let mut users: Vec<UserInfo> = records.into_iter()
+        .map(|record| UserInfo { username: record.username, role: record.role })
+        .collect();
+    users.sort_by(|a, b| a.username.cmp(&b.username));
+    Ok(users)
+}
+
+/// Gets a user account
+///
+/// # Parameters
+/// * `username` - The name of the account
+///
+/// # Returns
+/// * `Result<Option<UserInfo>, String>` - The account, `None` when it does not exist, or an error message
+pub async fn get_user(username: &str) -> Result<Option<UserInfo>, String> {
+    let db = get_app_db().await?;
+    let account: Option<AccountRecord> = db
+        .select((ACCOUNT_TABLE, username))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(account.map(|account| UserInfo { username: account.username, role: account.role }))
+}
+
+/// Deletes a user account, logs out all its sessions and revokes its API keys
+///
+/// # Parameters
+/// * `username` - The account to delete
+///
+/// # Returns
+/// * `Result<(), String>` - Success or an error message
+pub async fn delete_user(username: &str) -> Result<(), String> {
+    let db = get_app_db().await?;
+    let deleted: Option<AccountRecord> = db
+        .delete((ACCOUNT_TABLE, username))
+        .await
+        .map_err(|e| e.to_string())?;
+    if deleted.is_none() {
+        return Err(format!("User {} does not exist", username));
+    }
+
+    db.query(format!("DELETE {} WHERE username = $username", SESSION_TABLE))
+        .bind(("username", username.to_string()))
+        .await
+        .map_err(|e| e.to_string())?;
+    api_keys::revoke_user_keys(username).await?;
+    info!(username, "User deleted");
+    Ok(())
+}
+
+/// Checks a username and password and opens a session
+///
+/// # Parameters
+/// * `username` - The name the user logs in with
+/// * `password` - The password of the account
+///
+/// # Returns
+/// * `Result<(UserInfo, String), String>` - The account and the session token, or an error message
+pub async fn login(username: &str, password: &str) -> Result<(UserInfo, String), String> {
+    let db = get_app_db().await?;
+    purge_expired_sessions().await?;
+    let account: Option<AccountRecord> = db
+        .select((ACCOUNT_TABLE, username.trim()))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // A password is verified for unknown users too, so both take as long
+    let password_hash = match &account {
+        Some(account) => account.password_hash.clone(),
+        None => dummy_hash()?,
+    };
+    let is_valid = verify_password(password, &password_hash);
+
+    // The same message is returned for unknown users and wrong passwords
+    let account = match account {
+        Some(account) if is_valid => account,
+        _ => {
+            warn!(username, "Failed login");
+            return Err("Invalid username or password".to_string());
+        }
+    };
+
+    let token = random_token();
+    let session = SessionRecord {
+        username: account.username.clone(),
+        expires_at: now_secs() + SESSION_DURATION_SECS,
+    };
+    let _: Option<SessionRecord> = db
+        .create((SESSION_TABLE, token_id(&token)))
+        .content(session)
+        .await
+        .map_err(|e| {
+            error!("Error creating session: {}", e);
+            e.to_string()
+        })?;
+
+    info!(username = %account.username, "User logged in");
+    Ok((UserInfo { username: account.username, role: account.role }, token))
+}
+
+/// Closes a session
+///
+/// # Parameters
+/// * `token` - The session token
+///
+/// # Returns
+/// * `Result<(), String>` - Success or an error message
+pub async fn logout(token: &str) -> Result<(), String> {
+    let db = get_app_db().await?;
+    let _: Option<SessionRecord> = db
+        .delete((SESSION_TABLE, token_id(token)))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Deletes the sessions that have expired
+///
+/// # Returns
+/// * `Result<(), String>` - Success or an error message
+pub async fn purge_expired_sessions() -> Result<(), String> {
+    let db = get_app_db().await?;
+    db.query(format!("DELETE {} WHERE expires_at <= $now", SESSION_TABLE))
+        .bind(("now", now_secs()))
+        .await
+        .map_err(|e| {
+            error!("Error deleting expired sessions: {}", e);
+            e.to_string()
+        })?;
+    Ok(())
+}
+
+/// Identifies the user making a request from its session cookie or API key
+///
+/// Requests from browsers carry the session cookie, while scripts send an API
+/// key as a bearer token in the `Authorization` header. The API key is checked
+/// when the session cookie is missing, unknown or expired.
+///
+/// # Parameters
+/// * `headers` - The headers of the request
+///
+/// # Returns
+/// * `Result<Option<UserInfo>, String>` - The user, `None` when the request carries no valid credentials, or an error message
+pub async fn authenticate(headers: &HeaderMap) -> Result<Option<UserInfo>, String> {
+    if let Some(token) = session_token(headers) {
+        if let Some(user) = session_user(&token).await? {
+            return Ok(Some(user));
+        }
+    }
+
+    match api_keys::bearer_token(headers) {
+        Some(key) => api_keys::authenticate_key(&key).await,
+        None => Ok(None),
+    }
+}
+
+/// Gets the user of a session, removing the session when it has expired
+async fn session_user(token: &str) -> Result<Option<UserInfo>, String> {
+    let db = get_app_db().await?;
+    let session: Option<SessionRecord> = db
+        .select((SESSION_TABLE, token_id(token)))
+        .await
+        .map_err(|e| e.to_string())?;
+    let Some(session) = session else {
+        return Ok(None);
+    };
+    if session.expires_at <= now_secs() {
+        logout(token).await?;
+        return Ok(None);
+    }
+
+    // The account may have been deleted since the session was opened
+    get_user(&session.username).await
+}
+
+/// Reads the session token from the cookies of a request
+pub fn session_token(headers: &HeaderMap) -> Option<String> {
+    headers.get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value.to_string())
+}
+
+/// Builds the `Set-Cookie` value storing a session token in the browser
+///
+/// An empty token with a zero lifetime removes the cookie.
+pub fn session_cookie(token: &str, max_age_secs: u64) -> String {
+    format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Strict; Max-Age={}",
+        SESSION_COOKIE, token, max_age_secs
+    )
+}
+
+/// Hashes a password with Argon2 and a random salt
+fn hash_password(password: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Returns the hash checked for unknown usernames, computed on first use
+fn dummy_hash() -> Result<String, String> {
+    if let Some(hash) = DUMMY_HASH.get() {
+        return Ok(hash.clone());
+    }
+    let hash = hash_password(&random_token())?;
+    Ok(DUMMY_HASH.get_or_init(|| hash).clone())
+}
+
+/// Checks a password against its stored Argon2 hash
+fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+}
+
+/// Generates a random token of 32 bytes encoded as hexadecimal
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Returns the identifier a token is stored under, a SHA-256 hash of the token
+pub fn token_id(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Returns the current time in seconds since the Unix epoch
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
@@ -0,0 +1,353 @@
+//! Knowledge Base Backup
+//!
+//! This module exports the knowledge base into a portable archive and imports
+//! such archives, so a corpus embedded on one machine can be shipped to another
+//! without computing the embeddings again.
+//!
+//! An archive is a JSONL file. The first line is a header naming the embedding
+//! model and the vector dimensions, followed by one line per document with its
+//! metadata and text, and one line per chunk with its offsets, text and vector.
+//! Exports read the vectors stored with the chunks, so nothing is embedded.
+//!
+//! Importing validates the whole archive and keeps a copy of it in the import
+//! folder. The document table is rebuilt at every start, so the archives of the
+//! import folder are restored into it after the context folder is ingested:
+//! their chunks are inserted with their vectors and their documents keep their
+//! title, source, collection and ingestion time. The chunks are only embedded
+//! again when the archive was embedded with a different model than the running
+//! one.
+
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::model::backup::ArchiveSummary;
+use crate::server::config;
+use crate::server::database_impl::{self, ChunkRow, DocumentRecord};
+use crate::server::embedding::{embed_texts, embedding_model_id};
+use tracing::info;
+
+/// Version of the archive format written by this module
+const ARCHIVE_FORMAT_VERSION: u32 = 2;
+
+/// A line of a knowledge base archive
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ArchiveRecord {
+    /// First line of the archive, describing the embeddings it contains
+    Header {
+        format_version: u32,
+        model_id: String,
+        dimensions: usize,
+    },
+    /// A document with its metadata and full text
+    Document {
+        id: usize,
+        title: String,
+        source: String,
+        collection: String,
+        ingested_at: String,
+        body: String,
+    },
+    /// A chunk of a document with its byte range and embedding vector
+    Chunk {
+        document_id: usize,
+        position: usize,
+        start: usize,
+        end: usize,
+        text: String,
+        vector: Vec<f32>,
+    },
+}
+
+/// The validated contents of an archive
+struct Archive {
+    /// Identifier of the embedding model the vectors were computed with
+    model_id: String,
+    /// Number of dimensions of the vectors
+    dimensions: usize,
+    /// The documents with their chunks, in archive order
+    documents: Vec<(DocumentRecord, Vec<ChunkRow>)>,
+    /// Whether the vectors were computed again with the running model
+    reembedded: bool,
+}
+
+impl Archive {
+    /// Number of chunks in the archive
+    fn chunk_count(&self) -> usize {
+        self.documents.iter().map(|(_, chunks)| chunks.len()).sum()
+    }
+
+    /// Summarizes the archive stored at the given path
+    fn summary(&self, path: &Path) -> ArchiveSummary {
+        ArchiveSummary {
+            path: path.display().to_string(),
+            model_id: self.model_id.clone(),
+            dimensions: self.dimensions,
+            documents: self.documents.len(),
+            chunks: self.chunk_count(),
+            reembedded: self.reembedded,
+        }
+    }
+}
+
+/// Exports the knowledge base into a new archive in the backup folder
+///
+/// The chunk vectors are the ones stored in the document table, so exporting
+/// does not run the embedding model.
+///
+/// # Returns
+/// * `Result<ArchiveSummary, String>` - The path and contents of the archive or an error message
+pub async fn export_archive() -> Result<ArchiveSummary, String> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_secs();
+    let path = Path::new(&config::get().knowledge_base.backup_folder).join(format!("knowledge_base-{}.jsonl", timestamp));
+    export_archive_to(&path).await
+}
+
+/// Exports the knowledge base into an archive at the given path
+///
+/// # Parameters
+/// * `path` - Where to write the archive
+///
+/// # Returns
+/// * `Result<ArchiveSummary, String>` - The contents of the archive or an error message
+pub async fn export_archive_to(path: &Path) -> Result<ArchiveSummary, String> {
+    let mut documents = Vec::new();
+    for document in database_impl::list_document_records().await? {
+        let chunks = database_impl::list_document_chunks(document.id).await?;
+        documents.push((document, chunks));
+    }
+
+    let dimensions = match documents.iter().flat_map(|(_, chunks)| chunks).next() {
+        Some(chunk) => chunk.embedding.len(),
+        None => model_dimensions().await?,
+    };
+    let archive = Archive {
+        model_id: embedding_model_id().to_string(),
+        dimensions,
+        documents,
+        reembedded: false,
+    };
+    write_archive(path, &archive)?;
+
+    info!("Exported {} documents and {} chunks to {}", archive.documents.len(), archive.chunk_count(), path.display());
+    Ok(archive.summary(path))
+}
+
+/// Imports an archive into the import folder
+///
+/// The whole archive is validated before it is kept: every vector must have the
+/// dimensions of the header and every chunk must belong to a document of the
+/// archive. When the archive was embedded with another model than the running
+/// one, the chunks are embedded again and the archive is kept with the new
+/// vectors. Importing an archive with the same file name replaces it.
+///
+/// The documents are only added to the document table by [`restore_archive`],
+/// which the server runs for every imported archive when it starts.
+///
+/// # Parameters
+/// * `path` - Path of the archive
+///
+/// # Returns
+/// * `Result<ArchiveSummary, String>` - The path of the kept copy and the contents of the archive, or an error message
+pub async fn import_archive(path: &Path) -> Result<ArchiveSummary, String> {
+    let archive = read_archive(path).await?;
+    let file_name = path.file_name().ok_or(format!("{} is not a file", path.display()))?;
+    let imported = Path::new(&config::get().knowledge_base.import_folder).join(file_name);
+    write_archive(&imported, &archive)?;
+
+    info!(
+        "Imported {} documents and {} chunks from {}",
+        archive.documents.len(), archive.chunk_count(), path.display()
+    );
+    Ok(archive.summary(&imported))
+}
+
+/// Restores the documents of an archive into the running document table
+///
+/// # Parameters
+/// * `path` - Path of the archive
+///
+/// # Returns
+/// * `Result<ArchiveSummary, String>` - The contents of the restored archive or an error message
+pub async fn restore_archive(path: &Path) -> Result<ArchiveSummary, String> {
+    let archive = read_archive(path).await?;
+    let summary = archive.summary(path);
+    database_impl::restore_documents(archive.documents).await?;
+
+    info!("Restored {} documents and {} chunks from {}", summary.documents, summary.chunks, path.display());
+    Ok(summary)
+}
+
+/// Restores every archive of the import folder into the document table
+///
+/// # Returns
+/// * `Result<(), String>` - Success or an error message
+pub async fn restore_imported_archives() -> Result<(), String> {
+    let folder = Path::new(&config::get().knowledge_base.import_folder);
+    if !folder.exists() {
+        return Ok(());
+    }
+
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(folder)
+        .map_err(|e| format!("Error reading {}: {}", folder.display(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "jsonl"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        restore_archive(&path).await?;
+    }
+    Ok(())
+}
+
+/// Reads and validates an archive
+///
+/// The vectors are embedded again with the running model when the archive was
+/// embedded with another one; otherwise their dimensions must match the model.
+async fn read_archive(path: &Path) -> Result<Archive, String> {
+    let mut records = read_records(path)?.into_iter();
+
+    let (model_id, dimensions) = match records.next() {
+        Some(ArchiveRecord::Header { format_version, model_id, dimensions }) => {
+            if format_version != ARCHIVE_FORMAT_VERSION {
+                return Err(format!("Unsupported archive format version {}", format_version));
+            }
+            (model_id, dimensions)
+        }
+        _ => return Err("The archive does not start with a header".to_string()),
+    };
+
+    let mut documents: Vec<(DocumentRecord, Vec<ChunkRow>)> = Vec::new();
+    for record in records {
+        match record {
+            ArchiveRecord::Header { .. } => return Err("The archive has more than one header".to_string()),
+            ArchiveRecord::Document { id, title, source, collection, ingested_at, body } => {
+                let document = DocumentRecord { id, title, source, collection, body, ingested_at };
+                documents.push((document, Vec::new()));
+            }
+            ArchiveRecord::Chunk { document_id, position, start, end, text, vector } => {
+                if vector.len() != dimensions {
+                    return Err(format!(
+                        "Chunk {} of document {} has {} dimensions instead of {}",
+                        position, document_id, vector.len(), dimensions
+                    ));
+                }
+                let Some((document, chunks)) = documents.iter_mut().rev().find(|(document, _)| document.id == document_id) else {
+                    return Err(format!("Chunk {} belongs to unknown document {}", position, document_id));
+                };
+                chunks.push(ChunkRow {
+                    document_id,
+                    position,
+                    collection: document.collection.clone(),
+                    title: document.title.clone(),
+                    start,
+                    end,
+                    text,
+                    embedding: vector,
+                });
+            }
+        }
+    }
+
+    let mut archive = Archive { model_id, dimensions, documents, reembedded: false };
+    if archive.model_id == embedding_model_id() {
+        let expected_dimensions = model_dimensions().await?;
+        if archive.dimensions != expected_dimensions {
+            return Err(format!(
+                "The archive has {}-dimensional vectors but the embedding model produces {}",
+                archive.dimensions, expected_dimensions
+            ));
+        }
+    } else {
+        reembed_archive(&mut archive).await?;
+    }
+    Ok(archive)
+}
+
+/// Embeds the chunks of an archive again with the running embedding model
+async fn reembed_archive(archive: &mut Archive) -> Result<(), String> {
+    info!(
+        "The archive was embedded with {} but this server uses {}, embedding its chunks again",
+        archive.model_id, embedding_model_id()
+    );
+    for (_, chunks) in &mut archive.documents {
+        let vectors = embed_texts(chunks.iter().map(|chunk| chunk.text.clone()).collect()).await?;
+        for (chunk, vector) in chunks.iter_mut().zip(vectors) {
+            chunk.embedding = vector;
+        }
+    }
+
+    archive.model_id = embedding_model_id().to_string();
+    archive.dimensions = model_dimensions().await?;
+    archive.reembedded = true;
+    Ok(())
+}
+
+/// Writes an archive as JSON lines
+fn write_archive(path: &Path, archive: &Archive) -> Result<(), String> {
+    let mut records = vec![ArchiveRecord::Header {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        model_id: archive.model_id.clone(),
+        dimensions: archive.dimensions,
+    }];
+    for (document, chunks) in &archive.documents {
+        records.push(ArchiveRecord::Document {
+            id: document.id,
+            title: document.title.clone(),
+            source: document.source.clone(),
+            collection: document.collection.clone(),
+            ingested_at: document.ingested_at.clone(),
+            body: document.body.clone(),
+        });
+        records.extend(chunks.iter().map(|chunk| ArchiveRecord::Chunk {
+            document_id: document.id,
+            position: chunk.position,
+            start: chunk.start,
+            end: chunk.end,
+            text: chunk.text.clone(),
+            vector: chunk.embedding.clone(),
+        }));
+    }
+    write_records(path, records.iter())
+}
+
+/// Number of dimensions of the vectors produced by the running embedding model
+async fn model_dimensions() -> Result<usize, String> {
+    let probe = embed_texts(vec!["dimension probe".to_string()]).await?;
+    probe.first()
+        .map(|vector| vector.len())
+        .ok_or("The embedding model returned no vector".to_string())
+}
+
+/// Writes the archive records as JSON lines
+fn write_records<'a>(path: &Path, records: impl Iterator<Item = &'a ArchiveRecord>) -> Result<(), String> {
+    if let Some(folder) = path.parent() {
+        std::fs::create_dir_all(folder).map_err(|e| e.to_string())?;
+    }
+    let file = std::fs::File::create(path).map_err(|e| format!("Error creating archive: {}", e))?;
+    let mut writer = BufWriter::new(file);
+    for record in records {
+        serde_json::to_writer(&mut writer, record).map_err(|e| e.to_string())?;
+        writer.write_all(b"\n").map_err(|e| e.to_string())?;
+    }
+    writer.flush().map_err(|e| e.to_string())
+}
+
+/// Reads the archive records, skipping blank lines
+fn read_records(path: &Path) -> Result<Vec<ArchiveRecord>, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("Error opening archive: {}", e))?;
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .map(|(number, line)| {
+            let line = line.map_err(|e| e.to_string())?;
+            serde_json::from_str(&line).map_err(|e| format!("Invalid record on line {}: {}", number + 1, e))
+        })
+        .collect()
+}
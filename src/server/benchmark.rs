@@ -0,0 +1,100 @@
+//! Generation Benchmark
+//!
+//! This module runs a suite of prompts through the chat model and measures each
+//! response like the server does, to compare models, quantizations or machines.
+//! The chat session is reset before every prompt so the responses do not depend
+//! on each other.
+//!
+//! Prompt suites are text files with one prompt per line; blank lines and lines
+//! starting with `#` are ignored.
+
+use std::path::Path;
+use futures::StreamExt;
+use serde::Serialize;
+use crate::model::metrics::{GenerationMetrics, GenerationSummary};
+use crate::server::generation_metrics::{summarize, GenerationTimer};
+use crate::server::llm;
+
+/// Key of the chat session the benchmark prompts are answered in
+const BENCHMARK_SESSION: &str = "benchmark";
+
+/// Measurements of one response of the benchmark
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkRun {
+    pub prompt: String,
+    pub metrics: GenerationMetrics,
+    pub tokens_per_second: f64,
+}
+
+/// Results of a benchmark
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkReport {
+    pub summary: GenerationSummary,
+    pub runs: Vec<BenchmarkRun>,
+}
+
+/// Loads the prompts of a suite file
+///
+/// # Parameters
+/// * `path` - Path of the prompt suite
+///
+/// # Returns
+/// * `Result<Vec<String>, String>` - The prompts or an error message
+pub fn load_prompt_suite(path: &Path) -> Result<Vec<String>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+    let prompts: Vec<String> = content.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+
+    if prompts.is_empty() {
+        return Err(format!("{} contains no prompts", path.display()));
+    }
+    Ok(prompts)
+}
+
+/// Generates a response to every prompt `repetitions` times and measures them
+///
+/// # Parameters
+/// * `prompts` - The prompt suite
+/// * `repetitions` - Number of responses generated for each prompt
+///
+/// # Returns
+/// * `Result<BenchmarkReport, String>` - The measurements and their summary or an error message
+pub async fn run_benchmark(prompts: &[String], repetitions: usize) -> Result<BenchmarkReport, String> {
+    let mut runs = Vec::with_capacity(prompts.len() * repetitions);
+    for repetition in 0..repetitions {
+        for (index, prompt) in prompts.iter().enumerate() {
+            llm::reset_chat(BENCHMARK_SESSION).await?;
+
+            let mut timer = GenerationTimer::start(llm::count_tokens(prompt)?);
+            let (_, mut stream) = llm::try_get_stream(BENCHMARK_SESSION, prompt).await?;
+            while stream.next().await.is_some() {
+                timer.on_token();
+            }
+            let metrics = timer.finish();
+
+            println!(
+                "[{}/{}] {:.0} ms to first token, {:.1} tokens/s, {} completion tokens",
+                repetition * prompts.len() + index + 1,
+                prompts.len() * repetitions,
+                metrics.time_to_first_token_ms,
+                metrics.tokens_per_second(),
+                metrics.completion_tokens
+            );
+            runs.push(BenchmarkRun {
+                prompt: prompt.clone(),
+                tokens_per_second: metrics.tokens_per_second(),
+                metrics,
+            });
+        }
+    }
+
+    let metrics: Vec<GenerationMetrics> = runs.iter().map(|run| run.metrics.clone()).collect();
+    Ok(BenchmarkReport {
+        summary: summarize(&metrics),
+        runs,
+    })
+}
@@ -0,0 +1,208 @@
+//! Calculator Tool
+//!
+//! This module provides the calculator tool of the assistant. Language models
+//! are unreliable at arithmetic, so expressions are evaluated exactly by a small
+//! recursive descent parser supporting `+`, `-`, `*`, `/`, `%`, `^`, parentheses,
+//! unary minus, the `pi` and `e` constants and the `sqrt`, `abs`, `ln`, `log`,
+//! `sin`, `cos` and `tan` functions.
+
+use futures::future::BoxFuture;
+use serde_json::{json, Value};
+use crate::server::tools::Tool;
+
+/// Tool evaluating arithmetic expressions
+pub struct Calculator;
+
+impl Tool for Calculator {
+    fn name(&self) -> &'static str {
+        "calculator"
+    }
+
+    fn description(&self) -> &'static str {
+        "Evaluates an arithmetic expression such as (3 + 4) * 2 ^ 3 or sqrt(16) / 2."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "expression": { "type": "string", "description": "The expression to evaluate" }
+            },
+            "required": ["expression"]
+        })
+    }
+
+    fn call(&self, arguments: Value) -> BoxFuture<'_, Result<String, String>> {
+        Box::pin(async move {
+            let expression = arguments["expression"].as_str().unwrap_or_default();
+            evaluate(expression).map(|value| value.to_string())
+        })
+    }
+}
+
+/// Evaluates an arithmetic expression
+///
+/// # Parameters
+/// * `expression` - The expression to evaluate
+///
+/// # Returns
+/// * `Result<f64, String>` - The value of the expression or a description of the syntax error
+pub fn evaluate(expression: &str) -> Result<f64, String> {
+    let mut parser = ExpressionParser {
+        chars: expression.chars().filter(|c| !c.is_whitespace()).collect(),
+        position: 0,
+    };
+    let value = parser.parse_sum()?;
+    if parser.position < parser.chars.len() {
+        return Err(format!("Unexpected character '{}'", parser.chars[parser.position]));
+    }
+    if !value.is_finite() {
+        return Err("The result is not a finite number".to_string());
+    }
+    Ok(value)
+}
+
+/// Recursive descent parser evaluating the expression as it is read
+struct ExpressionParser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl ExpressionParser {
+    /// Returns the next character without consuming it
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    /// Consumes the next character if it is `expected`
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// sum := product (('+' | '-') product)*
+    fn parse_sum(&mut self) -> Result<f64, String> {
+        let mut value = self.parse_product()?;
+        loop {
+            if self.eat('+') {
+                value += self.parse_product()?;
+            } else if self.eat('-') {
+                value -= self.parse_product()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// product := power (('*' | '/' | '%') power)*
+    fn parse_product(&mut self) -> Result<f64, String> {
+        let mut value = self.parse_power()?;
+        loop {
+            if self.eat('*') {
+                value *= self.parse_power()?;
+            } else if self.eat('/') {
+                let divisor = self.parse_power()?;
+                if divisor == 0.0 {
+                    return Err("Division by zero".to_string());
+                }
+                value /= divisor;
+            } else if self.eat('%') {
+                let divisor = self.parse_power()?;
+                if divisor == 0.0 {
+                    return Err("Division by zero".to_string());
+                }
+                value %= divisor;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// power := unary ('^' power)?, right associative
+    fn parse_power(&mut self) -> Result<f64, String> {
+        let base = self.parse_unary()?;
+        if self.eat('^') {
+            Ok(base.powf(self.parse_power()?))
+        } else {
+            Ok(base)
+        }
+    }
+
+    /// unary := ('-' | '+') unary | atom
+    fn parse_unary(&mut self) -> Result<f64, String> {
+        if self.eat('-') {
+            Ok(-self.parse_unary()?)
+        } else if self.eat('+') {
+            self.parse_unary()
+        } else {
+            self.parse_atom()
+        }
+    }
+
+    /// atom := number | '(' sum ')' | function '(' sum ')'
+    fn parse_atom(&mut self) -> Result<f64, String> {
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let value = self.parse_sum()?;
+                if !self.eat(')') {
+                    return Err("Missing closing parenthesis".to_string());
+                }
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.parse_number(),
+            Some(c) if c.is_ascii_alphabetic() => self.parse_function(),
+            Some(c) => Err(format!("Unexpected character '{}'", c)),
+            None => Err("Unexpected end of expression".to_string()),
+        }
+    }
+
+    /// Reads a decimal number
+    fn parse_number(&mut self) -> Result<f64, String> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+            self.position += 1;
+        }
+        let number: String = self.chars[start..self.position].iter().collect();
+        number.parse::<f64>().map_err(|_| format!("Invalid number '{}'", number))
+    }
+
+    /// Reads a function call or a named constant
+    fn parse_function(&mut self) -> Result<f64, String> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.position += 1;
+        }
+        let name: String = self.chars[start..self.position].iter().collect();
+
+        match name.as_str() {
+            "pi" => return Ok(std::f64::consts::PI),
+            "e" => return Ok(std::f64::consts::E),
+            _ => {}
+        }
+
+        if !self.eat('(') {
+            return Err(format!("Expected '(' after {}", name));
+        }
+        let argument = self.parse_sum()?;
+        if !self.eat(')') {
+            return Err("Missing closing parenthesis".to_string());
+        }
+
+        match name.as_str() {
+            "sqrt" if argument < 0.0 => Err("Square root of a negative number".to_string()),
+            "sqrt" => Ok(argument.sqrt()),
+            "abs" => Ok(argument.abs()),
+            "ln" => Ok(argument.ln()),
+            "log" => Ok(argument.log10()),
+            "sin" => Ok(argument.sin()),
+            "cos" => Ok(argument.cos()),
+            "tan" => Ok(argument.tan()),
+            _ => Err(format!("Unknown function {}", name)),
+        }
+    }
+}
@@ -0,0 +1,348 @@
+//! Chunking Implementation
+//!
+//! This module splits documents into chunks according to the chunking strategy
+//! configured for their collection. Semantic chunking is delegated to kalosm,
+//! while the fixed-size, sentence and markdown-heading strategies are computed
+//! here from the text alone, which also makes them cheap to preview.
+//!
+//! Markdown chunks keep the breadcrumb of the headings they are nested under.
+//! The breadcrumb is prepended to the chunk text both when embedding the chunk
+//! and when indexing it for keyword search, so a chunk deep inside a section is
+//! still found by the words of its headings.
+
+use std::ops::Range;
+use kalosm::language::{Chunk, Chunker, Document, Embedder, EmbedderExt, SemanticChunker};
+use crate::model::collection::{ChunkPreview, ChunkingStrategy};
+use crate::server::embedding_cache::CachedEmbedder;
+
+/// Separator placed between the headings of a breadcrumb
+const BREADCRUMB_SEPARATOR: &str = " > ";
+
+/// Chunker applying the chunking strategy of a collection
+///
+/// Used when ingesting documents so they are embedded and indexed with the
+/// same chunks that are shown by the preview.
+pub struct CollectionChunker {
+    strategy: ChunkingStrategy,
+    semantic: SemanticChunker,
+}
+
+impl CollectionChunker {
+    /// Creates a chunker for the given strategy
+    pub fn new(strategy: ChunkingStrategy) -> Self {
+        Self {
+            strategy,
+            semantic: SemanticChunker::default(),
+        }
+    }
+}
+
+impl Chunker for CollectionChunker {
+    type Error<E: Send + Sync + 'static> = E;
+
+    async fn chunk<E: Embedder + Send>(
+        &self,
+        document: &Document,
+        embedder: &E,
+    ) -> Result<Vec<Chunk>, Self::Error<E::Error>> {
+        let Some(chunks) = split_text(&self.strategy, document.body()) else {
+            return self.semantic.chunk(document, embedder).await;
+        };
+
+        let texts = chunks.iter().map(chunk_context_text).collect();
+        let embeddings = embedder.embed_vec(texts).await?;
+
+        Ok(chunks.into_iter().zip(embeddings).map(|(chunk, embedding)| {
+            Chunk {
+                byte_range: chunk.start..chunk.end,
+                embeddings: vec![embedding],
+            }
+        }).collect())
+    }
+}
+
+/// Shows how a text would be chunked by a strategy
+///
+/// Semantic chunking needs the embedding model, so `embedder` is used to run
+/// the semantic chunker; the other strategies only look at the text.
+///
+/// # Parameters
+/// * `strategy` - The chunking strategy to preview
+/// * `text` - The document text
+/// * `embedder` - The embedding model used by semantic chunking
+///
+/// # Returns
+/// * `Result<Vec<ChunkPreview>, String>` - The chunks in document order or an error message
+pub async fn preview_chunks(
+    strategy: &ChunkingStrategy,
+    text: &str,
+    embedder: &CachedEmbedder,
+) -> Result<Vec<ChunkPreview>, String> {
+    if let Some(chunks) = split_text(strategy, text) {
+        return Ok(chunks);
+    }
+
+    let document = Document::from_parts("Preview", text);
+    let chunks = SemanticChunker::default()
+        .chunk(&document, embedder)
+        .await
+        .map_err(|e| format!("Error chunking document: {}", e))?;
+
+    Ok(chunks.into_iter()
+        .map(|chunk| preview_from_range(text, chunk.byte_range, None))
+        .collect())
+}
+
+/// Splits a text with a strategy that does not need embeddings
+///
+/// Returns `None` for semantic chunking, which can only be computed by kalosm.
+pub fn split_text(strategy: &ChunkingStrategy, text: &str) -> Option<Vec<ChunkPreview>> {
+    match strategy {
+        ChunkingStrategy::Semantic => None,
+        ChunkingStrategy::FixedSize { chunk_size, overlap } => {
+            Some(window_chunks(text, &word_spans(text), *chunk_size, *overlap))
+        }
+        ChunkingStrategy::Sentence { sentences_per_chunk, overlap } => {
+            Some(window_chunks(text, &sentence_spans(text), *sentences_per_chunk, *overlap))
+        }
+        ChunkingStrategy::MarkdownHeadings { max_chunk_chars } => {
+            Some(markdown_chunks(text, *max_chunk_chars))
+        }
+    }
+}
+
+/// Splits a text into sentences
+pub fn sentences(text: &str) -> Vec<ChunkPreview> {
+    sentence_spans(text)
+        .into_iter()
+        .map(|range| preview_from_range(text, range, None))
+        .collect()
+}
+
+/// Markdown sections of a document: the byte range of every section with its breadcrumb
+pub type Sections = Vec<(Range<usize>, Option<String>)>;
+
+/// Splits a document into the sections its chunks take their breadcrumbs from
+///
+/// Computed once per document and passed to `chunk_at` for each of its chunks.
+/// Empty unless the strategy splits on markdown headings.
+pub fn document_sections(strategy: &ChunkingStrategy, text: &str) -> Sections {
+    match strategy {
+        ChunkingStrategy::MarkdownHeadings { .. } => markdown_sections(text),
+        _ => Vec::new(),
+    }
+}
+
+/// Builds the chunk covering a byte range of a document
+///
+/// Markdown chunks get the breadcrumb of the section they start in, looked up
+/// in the sections computed by `document_sections`.
+pub fn chunk_at(text: &str, sections: &[(Range<usize>, Option<String>)], range: Range<usize>) -> ChunkPreview {
+    let breadcrumb = breadcrumb_at(sections, range.start);
+    preview_from_range(text, range, breadcrumb)
+}
+
+/// Returns the text used to embed and index a chunk
+///
+/// The breadcrumb of markdown chunks is prepended to the chunk text.
+pub fn chunk_context_text(chunk: &ChunkPreview) -> String {
+    match &chunk.breadcrumb {
+        Some(breadcrumb) => format!("{}\n\n{}", breadcrumb, chunk.text),
+        None => chunk.text.clone(),
+    }
+}
+
+/// Returns the heading breadcrumb of the markdown section containing a byte offset
+///
+/// The sections are in document order, so the section is found by binary search.
+fn breadcrumb_at(sections: &[(Range<usize>, Option<String>)], offset: usize) -> Option<String> {
+    let index = sections.partition_point(|(range, _)| range.end <= offset);
+    sections.get(index)
+        .filter(|(range, _)| range.contains(&offset))
+        .and_then(|(_, breadcrumb)| breadcrumb.clone())
+}
+
+/// Builds a chunk preview from a byte range of a text
+fn preview_from_range(text: &str, range: Range<usize>, breadcrumb: Option<String>) -> ChunkPreview {
+    ChunkPreview {
+        start: range.start,
+        end: range.end,
+        breadcrumb,
+        text: text[range].to_string(),
+    }
+}
+
+/// Groups consecutive spans into overlapping windows
+///
+/// Each chunk covers `size` spans and starts `size - overlap` spans after the
+/// previous one.
+fn window_chunks(text: &str, spans: &[Range<usize>], size: usize, overlap: usize) -> Vec<ChunkPreview> {
+    let size = size.max(1);
+    let step = size.saturating_sub(overlap).max(1);
+    let mut chunks = Vec::new();
+
+    let mut first = 0;
+    while first < spans.len() {
+        let last = (first + size).min(spans.len()) - 1;
+        chunks.push(preview_from_range(text, spans[first].start..spans[last].end, None));
+        if last == spans.len() - 1 {
+            break;
+        }
+        first += step;
+    }
+
+    chunks
+}
+
+/// Returns the byte ranges of the words of a text
+fn word_spans(text: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut start = None;
+
+    for (index, c) in text.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(word_start)) => {
+                spans.push(word_start..index);
+                start = None;
+            }
+            (false, None) => start = Some(index),
+            _ => {}
+        }
+    }
+    if let Some(word_start) = start {
+        spans.push(word_start..text.len());
+    }
+
+    spans
+}
+
+/// Returns the byte ranges of the sentences of a text
+///
+/// A sentence ends with `.`, `!` or `?` followed by whitespace, or at a blank
+/// line so headings and list items do not merge into the next sentence.
+fn sentence_spans(text: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, next)| next);
+        let ends_sentence = matches!(c, '.' | '!' | '?') && next.is_none_or(char::is_whitespace);
+        let ends_paragraph = c == '\n' && next == Some('\n');
+
+        if ends_sentence || ends_paragraph {
+            let end = index + c.len_utf8();
+            push_trimmed(text, start..end, &mut spans);
+            start = end;
+        }
+    }
+    push_trimmed(text, start..text.len(), &mut spans);
+
+    spans
+}
+
+/// Returns the byte ranges of the paragraphs of a text range
+fn paragraph_spans(text: &str, range: Range<usize>) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut start = range.start;
+
+    while let Some(offset) = text[start..range.end].find("\n\n") {
+        push_trimmed(text, start..start + offset, &mut spans);
+        start += offset + 2;
+    }
+    push_trimmed(text, start..range.end, &mut spans);
+
+    spans
+}
+
+/// Pushes a range with its surrounding whitespace removed, skipping blank ranges
+fn push_trimmed(text: &str, range: Range<usize>, spans: &mut Vec<Range<usize>>) {
+    let slice = &text[range.clone()];
+    let trimmed_start = slice.len() - slice.trim_start().len();
+    let trimmed = slice.trim();
+    if !trimmed.is_empty() {
+        let start = range.start + trimmed_start;
+        spans.push(start..start + trimmed.len());
+    }
+}
+
+/// Splits a markdown text at its headings and limits the size of each chunk
+///
+/// Sections longer than `max_chunk_chars` are split into groups of paragraphs;
+/// every piece keeps the breadcrumb of its section.
+fn markdown_chunks(text: &str, max_chunk_chars: usize) -> Vec<ChunkPreview> {
+    let mut chunks = Vec::new();
+
+    for (range, breadcrumb) in markdown_sections(text) {
+        let mut current: Option<Range<usize>> = None;
+        for paragraph in paragraph_spans(text, range) {
+            current = match current {
+                Some(group) if paragraph.end - group.start <= max_chunk_chars => Some(group.start..paragraph.end),
+                Some(group) => {
+                    chunks.push(preview_from_range(text, group, breadcrumb.clone()));
+                    Some(paragraph)
+                }
+                None => Some(paragraph),
+            };
+        }
+        if let Some(group) = current {
+            chunks.push(preview_from_range(text, group, breadcrumb.clone()));
+        }
+    }
+
+    chunks
+}
+
+/// Splits a markdown text into sections, each starting at a heading
+///
+/// Returns the byte range of every section with the breadcrumb of the headings
+/// it is nested under. Lines inside fenced code blocks are never headings.
+fn markdown_sections(text: &str) -> Vec<(Range<usize>, Option<String>)> {
+    let mut sections = Vec::new();
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut section_start = 0;
+    let mut in_code_block = false;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+        }
+
+        if let Some((level, title)) = parse_heading(trimmed).filter(|_| !in_code_block) {
+            if offset > section_start {
+                sections.push((section_start..offset, format_breadcrumb(&headings)));
+            }
+            headings.retain(|(existing_level, _)| *existing_level < level);
+            headings.push((level, title));
+            section_start = offset;
+        }
+
+        offset += line.len();
+    }
+    if text.len() > section_start {
+        sections.push((section_start..text.len(), format_breadcrumb(&headings)));
+    }
+
+    sections
+}
+
+/// Parses an ATX heading line into its level and title
+fn parse_heading(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let title = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then(|| (level, title.trim().trim_end_matches('#').trim().to_string()))
+}
+
+/// Joins the titles of a heading stack into a breadcrumb
+fn format_breadcrumb(headings: &[(usize, String)]) -> Option<String> {
+    if headings.is_empty() {
+        None
+    } else {
+        Some(headings.iter()
+            .map(|(_, title)| title.as_str())
+            .collect::<Vec<_>>()
+            .join(BREADCRUMB_SEPARATOR))
+    }
+}
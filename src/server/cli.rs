@@ -0,0 +1,245 @@
+//! Command Line Interface
+//!
+//! This module implements the maintenance commands of the server binary. When
+//! the binary is started with a known command it runs it and exits instead of
+//! launching the web application:
+//!
+//! - `export [path]` - Builds the knowledge base and exports it into an archive
+//! - `import <path>` - Imports an archive so its documents are restored on the next start
+//! - `eval <golden set> [options]` - Evaluates retrieval against a golden question set,
+//!   using the knowledge base built by the last server start
+//! - `bench <prompt suite> [options]` - Measures the generation speed of the chat model
+//! - `adduser <username> [--admin]` - Creates a user account
+//!
+//! Commands open the same database files as the server, so the server must be
+//! stopped while they run.
+
+use std::path::Path;
+use crate::model::auth::Role;
+use crate::model::document::{SearchMode, SearchOptions};
+use crate::server::{auth, backup, benchmark, evaluation};
+use crate::server::database_impl::{connect_to_database, open_database};
+use crate::server::embedding::init_embedding_model;
+use crate::server::llm::init_chat_model;
+
+/// Usage of the evaluation command
+const EVAL_USAGE: &str = "Usage: rusty_bot eval <golden set> [--top-k N] [--mode vector|keyword|hybrid] \
+    [--rerank] [--no-answers] [--output report.json]";
+
+/// Usage of the benchmark command
+const BENCH_USAGE: &str = "Usage: rusty_bot bench <prompt suite> [--runs N] [--output report.json]";
+
+/// Usage of the account creation command
+const ADDUSER_USAGE: &str = "Usage: rusty_bot adduser <username> [--admin]";
+
+/// Runs the command given on the command line, if any
+///
+/// # Returns
+/// * `Option<i32>` - The exit code of the command, or `None` when no command was given
+pub fn run_from_args() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = args.first()?.as_str();
+    if !matches!(command, "export" | "import" | "eval" | "bench" | "adduser") {
+        return None;
+    }
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Error starting runtime: {}", e);
+            return Some(1);
+        }
+    };
+
+    let result = runtime.block_on(async {
+        match command {
+            "export" => export(args.get(1).map(String::as_str)).await,
+            "import" => match args.get(1) {
+                Some(path) => import(path).await,
+                None => Err("Usage: rusty_bot import <path>".to_string()),
+            },
+            "eval" => eval(&args[1..]).await,
+            "bench" => bench(&args[1..]).await,
+            _ => adduser(&args[1..]).await,
+        }
+    });
+
+    match result {
+        Ok(()) => Some(0),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            Some(1)
+        }
+    }
+}
+
+/// Builds the knowledge base from the context folder and exports it
+async fn export(path: Option<&str>) -> Result<(), String> {
+    init_embedding_model().await?;
+    connect_to_database().await?;
+
+    let summary = match path {
+        Some(path) => backup::export_archive_to(Path::new(path)).await?,
+        None => backup::export_archive().await?,
+    };
+    println!(
+        "Archive written to {} ({} documents, {} chunks, {} {}-dimensional vectors)",
+        summary.path, summary.documents, summary.chunks, summary.model_id, summary.dimensions
+    );
+    Ok(())
+}
+
+/// Imports an archive into the import folder
+async fn import(path: &str) -> Result<(), String> {
+    init_embedding_model().await?;
+
+    let summary = backup::import_archive(Path::new(path)).await?;
+    println!(
+        "Imported {} documents and {} chunks{} into {}; they will be restored on the next start",
+        summary.documents,
+        summary.chunks,
+        if summary.reembedded { " (embedded again)" } else { "" },
+        summary.path
+    );
+    Ok(())
+}
+
+/// Evaluates the knowledge base against a golden question set
+///
+/// The document database built by the last server start is opened as it is, so
+/// the evaluated knowledge base is the one the server answers from.
+///
+/// The JSON report is written to the `--output` file, or printed when none is given.
+async fn eval(args: &[String]) -> Result<(), String> {
+    let golden_path = args.first().filter(|arg| !arg.starts_with("--")).ok_or(EVAL_USAGE)?;
+    let mut options = SearchOptions {
+        top_k: 5,
+        rewrite_query: false,
+        ..SearchOptions::default()
+    };
+    let mut generate_answers = true;
+    let mut output = None;
+
+    let mut flags = args[1..].iter();
+    while let Some(flag) = flags.next() {
+        match flag.as_str() {
+            "--top-k" => {
+                options.top_k = flags.next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|top_k| *top_k > 0)
+                    .ok_or(EVAL_USAGE)?;
+            }
+            "--mode" => {
+                options.mode = match flags.next().map(String::as_str) {
+                    Some("vector") => SearchMode::Vector,
+                    Some("keyword") => SearchMode::Keyword,
+                    Some("hybrid") => SearchMode::Hybrid,
+                    _ => return Err(EVAL_USAGE.to_string()),
+                };
+            }
+            "--rerank" => options.rerank = true,
+            "--no-answers" => generate_answers = false,
+            "--output" => output = Some(flags.next().ok_or(EVAL_USAGE)?.clone()),
+            _ => return Err(EVAL_USAGE.to_string()),
+        }
+    }
+
+    let questions = evaluation::load_golden_set(Path::new(golden_path))?;
+    if generate_answers || options.rerank {
+        init_chat_model().await?;
+    }
+    init_embedding_model().await?;
+    open_database().await?;
+
+    let report = evaluation::evaluate(&questions, &options, generate_answers).await?;
+    let metric = |value: Option<f32>| value.map_or_else(|| "n/a".to_string(), |value| format!("{:.3}", value));
+    println!(
+        "recall@{}: {}, MRR: {}, answer similarity: {}",
+        options.top_k,
+        metric(report.summary.recall_at_k),
+        metric(report.summary.mrr),
+        metric(report.summary.answer_similarity)
+    );
+
+    write_report(&report, output.as_deref())
+}
+
+/// Runs a prompt suite through the chat model and reports the generation metrics
+///
+/// The JSON report is written to the `--output` file, or printed when none is given.
+async fn bench(args: &[String]) -> Result<(), String> {
+    let suite_path = args.first().filter(|arg| !arg.starts_with("--")).ok_or(BENCH_USAGE)?;
+    let mut repetitions = 1;
+    let mut output = None;
+
+    let mut flags = args[1..].iter();
+    while let Some(flag) = flags.next() {
+        match flag.as_str() {
+            "--runs" => {
+                repetitions = flags.next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|runs| *runs > 0)
+                    .ok_or(BENCH_USAGE)?;
+            }
+            "--output" => output = Some(flags.next().ok_or(BENCH_USAGE)?.clone()),
+            _ => return Err(BENCH_USAGE.to_string()),
+        }
+    }
+
+    let prompts = benchmark::load_prompt_suite(Path::new(suite_path))?;
+    init_chat_model().await?;
+
+    let report = benchmark::run_benchmark(&prompts, repetitions).await?;
+    let summary = &report.summary;
+    println!(
+        "{} responses: time to first token p50 {:.0} ms / p95 {:.0} ms, {:.1} / {:.1} tokens/s, {:.0} / {:.0} completion tokens",
+        summary.responses,
+        summary.time_to_first_token_ms.p50,
+        summary.time_to_first_token_ms.p95,
+        summary.tokens_per_second.p50,
+        summary.tokens_per_second.p95,
+        summary.completion_tokens.p50,
+        summary.completion_tokens.p95
+    );
+
+    write_report(&report, output.as_deref())
+}
+
+/// Creates a user account
+///
+/// The password is read from the `RUSTY_BOT_PASSWORD` environment variable, or
+/// from the first line of standard input when it is not set.
+async fn adduser(args: &[String]) -> Result<(), String> {
+    let (username, role) = match args {
+        [username] => (username, Role::User),
+        [username, flag] if flag == "--admin" => (username, Role::Admin),
+        _ => return Err(ADDUSER_USAGE.to_string()),
+    };
+
+    let password = match std::env::var("RUSTY_BOT_PASSWORD") {
+        Ok(password) => password,
+        Err(_) => {
+            println!("Password for {}:", username);
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line).map_err(|e| e.to_string())?;
+            line.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+
+    let user = auth::create_user(username, &password, role).await?;
+    println!("Created {:?} account {}", user.role, user.username);
+    Ok(())
+}
+
+/// Writes a JSON report to a file, or prints it when no file is given
+fn write_report(report: &impl serde::Serialize, output: Option<&str>) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(report).map_err(|e| e.to_string())?;
+    match output {
+        Some(path) => {
+            std::fs::write(path, json).map_err(|e| format!("Error writing report: {}", e))?;
+            println!("Report written to {}", path);
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
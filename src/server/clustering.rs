@@ -0,0 +1,239 @@
+//! Topic Clustering
+//!
+//! This module builds an overview of what the knowledge base covers. The chunk
+//! embeddings are grouped with k-means, and the language model is asked to label
+//! each group from the chunks closest to its center.
+//!
+//! Clustering reads the embeddings stored with the chunks at ingestion, but
+//! prompts the model once per topic, so the last topic map is kept in memory and
+//! only rebuilt on request.
+
+use tokio::sync::{Mutex, OnceCell};
+use crate::model::topic::{Topic, TopicMap};
+use crate::server::database_impl::{self, StoredChunk};
+use crate::server::llm::{complete_prompt, TokenUsage};
+use crate::server::ranking::cosine_similarity;
+use tracing::{error, info};
+
+/// Global singleton holding the last topic map that was built
+static TOPIC_MAP: OnceCell<Mutex<Option<TopicMap>>> = OnceCell::const_new();
+
+/// Maximum number of chunks clustered, in document order
+const MAX_CLUSTERED_CHUNKS: usize = 20000;
+
+/// Maximum number of k-means iterations
+const MAX_ITERATIONS: usize = 50;
+
+/// Number of chunks closest to the center kept as examples of a topic
+const EXAMPLES_PER_TOPIC: usize = 3;
+
+/// Number of chunks shown to the language model when labelling a topic
+const LABEL_SAMPLE_SIZE: usize = 5;
+
+/// Maximum number of characters of each chunk shown to the language model
+const LABEL_SAMPLE_CHARS: usize = 400;
+
+/// Maximum number of topics chosen automatically
+const MAX_AUTOMATIC_TOPICS: usize = 12;
+
+/// Maximum number of topics that can be requested, each one prompting the model
+pub const MAX_TOPICS: usize = 30;
+
+/// Instructions given to the model when labelling a topic
+const LABEL_SYSTEM_PROMPT: &str = "You name the topic shared by a group of text excerpts. \
+Answer with a short label of two to five words and nothing else.";
+
+/// Returns the topic map of the knowledge base
+///
+/// The map built by a previous call is returned unless `refresh` is set or no
+/// map has been built yet.
+///
+/// # Parameters
+/// * `clusters` - Number of topics to find, at most `MAX_TOPICS`, chosen from the corpus size when `None`
+/// * `refresh` - Whether to rebuild the map even if one is available
+///
+/// # Returns
+/// * `Result<(TopicMap, Option<TokenUsage>), String>` - The topics of the knowledge base, with the
+///   tokens used to label them when this call built the map, or an error message
+pub async fn get_topic_map(clusters: Option<usize>, refresh: bool) -> Result<(TopicMap, Option<TokenUsage>), String> {
+    let mut stored = TOPIC_MAP
+        .get_or_init(|| async { Mutex::new(None) })
+        .await
+        .lock()
+        .await;
+
+    // The lock is held while building so concurrent requests share a single job
+    let mut usage = None;
+    if refresh || stored.is_none() {
+        let mut label_usage = TokenUsage::default();
+        *stored = Some(build_topic_map(clusters, &mut label_usage).await?);
+        usage = Some(label_usage);
+    }
+    Ok((stored.clone().unwrap_or_default(), usage))
+}
+
+/// Clusters the chunks of the knowledge base and labels every cluster
+///
+/// The tokens of the labelling prompts are added to `usage`.
+async fn build_topic_map(clusters: Option<usize>, usage: &mut TokenUsage) -> Result<TopicMap, String> {
+    let chunks = database_impl::list_chunk_embeddings(MAX_CLUSTERED_CHUNKS).await?;
+    if chunks.is_empty() {
+        return Ok(TopicMap::default());
+    }
+
+    let embeddings: Vec<Vec<f32>> = chunks.iter().map(|chunk| chunk.embedding.clone()).collect();
+    let k = clusters
+        .unwrap_or_else(|| automatic_cluster_count(chunks.len()))
+        .clamp(1, MAX_TOPICS)
+        .min(chunks.len());
+    info!("Clustering {} chunks into {} topics", chunks.len(), k);
+
+    let (assignments, centroids) = k_means(&embeddings, k);
+
+    let mut topics = Vec::with_capacity(k);
+    for (cluster, centroid) in centroids.iter().enumerate() {
+        // Members of the cluster ordered from the closest to the center
+        let mut members: Vec<(usize, f32)> = assignments.iter()
+            .enumerate()
+            .filter(|(_, assigned)| **assigned == cluster)
+            .map(|(index, _)| (index, cosine_similarity(&embeddings[index], centroid)))
+            .collect();
+        if members.is_empty() {
+            continue;
+        }
+        members.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut documents: Vec<String> = members.iter()
+            .map(|(index, _)| chunks[*index].title.clone())
+            .collect();
+        documents.sort();
+        documents.dedup();
+
+        let closest: Vec<&StoredChunk> = members.iter()
+            .take(LABEL_SAMPLE_SIZE)
+            .map(|(index, _)| &chunks[*index])
+            .collect();
+        let label = label_topic(&closest, usage).await.unwrap_or_else(|e| {
+            error!("Error labelling topic {}: {}", cluster + 1, e);
+            format!("Topic {}", cluster + 1)
+        });
+
+        topics.push(Topic {
+            label,
+            chunk_count: members.len(),
+            documents,
+            examples: closest.into_iter().take(EXAMPLES_PER_TOPIC).map(StoredChunk::to_document_chunk).collect(),
+        });
+    }
+
+    topics.sort_by(|a, b| b.chunk_count.cmp(&a.chunk_count));
+    Ok(TopicMap {
+        topics,
+        chunk_count: chunks.len(),
+    })
+}
+
+/// Chooses a number of topics from the number of chunks
+///
+/// Uses the common `sqrt(n / 2)` rule of thumb, bounded to keep the map readable.
+fn automatic_cluster_count(chunks: usize) -> usize {
+    ((chunks as f64 / 2.0).sqrt().round() as usize).clamp(1, MAX_AUTOMATIC_TOPICS)
+}
+
+/// Groups vectors into `k` clusters by cosine similarity
+///
+/// Spherical k-means: vectors are normalized and each centroid is the normalized
+/// mean of its members. The initial centroids are picked with farthest-point
+/// seeding, starting from the first vector, so the result is deterministic.
+///
+/// # Returns
+/// * The cluster index of every vector and the centroid of every cluster
+fn k_means(vectors: &[Vec<f32>], k: usize) -> (Vec<usize>, Vec<Vec<f32>>) {
+    let points: Vec<Vec<f32>> = vectors.iter().map(|vector| normalize(vector.clone())).collect();
+
+    let mut centroids = vec![points[0].clone()];
+    while centroids.len() < k {
+        let farthest = points.iter()
+            .map(|point| nearest_centroid(point, &centroids).1)
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(0, |(index, _)| index);
+        centroids.push(points[farthest].clone());
+    }
+
+    let mut assignments = vec![usize::MAX; points.len()];
+    for _ in 0..MAX_ITERATIONS {
+        let next: Vec<usize> = points.iter()
+            .map(|point| nearest_centroid(point, &centroids).0)
+            .collect();
+        if next == assignments {
+            break;
+        }
+        assignments = next;
+
+        for (cluster, centroid) in centroids.iter_mut().enumerate() {
+            let mut sum = vec![0.0; centroid.len()];
+            let mut members = 0;
+            for (point, _) in points.iter().zip(&assignments).filter(|(_, assigned)| **assigned == cluster) {
+                sum.iter_mut().zip(point).for_each(|(total, value)| *total += value);
+                members += 1;
+            }
+            // Empty clusters keep their previous centroid
+            if members > 0 {
+                *centroid = normalize(sum);
+            }
+        }
+    }
+
+    (assignments, centroids)
+}
+
+/// Returns the index of the most similar centroid and its similarity
+fn nearest_centroid(point: &[f32], centroids: &[Vec<f32>]) -> (usize, f32) {
+    centroids.iter()
+        .enumerate()
+        .map(|(index, centroid)| (index, cosine_similarity(point, centroid)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((0, 0.0))
+}
+
+/// Scales a vector to unit length, leaving zero vectors unchanged
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|value| *value /= norm);
+    }
+    vector
+}
+
+/// Asks the language model for a short label describing a group of chunks
+async fn label_topic(chunks: &[&StoredChunk], usage: &mut TokenUsage) -> Result<String, String> {
+    let excerpts = chunks.iter()
+        .enumerate()
+        .map(|(index, chunk)| {
+            let text: String = chunk.text.chars().take(LABEL_SAMPLE_CHARS).collect();
+            format!("Excerpt {} ({}):\n{}", index + 1, chunk.title, text)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let (answer, label_usage) = complete_prompt(LABEL_SYSTEM_PROMPT, &excerpts, 16).await?;
+    *usage += label_usage;
+    parse_label(&answer).ok_or_else(|| "The model returned an empty label".to_string())
+}
+
+/// Extracts the label from the model answer
+///
+/// Keeps the first non-empty line and strips quotes, list markers and a
+/// leading "Label:" prefix the model sometimes adds.
+fn parse_label(answer: &str) -> Option<String> {
+    let line = answer.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = line
+        .strip_prefix("Label:")
+        .or_else(|| line.strip_prefix("label:"))
+        .unwrap_or(line);
+    let label = line
+        .trim_start_matches(['-', '*', ' '])
+        .trim_matches(['"', '\'', '.', ' ']);
+    (!label.is_empty()).then(|| label.to_string())
+}
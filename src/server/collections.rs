@@ -0,0 +1,51 @@
+//! Collection Configuration Storage
+//!
+//! This module loads and saves the configuration of the document collections in
+//! the persistent application database. Collections without a stored
+//! configuration use the default one.
+
+use crate::model::collection::CollectionConfig;
+use crate::server::app_store::get_app_db;
+use tracing::error;
+
+/// Name of the table holding the collection configurations
+const COLLECTION_TABLE: &str = "collection";
+
+/// Loads the configuration of a collection
+///
+/// # Parameters
+/// * `name` - The name of the collection
+///
+/// # Returns
+/// * `Result<CollectionConfig, String>` - The stored configuration, the default one if none is stored, or an error
+pub async fn load_collection_config(name: &str) -> Result<CollectionConfig, String> {
+    let db = get_app_db().await?;
+    let stored: Option<CollectionConfig> = db
+        .select((COLLECTION_TABLE, name))
+        .await
+        .map_err(|e| {
+            error!("Error loading collection configuration: {}", e);
+            e.to_string()
+        })?;
+    Ok(stored.unwrap_or_else(|| CollectionConfig::new(name)))
+}
+
+/// Saves the configuration of a collection, replacing any previous one
+///
+/// # Parameters
+/// * `config` - The configuration to store
+///
+/// # Returns
+/// * `Result<(), String>` - Success or an error message
+pub async fn save_collection_config(config: CollectionConfig) -> Result<(), String> {
+    let db = get_app_db().await?;
+    let _: Option<CollectionConfig> = db
+        .upsert((COLLECTION_TABLE, config.name.clone()))
+        .content(config)
+        .await
+        .map_err(|e| {
+            error!("Error saving collection configuration: {}", e);
+            e.to_string()
+        })?;
+    Ok(())
+}
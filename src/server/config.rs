@@ -0,0 +1,148 @@
+//! Server Configuration
+//!
+//! This module loads the typed server configuration when the server starts. The
+//! values are read from a TOML file, `rusty_bot.toml` in the working directory
+//! or the file named by `RUSTY_BOT_CONFIG`, and can be overridden by environment
+//! variables:
+//!
+//! - `RUSTY_BOT_DB_PATH`, `RUSTY_BOT_DB_NAMESPACE`, `RUSTY_BOT_DB_DATABASE` - Document database
+//! - `RUSTY_BOT_APP_DB_PATH`, `RUSTY_BOT_APP_DB_NAMESPACE`, `RUSTY_BOT_APP_DB_DATABASE` - Application database
+//! - `RUSTY_BOT_CONTEXT_FOLDER` - Folder the knowledge base is ingested from
+//! - `RUSTY_BOT_BACKUP_FOLDER`, `RUSTY_BOT_IMPORT_FOLDER` - Folders of the exported and imported archives
+//! - `RUSTY_BOT_INGEST_BATCH_SIZE`, `RUSTY_BOT_INGEST_CONCURRENCY` - Ingestion pipeline
+//! - `RUSTY_BOT_MODEL` - Identifier of the language model
+//! - `RUSTY_BOT_EMBEDDING_MODEL` - Identifier of the embedding model
+//! - `RUSTY_BOT_TEMPERATURE`, `RUSTY_BOT_TOP_P`, `RUSTY_BOT_MAX_LENGTH` - Sampling parameters
+//! - `RUSTY_BOT_PERSONA`, `RUSTY_BOT_RETRIEVAL_TOP_K` - Initial runtime settings
+//!
+//! Missing values use the defaults. The runtime settings of the file are only
+//! used until admins change them from the settings page; see `server::settings`.
+
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use tracing::{info, warn};
+use crate::model::document::MAX_RETRIEVAL_TOP_K;
+use crate::model::settings::{EmbeddingModelSource, ModelSource, ServerConfig};
+
+/// Configuration file read when `RUSTY_BOT_CONFIG` is not set
+const DEFAULT_CONFIG_FILE: &str = "./rusty_bot.toml";
+
+/// Global singleton for the configuration, loaded once
+static CONFIG: OnceLock<ServerConfig> = OnceLock::new();
+
+/// Loads the configuration so errors are reported when the server starts
+///
+/// Calling it more than once has no effect.
+///
+/// # Returns
+/// * `Result<(), String>` - Success or a message describing the invalid configuration
+pub fn init() -> Result<(), String> {
+    if CONFIG.get().is_none() {
+        let config = load()?;
+        info!(file = ?config_file(), model = config.model.source.id(), "Configuration loaded");
+        let _ = CONFIG.set(config);
+    }
+    Ok(())
+}
+
+/// Returns the configuration of the server
+///
+/// The configuration is loaded on first use when `init` was not called; an
+/// invalid configuration is then replaced by the defaults.
+pub fn get() -> &'static ServerConfig {
+    CONFIG.get_or_init(|| load().unwrap_or_else(|e| {
+        warn!("Invalid configuration, using the defaults: {}", e);
+        ServerConfig::default()
+    }))
+}
+
+/// Returns the configuration file, if it exists
+pub fn config_file() -> Option<String> {
+    let path = std::env::var("RUSTY_BOT_CONFIG").unwrap_or_else(|_| DEFAULT_CONFIG_FILE.to_string());
+    Path::new(&path).exists().then_some(path)
+}
+
+/// Reads the configuration file and applies the environment overrides
+fn load() -> Result<ServerConfig, String> {
+    let mut config = match config_file() {
+        Some(path) => {
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Error reading {}: {}", path, e))?;
+            toml::from_str(&text).map_err(|e| format!("Error parsing {}: {}", path, e))?
+        }
+        None => {
+            if let Ok(path) = std::env::var("RUSTY_BOT_CONFIG") {
+                return Err(format!("Configuration file {} does not exist", path));
+            }
+            ServerConfig::default()
+        }
+    };
+
+    override_from_env("RUSTY_BOT_DB_PATH", &mut config.database.path)?;
+    override_from_env("RUSTY_BOT_DB_NAMESPACE", &mut config.database.namespace)?;
+    override_from_env("RUSTY_BOT_DB_DATABASE", &mut config.database.database)?;
+    override_from_env("RUSTY_BOT_APP_DB_PATH", &mut config.app_database.path)?;
+    override_from_env("RUSTY_BOT_APP_DB_NAMESPACE", &mut config.app_database.namespace)?;
+    override_from_env("RUSTY_BOT_APP_DB_DATABASE", &mut config.app_database.database)?;
+    override_from_env("RUSTY_BOT_CONTEXT_FOLDER", &mut config.knowledge_base.context_folder)?;
+    override_from_env("RUSTY_BOT_BACKUP_FOLDER", &mut config.knowledge_base.backup_folder)?;
+    override_from_env("RUSTY_BOT_IMPORT_FOLDER", &mut config.knowledge_base.import_folder)?;
+    override_from_env("RUSTY_BOT_INGEST_BATCH_SIZE", &mut config.ingestion.batch_size)?;
+    override_from_env("RUSTY_BOT_INGEST_CONCURRENCY", &mut config.ingestion.concurrency)?;
+    override_from_env("RUSTY_BOT_TEMPERATURE", &mut config.sampling.temperature)?;
+    override_from_env("RUSTY_BOT_TOP_P", &mut config.sampling.top_p)?;
+    override_from_env("RUSTY_BOT_MAX_LENGTH", &mut config.sampling.max_length)?;
+    override_from_env("RUSTY_BOT_PERSONA", &mut config.settings.persona)?;
+    override_from_env("RUSTY_BOT_RETRIEVAL_TOP_K", &mut config.settings.retrieval_top_k)?;
+    if let Ok(id) = std::env::var("RUSTY_BOT_MODEL") {
+        config.model.source = ModelSource::from_id(&id).ok_or_else(|| unknown_model(&id))?;
+    }
+    if let Ok(id) = std::env::var("RUSTY_BOT_EMBEDDING_MODEL") {
+        config.model.embedding = EmbeddingModelSource::from_id(&id).ok_or_else(|| unknown_embedding_model(&id))?;
+    }
+
+    validate(&config)?;
+    Ok(config)
+}
+
+/// Replaces a value with the one of an environment variable, when it is set
+fn override_from_env<T: FromStr>(name: &str, value: &mut T) -> Result<(), String> {
+    if let Ok(text) = std::env::var(name) {
+        *value = text.parse().map_err(|_| format!("Invalid value for {}: {}", name, text))?;
+    }
+    Ok(())
+}
+
+/// Checks that the values of the configuration are in their valid ranges
+fn validate(config: &ServerConfig) -> Result<(), String> {
+    let sampling = &config.sampling;
+    if sampling.temperature < 0.0 {
+        return Err("The temperature must not be negative".to_string());
+    }
+    if sampling.top_p <= 0.0 || sampling.top_p > 1.0 {
+        return Err("top_p must be greater than 0 and at most 1".to_string());
+    }
+    if sampling.max_length == 0 {
+        return Err("max_length must be greater than 0".to_string());
+    }
+    if config.ingestion.batch_size == 0 || config.ingestion.concurrency == 0 {
+        return Err("The ingestion batch_size and concurrency must be greater than 0".to_string());
+    }
+    if !(1..=MAX_RETRIEVAL_TOP_K).contains(&config.settings.retrieval_top_k) {
+        return Err(format!("retrieval_top_k must be between 1 and {}", MAX_RETRIEVAL_TOP_K));
+    }
+    Ok(())
+}
+
+/// Error message for an unsupported language model
+fn unknown_model(id: &str) -> String {
+    let supported = ModelSource::ALL.iter().map(|source| source.id()).collect::<Vec<_>>().join(", ");
+    format!("Unknown model {}, supported models: {}", id, supported)
+}
+
+/// Error message for an unsupported embedding model
+fn unknown_embedding_model(id: &str) -> String {
+    let supported = EmbeddingModelSource::ALL.iter().map(|source| source.id()).collect::<Vec<_>>().join(", ");
+    format!("Unknown embedding model {}, supported models: {}", id, supported)
+}
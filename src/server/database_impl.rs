@@ -2,264 +2,1079 @@
 //!
 //! This module provides the core database functionality for the application,
 //! including connection management, document storage, and semantic search capabilities.
-//! It leverages SurrealDB for document storage and kalosm for embedding-based search.
+//! Every chunk of a document is stored in SurrealDB with its text and its embedding,
+//! indexed both for nearest-neighbour search and for BM25 full-text search, so the
+//! two retrieval signals always see the same chunks. Kalosm provides the embedding
+//! model and the semantic chunker.
 
-use kalosm::EmbeddingIndexedTableSearchResult;
 use kalosm::language::*;
-use kalosm::language::Embedding;  // Corrigiendo la importación de Embedding
 use tokio::sync::{Mutex, OnceCell}; 
-use surrealdb::{Connection, Surreal};
+use surrealdb::Surreal;
 use surrealdb::engine::local::{Db, SurrealKv};
-use crate::model::document::SimpleDocumentResult;
+use crate::model::collection::{ChunkPreview, ChunkingStrategy, CollectionConfig};
+use crate::model::document::{DocumentChunk, DocumentDetails, DocumentInfo, ScoreSignals, MAX_RERANK_CANDIDATES, SearchMode, SearchOptions, SimpleDocumentResult};
+use crate::model::embedding::IngestionReport;
+use crate::model::settings::IngestionConfig;
+use crate::model::status::ComponentState;
+use crate::server::chunking::{self, CollectionChunker};
+use crate::server::backup;
+use crate::server::collections::load_collection_config;
+use crate::server::config;
+use crate::server::embedding::{embed_text, EMBEDDING_MODEL};
+use crate::server::embedding_cache::CachedEmbedder;
+use crate::server::llm::TokenUsage;
+use crate::server::prometheus;
+use crate::server::startup;
+use crate::server::status::{self, Component};
+use crate::server::ranking::{fuse_rankings, maximal_marginal_relevance, merge_query_results};
+use crate::server::rerank::rerank;
+use futures::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+use tracing::{debug, error, info, warn};
 
 /// Global singleton for the database connection
 /// Uses OnceCell and Mutex for thread-safe access and initialization
 static DB_CONN: OnceCell<Mutex<Option<Surreal<Db>>>> = OnceCell::const_new();
 
-/// Global singleton for the document table
-/// Stores documents with embedding-based search capabilities
-static DOCUMENT_TABLE: OnceCell<Mutex<Option<DocumentTable<Db>>>> = OnceCell::const_new();
+/// Global singleton for the configurations the collections were built with,
+/// by collection name, loaded the first time a collection receives documents
+static COLLECTION_CONFIGS: OnceCell<Mutex<HashMap<String, CollectionConfig>>> = OnceCell::const_new();
 
 /// Constants for database configuration
-const DB_PATH: &str = "./db";
-const DB_FILE: &str = "./db/temp.db";
-const EMBEDDINGS_FILE: &str = "./db/embeddings.db";
-const NAMESPACE: &str = "test";
-const DATABASE: &str = "test";
+/// The location of the database and of the context folder come from `server::config`
+const DB_FILE_NAME: &str = "temp.db";
 const TABLE_NAME: &str = "documents";
-const CONTEXT_FOLDER: &str = "./context";
+const DOCUMENT_INFO_TABLE_NAME: &str = "document_info";
+
+/// Collection of the files placed directly in the context folder
+/// Files in a subfolder belong to the collection named after that subfolder
+const DEFAULT_COLLECTION: &str = "documents";
+
+/// Stored embeddings of the chunks returned by a search, by chunk identifier
+type ChunkEmbeddings = HashMap<String, Vec<f32>>;
+
+/// Minimum size of the candidate list explored by the vector index during a search
+/// Larger values find the true nearest neighbours more often at a higher cost
+const VECTOR_SEARCH_MIN_EF: usize = 40;
+
+/// Largest number of results fetched from the vector or full-text index by a single search
+/// Covers the widest hybrid fusion over the largest candidate set a client may request
+const MAX_INDEX_RESULTS: usize = 4 * MAX_RERANK_CANDIDATES;
+
+/// Minimum number of candidates fetched from each signal in hybrid mode
+/// Fusion needs a wider candidate set than the final number of results
+const HYBRID_MIN_CANDIDATES: usize = 20;
+
+/// A document loaded from the context folder, with the file it came from
+struct SourceDocument {
+    /// Identifier of the document, assigned in loading order
+    id: usize,
+    /// Path of the file the document was loaded from
+    source: String,
+    /// Name of the collection the document belongs to
+    collection: String,
+    /// The document itself
+    document: Document,
+}
+
+/// A chunk of a document stored in the document table
+///
+/// The same text is embedded and indexed for full-text search, so both
+/// retrieval signals return the same chunks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkRow {
+    /// Identifier of the document the chunk belongs to
+    pub document_id: usize,
+    /// Position of the chunk in the document
+    pub position: usize,
+    /// Name of the collection the document belongs to
+    pub collection: String,
+    pub title: String,
+    /// Byte offset of the start of the chunk in the document
+    pub start: usize,
+    /// Byte offset of the end of the chunk in the document
+    pub end: usize,
+    /// The chunk text, prefixed with its heading breadcrumb for markdown chunks
+    pub text: String,
+    pub embedding: Vec<f32>,
+}
+
+/// A document of the knowledge base with its full text and metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentRecord {
+    /// Identifier of the document, assigned in ingestion order
+    pub id: usize,
+    pub title: String,
+    /// Path of the file the document was loaded from
+    pub source: String,
+    /// Name of the collection the document belongs to
+    pub collection: String,
+    pub body: String,
+    /// Time at which the document was ingested, in RFC 3339 format
+    pub ingested_at: String,
+}
+
+/// A row returned by a nearest-neighbour search over the chunk embeddings
+#[derive(Debug, Deserialize)]
+struct VectorSearchRow {
+    document_id: usize,
+    position: usize,
+    title: String,
+    text: String,
+    embedding: Vec<f32>,
+    distance: f32,
+}
+
+/// A chunk read back from the document table with its stored embedding
+#[derive(Debug, Clone, Deserialize)]
+pub struct StoredChunk {
+    /// Identifier of the document the chunk belongs to
+    pub document_id: usize,
+    /// Position of the chunk in the document
+    pub position: usize,
+    pub title: String,
+    pub text: String,
+    pub embedding: Vec<f32>,
+}
+
+impl StoredChunk {
+    /// The identifier of the chunk, unique across the document table
+    pub fn id(&self) -> String {
+        chunk_id(self.document_id, self.position)
+    }
+
+    /// The title and text of the chunk, without its embedding
+    pub fn to_document_chunk(&self) -> DocumentChunk {
+        DocumentChunk {
+            title: self.title.clone(),
+            text: self.text.clone(),
+        }
+    }
+}
+
+/// A row returned by a full-text search over the chunks
+#[derive(Debug, Deserialize)]
+struct KeywordSearchRow {
+    document_id: usize,
+    position: usize,
+    title: String,
+    text: String,
+    embedding: Vec<f32>,
+    score: f32,
+}
 
 /// Establishes a connection to the database and initializes the document table
 ///
 /// This function coordinates the entire database setup process.
 ///
 /// Returns Ok(()) on success or an error message on failure
+#[tracing::instrument]
 pub async fn connect_to_database() -> Result<(), String> {
+    status::set_state(Component::DbConn, ComponentState::Loading);
+    status::set_state(Component::DocumentTable, ComponentState::Loading);
+
     // Initialize global singletons
     initialize_globals().await;
     
     // Clean old database files
-    cleanup_database_files()?;
+    status::track(Component::DbConn, cleanup_database_files())?;
 
     // Connect to database
-    let db = create_database_connection().await?;
+    let db = status::track(Component::DbConn, create_database_connection().await)?;
     
     // Configure namespace and database
-    configure_database(&db).await?;
+    status::track(Component::DbConn, configure_database(&db).await)?;
 
-    // Create document table
-    let dt = create_document_table(&db).await?;
+    // Create the document table with its indexes
+    status::track(Component::DocumentTable, create_document_table(&db).await)?;
     
     // Store connections in singletons
-    store_connections(db, dt).await;
+    store_connections(db).await;
+    status::set_state(Component::DbConn, ComponentState::Ready);
     
-    // Add documents to the database
-    add_documents().await?;
+    // Add documents to the database, then the documents of imported archives
+    status::track(Component::DocumentTable, add_documents().await)?;
+    status::track(Component::DocumentTable, backup::restore_imported_archives().await)?;
+    status::set_state(Component::DocumentTable, ComponentState::Ready);
     
-    println!("Database connection setup completed successfully");
+    info!("Database connection setup completed successfully");
     Ok(())
 }
 
+/// Opens the document database built by the last server start without changing it
+///
+/// Unlike `connect_to_database`, nothing is removed or ingested, so maintenance
+/// commands can work on the knowledge base the server answers from. The server
+/// must be stopped since the database files cannot be opened twice. The stored
+/// configurations of the collections present in the knowledge base are loaded.
+///
+/// # Returns
+/// * `Result<(), String>` - Success or an error message when no knowledge base was built
+pub async fn open_database() -> Result<(), String> {
+    initialize_globals().await;
+    if !Path::new(&config::get().database.path).join(DB_FILE_NAME).exists() {
+        return Err("No knowledge base has been built yet, start the server once to build it".to_string());
+    }
+
+    let db = create_database_connection().await?;
+    configure_database(&db).await?;
+    store_connections(db).await;
+
+    if count_documents().await? == 0 {
+        return Err("The knowledge base is empty".to_string());
+    }
+    for name in list_collection_names().await? {
+        collection_config(&name).await?;
+    }
+    Ok(())
+}
+
+/// Lists the names of the collections the ingested documents belong to
+async fn list_collection_names() -> Result<Vec<String>, String> {
+    #[derive(Deserialize)]
+    struct CollectionRow {
+        collection: String,
+    }
+
+    let db = get_database().await?;
+    let rows: Vec<CollectionRow> = db
+        .query(format!("SELECT collection FROM {} GROUP BY collection", DOCUMENT_INFO_TABLE_NAME))
+        .await
+        .and_then(|mut response| response.take(0))
+        .map_err(|e| e.to_string())?;
+    Ok(rows.into_iter().map(|row| row.collection).collect())
+}
+
 /// Initializes the global OnceCell singletons with empty values
 async fn initialize_globals() {
     DB_CONN.get_or_init(|| async { Mutex::new(None) }).await;
-    DOCUMENT_TABLE.get_or_init(|| async { Mutex::new(None) }).await;
+    COLLECTION_CONFIGS.get_or_init(|| async { Mutex::new(HashMap::new()) }).await;
 }
 
 /// Cleans up existing database files
 fn cleanup_database_files() -> Result<(), String> {
-    let db_path = PathBuf::from(DB_PATH);
+    let db_path = PathBuf::from(&config::get().database.path);
     if db_path.exists() {
         std::fs::remove_dir_all(&db_path).map_err(|e| {
-            eprintln!("Error removing existing database: {}", e);
+            error!("Error removing existing database: {}", e);
             e.to_string()
         })?;
-        println!("Removed existing database files");
+        info!("Removed existing database files");
     } else {
-        println!("No existing database found, creating a new one");
+        info!("No existing database found, creating a new one");
     }
     Ok(())
 }
 
 /// Creates a new database connection
 async fn create_database_connection() -> Result<Surreal<Db>, String> {
-    println!("Connecting to the database...");
-    let db = Surreal::new::<SurrealKv>(DB_FILE)
+    info!("Connecting to the database...");
+    let db_file = Path::new(&config::get().database.path).join(DB_FILE_NAME);
+    let db = Surreal::new::<SurrealKv>(db_file.to_string_lossy().as_ref())
         .await
         .map_err(|e| e.to_string())?;
-    println!("Database connected successfully");
+    info!("Database connected successfully");
     Ok(db)
 }
 
 /// Configures the namespace and database settings
 async fn configure_database(db: &Surreal<Db>) -> Result<(), String> {
-    db.use_ns(NAMESPACE).use_db(DATABASE)
+    let settings = &config::get().database;
+    db.use_ns(&settings.namespace).use_db(&settings.database)
         .await
         .map_err(|e| {
-            eprintln!("Error using namespace and database: {}", e);
+            error!("Error using namespace and database: {}", e);
             e.to_string()
         })
 }
 
-/// Creates the document table with semantic chunking
-async fn create_document_table(db: &Surreal<Db>) -> Result<DocumentTable<Db>, String> {
-    println!("Creating document table...");
-    let dt = db.document_table_builder(TABLE_NAME)
-        .with_chunker(SemanticChunker::default())
-        .at(EMBEDDINGS_FILE)
-        .build::<Document>()
+/// Creates the document table and the indexes used to search its chunks
+///
+/// The vector index compares embeddings by cosine distance; its dimension is
+/// read from the embedding model. The full-text analyzer splits on whitespace
+/// and character class changes without stemming, so exact identifiers, error
+/// codes and product names remain searchable.
+async fn create_document_table(db: &Surreal<Db>) -> Result<(), String> {
+    info!("Creating document table...");
+    let dimension = embed_text("dimension").await?.len();
+    db.query(format!(
+        "DEFINE ANALYZER IF NOT EXISTS chunk_analyzer TOKENIZERS blank, class FILTERS lowercase, ascii;
+         DEFINE TABLE IF NOT EXISTS {table} SCHEMALESS;
+         DEFINE INDEX IF NOT EXISTS {table}_text ON {table} FIELDS text SEARCH ANALYZER chunk_analyzer BM25;
+         DEFINE INDEX IF NOT EXISTS {table}_embedding ON {table} FIELDS embedding HNSW DIMENSION {dimension} DIST COSINE;",
+        table = TABLE_NAME,
+        dimension = dimension
+    ))
         .await
+        .and_then(|response| response.check())
         .map_err(|e| {
-            eprintln!("Error creating document table: {}", e);
+            error!("Error creating document table: {}", e);
             e.to_string()
         })?;
-    println!("Document table created successfully");
-    Ok(dt)
+    info!("Document table created successfully with {}-dimensional vectors", dimension);
+    Ok(())
 }
 
-/// Stores database connections in the global singletons
-async fn store_connections(db: Surreal<Db>, dt: DocumentTable<Db>) {
-    {
-        let mut db_guard = DB_CONN.get().unwrap().lock().await;
-        *db_guard = Some(db);
-    }
-    
-    {
-        let mut dt_guard = DOCUMENT_TABLE.get().unwrap().lock().await;
-        *dt_guard = Some(dt);
-    }
+/// Stores the database connection in the global singleton
+async fn store_connections(db: Surreal<Db>) {
+    let mut db_guard = DB_CONN.get().unwrap().lock().await;
+    *db_guard = Some(db);
 }
 
 /// Loads documents from the context folder and adds them to the document table
 ///
 /// Returns Ok(()) on success or an error message on failure
 async fn add_documents() -> Result<(), String> {
-    println!("Adding documents to the table...");
+    let options = &config::get().ingestion;
+    info!("Adding documents to the table...");
     
     // Load documents from folder
-    let raw_documents = load_documents_from_folder(CONTEXT_FOLDER)?;
+    let (documents, skipped) = load_documents_from_folder(&config::get().knowledge_base.context_folder).await?;
     
-    // Get document table reference
-    let table = get_document_table().await?;
-
-    // Process documents
-    let documents = process_documents(raw_documents).await?;
+    // Get database reference
+    let db = get_database().await?;
     
-    // Insert documents into table
-    insert_documents(&table, documents).await?;
+    // Insert documents into table, reporting the startup progress
+    let total = documents.len();
+    startup::update(|init| {
+        init.documents_ingested = 0;
+        init.documents_total = Some(total);
+    });
+    let on_inserted = |ingested: usize| startup::update(|init| init.documents_ingested = ingested);
+    let mut report = insert_documents(&db, documents, options, &on_inserted).await?;
+    report.skipped = skipped;
     
-    println!("All documents added successfully");
+    if !report.skipped.is_empty() {
+        warn!("{} files of the context folder were skipped", report.skipped.len());
+    }
+    info!(
+        "All documents added successfully: {} documents in {} ms ({:.2} documents/s, {:.1} chunks/s)",
+        report.documents,
+        report.elapsed_ms,
+        report.documents_per_second(),
+        report.chunks_per_second()
+    );
     Ok(())
 }
 
-/// Loads documents from the specified folder path
-fn load_documents_from_folder(folder_path: &str) -> Result<DocumentFolder, String> {
-    DocumentFolder::try_from(PathBuf::from(folder_path))
-        .map_err(|e| format!("Error loading documents from folder: {}", e))
+/// Loads the documents of every supported file in the specified folder
+///
+/// Files are visited in path order so document identifiers are stable across
+/// restarts. Files that cannot be read are returned with the reason they were
+/// skipped.
+async fn load_documents_from_folder(folder_path: &str) -> Result<(Vec<SourceDocument>, Vec<String>), String> {
+    let mut paths = Vec::new();
+    collect_files(Path::new(folder_path), &mut paths)
+        .map_err(|e| format!("Error loading documents from folder: {}", e))?;
+    paths.sort();
+    Ok(load_documents(paths, 0).await)
 }
 
-/// Processes raw documents into Document objects
-async fn process_documents(document_folder: DocumentFolder) -> Result<Vec<Document>, String> {
-    document_folder.into_documents().await
-        .map_err(|e| format!("Error processing documents: {}", e))
-        .map(|docs| {
-            docs.into_iter()
-                .map(|doc| {
-                    let title = doc.body().lines().next().unwrap_or("Unknown").to_string();
-                    let body = doc.body().to_string();
-                    Document::from_parts(title, body)
-                })
-                .collect()
-        })
+/// Loads the documents of the given files, numbering them from `first_id`
+///
+/// Files that cannot be read as documents are logged and returned separately,
+/// each with the reason it was skipped.
+async fn load_documents(paths: Vec<PathBuf>, first_id: usize) -> (Vec<SourceDocument>, Vec<String>) {
+    let context_folder = PathBuf::from(&config::get().knowledge_base.context_folder);
+    let mut documents = Vec::with_capacity(paths.len());
+    let mut skipped = Vec::new();
+    for path in paths {
+        let loaded = match FsDocument::try_from(path.clone()) {
+            Ok(file) => file.into_document().await.map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        match loaded {
+            Ok(document) => documents.push(SourceDocument {
+                id: first_id + documents.len(),
+                source: path.display().to_string(),
+                collection: collection_of(&context_folder, &path),
+                document: process_document(document),
+            }),
+            Err(e) => {
+                warn!("Skipping {}: {}", path.display(), e);
+                skipped.push(format!("{}: {}", path.display(), e));
+            }
+        }
+    }
+    (documents, skipped)
+}
+
+/// Returns the collection of a file: the subfolder of the context folder it is
+/// in, or the default collection for files placed directly in the context folder
+fn collection_of(context_folder: &Path, path: &Path) -> String {
+    let Ok(relative) = path.strip_prefix(context_folder) else {
+        return DEFAULT_COLLECTION.to_string();
+    };
+    let mut components = relative.components();
+    match (components.next(), components.next()) {
+        (Some(folder), Some(_)) => folder.as_os_str().to_string_lossy().to_string(),
+        _ => DEFAULT_COLLECTION.to_string(),
+    }
+}
+
+/// Collects the paths of the files in a folder and its subfolders
+fn collect_files(folder: &Path, paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(folder)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, paths)?;
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Titles a raw document with its first line
+fn process_document(document: Document) -> Document {
+    let title = document.body().lines().next().unwrap_or("Unknown").to_string();
+    let body = document.body().to_string();
+    Document::from_parts(title, body)
 }
 
 /// Inserts multiple documents into the document table
-async fn insert_documents(table: &DocumentTable<Db>, documents: Vec<Document>) -> Result<(), String> {
-    for document in documents {
-        insert_single_document(table, document).await?;
+///
+/// Every document is chunked with the strategy configured for its collection.
+/// The chunks are embedded ahead of insertion in batches, which fills the
+/// embedding cache so chunking only has to read the vectors back.
+/// Documents are then inserted with bounded concurrency, and `on_inserted` is
+/// called with the number of documents inserted so far after each one.
+async fn insert_documents(
+    db: &Surreal<Db>,
+    documents: Vec<SourceDocument>,
+    options: &IngestionConfig,
+    on_inserted: &(dyn Fn(usize) + Sync)
+) -> Result<IngestionReport, String> {
+    let time = Instant::now();
+    let document_count = documents.len();
+    let inserted = AtomicUsize::new(0);
+    let chunk_count = AtomicUsize::new(0);
+
+    // Embed chunks in batches ahead of insertion
+    prefetch_chunk_embeddings(&documents, options).await?;
+
+    // Insert documents concurrently
+    let (inserted, chunks_inserted) = (&inserted, &chunk_count);
+    futures::stream::iter(documents)
+        .map(|document| async move {
+            let strategy = collection_config(&document.collection).await?.chunking;
+            let chunks = chunk_document(&strategy, &document).await?;
+            let count = chunks.len();
+            insert_chunks(db, chunks).await?;
+            insert_document_info(db, &document, count).await?;
+            chunks_inserted.fetch_add(count, Ordering::Relaxed);
+            on_inserted(inserted.fetch_add(1, Ordering::Relaxed) + 1);
+            Ok::<(), String>(())
+        })
+        .buffer_unordered(options.concurrency)
+        .try_collect::<Vec<()>>()
+        .await?;
+
+    Ok(IngestionReport {
+        documents: document_count,
+        chunks: chunk_count.into_inner(),
+        elapsed_ms: time.elapsed().as_millis() as u64,
+        skipped: Vec::new(),
+    })
+}
+
+/// Embeds the chunks of the documents in batches through the embedding cache
+///
+/// Semantic chunk boundaries depend on the embeddings themselves, so nothing is
+/// embedded ahead of time for documents of collections using semantic chunking:
+/// the semantic chunker embeds the sentences of each document in its own model
+/// call while the document is inserted, and only the number of documents
+/// processed at the same time applies to them.
+async fn prefetch_chunk_embeddings(
+    documents: &[SourceDocument],
+    options: &IngestionConfig
+) -> Result<(), String> {
+    let mut texts = Vec::new();
+    for SourceDocument { document, collection, .. } in documents {
+        let strategy = collection_config(collection).await?.chunking;
+        if let Some(chunks) = chunking::split_text(&strategy, document.body()) {
+            texts.extend(chunks.iter().map(chunking::chunk_context_text));
+        }
     }
+
+    let embedding_model = embedding_model()?;
+    futures::stream::iter(texts.chunks(options.batch_size))
+        .map(|batch| embedding_model.embed_vec(batch.to_vec()))
+        .buffer_unordered(options.concurrency)
+        .try_for_each(|_| async { Ok(()) })
+        .await
+        .map_err(|e| {
+            error!("Error embedding chunks: {}", e);
+            e.to_string()
+        })?;
     Ok(())
 }
 
-/// Inserts a single document into the document table
-async fn insert_single_document(table: &DocumentTable<Db>, document: Document) -> Result<(), String> {
-    table.insert(document).await
+/// Splits a document with the chunking strategy of its collection and embeds every chunk
+async fn chunk_document(strategy: &ChunkingStrategy, source: &SourceDocument) -> Result<Vec<ChunkRow>, String> {
+    let embedding_model = embedding_model()?;
+    let document = &source.document;
+    let chunks = CollectionChunker::new(strategy.clone())
+        .chunk(document, embedding_model)
+        .await
+        .map_err(|e| {
+            error!("Error chunking document: {}", e);
+            e.to_string()
+        })?;
+
+    let sections = chunking::document_sections(strategy, document.body());
+    let mut rows = Vec::with_capacity(chunks.len());
+    for (position, chunk) in chunks.into_iter().enumerate() {
+        let text = chunking::chunk_context_text(&chunking::chunk_at(document.body(), &sections, chunk.byte_range.clone()));
+        let embedding = match chunk.embeddings.first() {
+            Some(embedding) => embedding.vector().to_vec(),
+            None => embed_text(&text).await?,
+        };
+        rows.push(ChunkRow {
+            document_id: source.id,
+            position,
+            collection: source.collection.clone(),
+            title: document.title().to_string(),
+            start: chunk.byte_range.start,
+            end: chunk.byte_range.end,
+            text,
+            embedding,
+        });
+    }
+    Ok(rows)
+}
+
+/// Inserts the chunks of a document into the document table
+async fn insert_chunks(db: &Surreal<Db>, chunks: Vec<ChunkRow>) -> Result<(), String> {
+    db.query(format!("INSERT INTO {} $chunks", TABLE_NAME))
+        .bind(("chunks", chunks))
+        .await
+        .and_then(|response| response.check())
         .map_err(|e| {
-            eprintln!("Error adding document: {}", e);
+            error!("Error adding document chunks: {}", e);
             e.to_string()
         })?;
     Ok(())
 }
 
-/// Gets a reference to the document table from the global singleton
-async fn get_document_table() -> Result<impl std::ops::Deref<Target = DocumentTable<Db>> + 'static, String> {
-    let document_table_mutex_ref = DOCUMENT_TABLE
-        .get()
-        .ok_or("Document table not initialized")?;
+/// Records the title, source file, collection, text, chunk count and ingestion time of a document
+async fn insert_document_info(
+    db: &Surreal<Db>,
+    source: &SourceDocument,
+    chunk_count: usize
+) -> Result<(), String> {
+    db.query(format!(
+        "CREATE {} SET id = $id, title = $title, source = $source, collection = $collection, body = $body, chunk_count = $chunk_count, ingested_at = time::now()",
+        DOCUMENT_INFO_TABLE_NAME
+    ))
+        .bind(("id", source.id))
+        .bind(("title", source.document.title().to_string()))
+        .bind(("source", source.source.clone()))
+        .bind(("collection", source.collection.clone()))
+        .bind(("body", source.document.body().to_string()))
+        .bind(("chunk_count", chunk_count))
+        .await
+        .and_then(|response| response.check())
+        .map_err(|e| {
+            error!("Error recording document information: {}", e);
+            e.to_string()
+        })?;
+    Ok(())
+}
 
-    let table_guard = document_table_mutex_ref.lock().await;
-    if table_guard.is_none() {
-        return Err("Document table is None".to_string());
+/// Gets the embedding model the chunks are embedded with
+fn embedding_model() -> Result<&'static CachedEmbedder, String> {
+    EMBEDDING_MODEL.get().ok_or("Embedding model not initialized".to_string())
+}
+
+/// Gets the configuration a collection is built with
+///
+/// The stored configuration is loaded the first time the collection is used and
+/// kept until the knowledge base is rebuilt, so every document of a collection
+/// is chunked the same way.
+///
+/// # Parameters
+/// * `name` - The name of the collection
+///
+/// # Returns
+/// * `Result<CollectionConfig, String>` - The configuration of the collection or an error
+pub async fn collection_config(name: &str) -> Result<CollectionConfig, String> {
+    let mut configs = COLLECTION_CONFIGS
+        .get()
+        .ok_or("Collection configurations not initialized")?
+        .lock()
+        .await;
+    if let Some(config) = configs.get(name) {
+        return Ok(config.clone());
     }
-    
-    Ok(tokio::sync::MutexGuard::map(table_guard, |t| {
-        t.as_mut().unwrap()
-    }))
+
+    let config = load_collection_config(name).await?;
+    info!("Collection {} uses {:?} chunking", name, config.chunking);
+    configs.insert(name.to_string(), config.clone());
+    Ok(config)
+}
+
+/// Lists the configurations of the collections the knowledge base was built with
+///
+/// # Returns
+/// * `Result<Vec<CollectionConfig>, String>` - The configurations by collection name or an error
+pub async fn list_collections() -> Result<Vec<CollectionConfig>, String> {
+    let mut configs: Vec<CollectionConfig> = COLLECTION_CONFIGS
+        .get()
+        .ok_or("Collection configurations not initialized")?
+        .lock()
+        .await
+        .values()
+        .cloned()
+        .collect();
+    configs.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(configs)
+}
+
+/// Gets a handle to the database connection from the global singleton
+async fn get_database() -> Result<Surreal<Db>, String> {
+    DB_CONN
+        .get()
+        .ok_or("Database not initialized")?
+        .lock()
+        .await
+        .clone()
+        .ok_or("Database connection is None".to_string())
 }
 
-/// Performs a semantic search query against the document database
+/// Performs a search query against the document database
+///
+/// Depending on `options.mode` the query is answered by the embedding index,
+/// the full-text index, or a reciprocal rank fusion of both. When reranking or
+/// MMR selection is enabled a wider candidate set is retrieved first; reranking
+/// reorders it with the language model and MMR picks a diverse subset of it
+/// before the best `options.top_k` results are returned.
 ///
 /// # Parameters
 /// * `query` - The search query text
+/// * `options` - The retrieval mode, number of results, fusion weight, reranking and MMR settings
+/// * `usage` - Accumulates the tokens of the reranking prompts
 ///
 /// # Returns
 /// * `Result<Vec<SimpleDocumentResult>, String>` - A vector of matching document results or an error
-pub async fn query(query: &str) -> Result<Vec<SimpleDocumentResult>, String> {
-    // Get document table
-    let table = get_document_table().await?;
-    
-    // Create embedding from query
-    let query_embed = create_embedding_from_query(&table, query).await?;
-    
-    // Perform semantic search
-    let results = perform_semantic_search(&table, query_embed).await?;
-    
-    // Convert results to SimpleDocumentResult
-    Ok(convert_search_results(results))
-}
-
-/// Creates an embedding vector from the query text
-async fn create_embedding_from_query(
-    table: &DocumentTable<Db>, 
-    query: &str
-) -> Result<Embedding, String> {
-    table.embedding_model().embed(query).await.map_err(|e| {
-        eprintln!("Error creating embedding: {}", e);
-        e.to_string()
+#[tracing::instrument(skip_all, fields(mode = ?options.mode, top_k = options.top_k, rerank = options.rerank))]
+pub async fn query(query: &str, options: &SearchOptions, usage: &mut TokenUsage) -> Result<Vec<SimpleDocumentResult>, String> {
+    let time = Instant::now();
+    let candidates = options.candidate_count();
+    let mut embeddings = ChunkEmbeddings::new();
+
+    let results = match options.mode {
+        SearchMode::Vector => vector_query(query, candidates, &mut embeddings).await?,
+        SearchMode::Keyword => keyword_query(query, candidates, &mut embeddings).await?,
+        SearchMode::Hybrid => {
+            let fusion_candidates = candidates.saturating_mul(4).max(HYBRID_MIN_CANDIDATES);
+            let vector_results = vector_query(query, fusion_candidates, &mut embeddings).await?;
+            let keyword_results = keyword_query(query, fusion_candidates, &mut embeddings).await?;
+            fuse_rankings(vector_results, keyword_results, options.keyword_weight)
+                .into_iter()
+                .take(candidates)
+                .collect()
+        }
+    };
+
+    let results = if options.rerank {
+        rerank(query, results, usage).await?
+    } else {
+        results
+    };
+
+    let results = match options.mmr_lambda {
+        Some(lambda) => diversify_results(query, results, &embeddings, lambda, options.top_k).await?,
+        None => results.into_iter().take(options.top_k).collect(),
+    };
+    prometheus::observe_retrieval(time.elapsed());
+    debug!(results = results.len(), elapsed_ms = time.elapsed().as_millis() as u64, "Search completed");
+    Ok(results)
+}
+
+/// Performs several search queries and merges their results
+///
+/// Used when a message has been rewritten into multiple paraphrased queries.
+///
+/// # Parameters
+/// * `queries` - The search query texts
+/// * `options` - The search options applied to every query
+/// * `usage` - Accumulates the tokens of the reranking prompts
+///
+/// # Returns
+/// * `Result<Vec<SimpleDocumentResult>, String>` - The best `options.top_k` results across all queries or an error
+pub async fn query_many(
+    queries: &[String],
+    options: &SearchOptions,
+    usage: &mut TokenUsage,
+) -> Result<Vec<SimpleDocumentResult>, String> {
+    let mut result_sets = Vec::with_capacity(queries.len());
+    for q in queries {
+        result_sets.push(query(q, options, usage).await?);
+    }
+    Ok(merge_query_results(result_sets, options.top_k))
+}
+
+/// Selects a diverse subset of the results with maximal marginal relevance
+///
+/// The chunks are compared through the embeddings stored with them, read by the
+/// search itself; only the query is embedded, with the model the chunks were
+/// indexed with, so it lives in the same space as the stored vectors.
+async fn diversify_results(
+    query: &str,
+    results: Vec<SimpleDocumentResult>,
+    embeddings: &ChunkEmbeddings,
+    lambda: f32,
+    top_k: usize,
+) -> Result<Vec<SimpleDocumentResult>, String> {
+    let query_embed = embed_text(query).await?;
+
+    let candidates = results.into_iter()
+        .map(|result| {
+            let chunk_embed = result.chunk_id.as_ref()
+                .and_then(|id| embeddings.get(id))
+                .cloned()
+                .unwrap_or_default();
+            (result, chunk_embed)
+        })
+        .collect();
+
+    Ok(maximal_marginal_relevance(&query_embed, candidates, lambda, top_k))
+}
+
+/// Lists the chunks stored in the document table with their embeddings
+///
+/// The embeddings are the ones computed at ingestion, so nothing is embedded again.
+///
+/// # Parameters
+/// * `limit` - Maximum number of chunks to return, in document and position order
+///
+/// # Returns
+/// * `Result<Vec<StoredChunk>, String>` - The chunks with their embeddings or an error
+pub async fn list_chunk_embeddings(limit: usize) -> Result<Vec<StoredChunk>, String> {
+    let db = get_database().await?;
+    db.query(format!(
+        "SELECT document_id, position, title, text, embedding FROM {} ORDER BY document_id, position LIMIT $limit",
+        TABLE_NAME
+    ))
+        .bind(("limit", limit))
+        .await
+        .and_then(|mut response| response.take(0))
+        .map_err(|e| {
+            error!("Error listing chunk embeddings: {}", e);
+            e.to_string()
+        })
+}
+
+/// Finds the stored chunks nearest to an embedding through the vector index
+///
+/// # Parameters
+/// * `embedding` - The embedding to search around
+/// * `neighbours` - Number of chunks to return
+///
+/// # Returns
+/// * `Result<Vec<(StoredChunk, f32)>, String>` - The nearest chunks with their cosine distance, closest first, or an error
+pub async fn nearest_chunks(embedding: Vec<f32>, neighbours: usize) -> Result<Vec<(StoredChunk, f32)>, String> {
+    let rows = nearest_rows(embedding, neighbours).await?;
+    Ok(rows.into_iter().map(|row| {
+        let chunk = StoredChunk {
+            document_id: row.document_id,
+            position: row.position,
+            title: row.title,
+            text: row.text,
+            embedding: row.embedding,
+        };
+        (chunk, row.distance)
+    }).collect())
+}
+
+/// Counts the documents ingested into the knowledge base
+///
+/// # Returns
+/// * `Result<usize, String>` - The number of documents or an error
+pub async fn count_documents() -> Result<usize, String> {
+    #[derive(Deserialize)]
+    struct CountRow {
+        count: usize,
+    }
+
+    let db = get_database().await?;
+    let count: Option<CountRow> = db
+        .query(format!("SELECT count() AS count FROM {} GROUP ALL", DOCUMENT_INFO_TABLE_NAME))
+        .await
+        .and_then(|mut response| response.take(0))
+        .map_err(|e| e.to_string())?;
+    Ok(count.map_or(0, |row| row.count))
+}
+
+/// Lists the documents ingested into the knowledge base
+///
+/// # Returns
+/// * `Result<Vec<DocumentInfo>, String>` - The documents in ingestion order or an error
+pub async fn list_documents() -> Result<Vec<DocumentInfo>, String> {
+    let db = get_database().await?;
+    db.query(format!(
+        "SELECT record::id(id) AS id, title, source, collection, chunk_count, <string> ingested_at AS ingested_at FROM {} ORDER BY id",
+        DOCUMENT_INFO_TABLE_NAME
+    ))
+        .await
+        .and_then(|mut response| response.take(0))
+        .map_err(|e| {
+            error!("Error listing documents: {}", e);
+            e.to_string()
+        })
+}
+
+/// Gets a document of the knowledge base with its chunks
+///
+/// # Parameters
+/// * `id` - Identifier of the document
+///
+/// # Returns
+/// * `Result<DocumentDetails, String>` - The document and its chunks in order, or an error
+pub async fn get_document(id: usize) -> Result<DocumentDetails, String> {
+    #[derive(Deserialize)]
+    struct ChunkText {
+        text: String,
+    }
+
+    let db = get_database().await?;
+    let mut response = db
+        .query(format!(
+            "SELECT record::id(id) AS id, title, source, collection, chunk_count, <string> ingested_at AS ingested_at FROM {} WHERE id = type::thing($table, $id);
+             SELECT text, position FROM {} WHERE document_id = $id ORDER BY position",
+            DOCUMENT_INFO_TABLE_NAME,
+            TABLE_NAME
+        ))
+        .bind(("table", DOCUMENT_INFO_TABLE_NAME))
+        .bind(("id", id))
+        .await
+        .map_err(|e| {
+            error!("Error loading document: {}", e);
+            e.to_string()
+        })?;
+
+    let info: Option<DocumentInfo> = response.take(0).map_err(|e| e.to_string())?;
+    let chunks: Vec<ChunkText> = response.take(1).map_err(|e| e.to_string())?;
+    Ok(DocumentDetails {
+        info: info.ok_or(format!("Document {} not found", id))?,
+        chunks: chunks.into_iter().map(|chunk| chunk.text).collect(),
     })
 }
 
-/// Performs semantic search using the embedding vector
-async fn perform_semantic_search(
-    table: &DocumentTable<Db>,
-    query_embed: Embedding
-) -> Result<Vec<EmbeddingIndexedTableSearchResult<Document>>, String> {
-    table.search(query_embed)
-        .with_results(1)
+/// Lists the documents of the knowledge base with their full text and metadata
+///
+/// # Returns
+/// * `Result<Vec<DocumentRecord>, String>` - The documents in ingestion order or an error
+pub async fn list_document_records() -> Result<Vec<DocumentRecord>, String> {
+    let db = get_database().await?;
+    db.query(format!(
+        "SELECT record::id(id) AS id, title, source, collection, body, <string> ingested_at AS ingested_at FROM {} ORDER BY id",
+        DOCUMENT_INFO_TABLE_NAME
+    ))
+        .await
+        .and_then(|mut response| response.take(0))
+        .map_err(|e| {
+            error!("Error listing document records: {}", e);
+            e.to_string()
+        })
+}
+
+/// Lists the chunks of a document with their offsets and stored embeddings
+///
+/// # Parameters
+/// * `document_id` - Identifier of the document
+///
+/// # Returns
+/// * `Result<Vec<ChunkRow>, String>` - The chunks in document order or an error
+pub async fn list_document_chunks(document_id: usize) -> Result<Vec<ChunkRow>, String> {
+    let db = get_database().await?;
+    db.query(format!("SELECT * OMIT id FROM {} WHERE document_id = $id ORDER BY position", TABLE_NAME))
+        .bind(("id", document_id))
+        .await
+        .and_then(|mut response| response.take(0))
+        .map_err(|e| {
+            error!("Error listing document chunks: {}", e);
+            e.to_string()
+        })
+}
+
+/// Restores documents and their embedded chunks directly into the document table
+///
+/// Nothing is chunked or embedded: the chunks are inserted with the vectors they
+/// carry, which must come from the running embedding model. The documents keep
+/// their title, source, collection and ingestion time but are numbered after the
+/// documents already ingested. Documents with the same source as a restored
+/// document are replaced.
+///
+/// # Parameters
+/// * `documents` - The documents with their chunks
+///
+/// # Returns
+/// * `Result<(), String>` - Success or an error message
+pub async fn restore_documents(documents: Vec<(DocumentRecord, Vec<ChunkRow>)>) -> Result<(), String> {
+    let db = get_database().await?;
+    let sources: Vec<String> = documents.iter().map(|(document, _)| document.source.clone()).collect();
+    db.query(format!(
+        "LET $replaced = SELECT VALUE record::id(id) FROM {info} WHERE source IN $sources;
+         DELETE {chunks} WHERE document_id IN $replaced;
+         DELETE {info} WHERE source IN $sources;",
+        info = DOCUMENT_INFO_TABLE_NAME,
+        chunks = TABLE_NAME
+    ))
+        .bind(("sources", sources))
+        .await
+        .and_then(|response| response.check())
+        .map_err(|e| {
+            error!("Error replacing restored documents: {}", e);
+            e.to_string()
+        })?;
+
+    let mut next_id = list_documents().await?
+        .iter()
+        .map(|document| document.id + 1)
+        .max()
+        .unwrap_or(0);
+    for (mut document, mut chunks) in documents {
+        document.id = next_id;
+        next_id += 1;
+        for chunk in &mut chunks {
+            chunk.document_id = document.id;
+            chunk.collection = document.collection.clone();
+            chunk.title = document.title.clone();
+        }
+        let chunk_count = chunks.len();
+        insert_chunks(&db, chunks).await?;
+        restore_document_info(&db, document, chunk_count).await?;
+    }
+    Ok(())
+}
+
+/// Records a restored document, keeping its original ingestion time
+async fn restore_document_info(db: &Surreal<Db>, document: DocumentRecord, chunk_count: usize) -> Result<(), String> {
+    db.query(format!(
+        "CREATE {} SET id = $id, title = $title, source = $source, collection = $collection, body = $body, chunk_count = $chunk_count, ingested_at = <datetime> $ingested_at",
+        DOCUMENT_INFO_TABLE_NAME
+    ))
+        .bind(("id", document.id))
+        .bind(("title", document.title))
+        .bind(("source", document.source))
+        .bind(("collection", document.collection))
+        .bind(("body", document.body))
+        .bind(("chunk_count", chunk_count))
+        .bind(("ingested_at", document.ingested_at))
+        .await
+        .and_then(|response| response.check())
+        .map_err(|e| {
+            error!("Error recording restored document: {}", e);
+            e.to_string()
+        })?;
+    Ok(())
+}
+
+/// Shows how a text would be chunked by a strategy without ingesting it
+///
+/// # Parameters
+/// * `strategy` - The chunking strategy to preview
+/// * `text` - The document text
+///
+/// # Returns
+/// * `Result<Vec<ChunkPreview>, String>` - The chunks in document order or an error
+pub async fn preview_chunks(strategy: &ChunkingStrategy, text: &str) -> Result<Vec<ChunkPreview>, String> {
+    chunking::preview_chunks(strategy, text, embedding_model()?).await
+}
+
+/// Performs a nearest-neighbour search over the chunk embeddings
+///
+/// The distance is turned into a score where higher values mean more relevant.
+/// The stored embeddings of the results are added to `embeddings`.
+async fn vector_query(query: &str, results: usize, embeddings: &mut ChunkEmbeddings) -> Result<Vec<SimpleDocumentResult>, String> {
+    if results == 0 {
+        return Ok(Vec::new());
+    }
+    let query_embed = embed_text(query).await?;
+    let rows = nearest_rows(query_embed, results).await?;
+
+    Ok(rows.into_iter().enumerate().map(|(rank, row)| {
+        let id = chunk_id(row.document_id, row.position);
+        embeddings.insert(id.clone(), row.embedding);
+        SimpleDocumentResult {
+            title: row.title,
+            body: row.text,
+            score: 1.0 / (1.0 + row.distance),
+            chunk_id: Some(id),
+            signals: ScoreSignals {
+                vector_distance: Some(row.distance),
+                vector_rank: Some(rank),
+                ..Default::default()
+            },
+        }
+    }).collect())
+}
+
+/// Runs a nearest-neighbour search over the HNSW index of the chunk embeddings
+async fn nearest_rows(embedding: Vec<f32>, results: usize) -> Result<Vec<VectorSearchRow>, String> {
+    let results = results.min(MAX_INDEX_RESULTS);
+    let db = get_database().await?;
+    db.query(format!(
+        "SELECT document_id, position, title, text, embedding, vector::distance::knn() AS distance FROM {} \
+         WHERE embedding <|{},{}|> $embedding ORDER BY distance",
+        TABLE_NAME,
+        results,
+        results.max(VECTOR_SEARCH_MIN_EF)
+    ))
+        .bind(("embedding", embedding))
         .await
-        .map_err(|e| e.to_string())
+        .and_then(|mut response| response.take(0))
+        .map_err(|e| {
+            error!("Error performing semantic search: {}", e);
+            e.to_string()
+        })
 }
 
-/// Converts search results to SimpleDocumentResult objects
-fn convert_search_results(
-    results: Vec<EmbeddingIndexedTableSearchResult<Document>>
-) -> Vec<SimpleDocumentResult> {
-    results.into_iter().map(|doc_result| {
+/// Performs a BM25 full-text search over the chunks
+///
+/// The stored embeddings of the results are added to `embeddings`.
+async fn keyword_query(query: &str, results: usize, embeddings: &mut ChunkEmbeddings) -> Result<Vec<SimpleDocumentResult>, String> {
+    let db = get_database().await?;
+
+    let rows: Vec<KeywordSearchRow> = db
+        .query(format!(
+            "SELECT document_id, position, title, text, embedding, search::score(1) AS score FROM {} \
+             WHERE text @1@ $query ORDER BY score DESC LIMIT $limit",
+            TABLE_NAME
+        ))
+        .bind(("query", query.to_string()))
+        .bind(("limit", results.min(MAX_INDEX_RESULTS)))
+        .await
+        .and_then(|mut response| response.take(0))
+        .map_err(|e| {
+            error!("Error performing keyword search: {}", e);
+            e.to_string()
+        })?;
+
+    Ok(rows.into_iter().enumerate().map(|(rank, row)| {
+        let id = chunk_id(row.document_id, row.position);
+        embeddings.insert(id.clone(), row.embedding);
         SimpleDocumentResult {
-            title: doc_result.record.title().to_string(),
-            body: doc_result.record.body().to_string(),
-            score: doc_result.distance,
+            title: row.title,
+            body: row.text,
+            score: row.score,
+            chunk_id: Some(id),
+            signals: ScoreSignals {
+                keyword_score: Some(row.score),
+                keyword_rank: Some(rank),
+                ..Default::default()
+            },
         }
-    }).collect()
-}
\ No newline at end of file
+    }).collect())
+}
+
+/// Formats the identifier of a chunk from its document and position
+fn chunk_id(document_id: usize, position: usize) -> String {
+    format!("{}:{}", document_id, position)
+}
@@ -3,35 +3,92 @@
 //! This module provides functionality for text embedding generation using the BERT model.
 //! It manages a singleton embedding model instance and offers methods to convert text
 //! into numerical vector representations for semantic search and comparison.
+//! Embeddings go through the persistent embedding cache, so identical texts are only
+//! embedded once per model.
 
-use std::sync::Mutex;
-use kalosm::language::{Bert};
+use kalosm::language::{Bert, BertSource};
 use tokio::sync::OnceCell;
+use crate::model::settings::EmbeddingModelSource;
+use crate::model::status::{ComponentState, LoadStage};
+use crate::server::config;
+use crate::server::startup;
+use crate::server::status::{self, Component};
+use crate::server::embedding_cache::{prepare_embedding_cache, CachedEmbedder};
+use tracing::{debug, info};
 
 /// Global singleton for the BERT embedding model
-/// Uses OnceCell and Mutex for thread-safe access and initialization
-pub static EMBEDDING_MODEL: OnceCell<Mutex<Bert>> = OnceCell::const_new();
+/// Uses OnceCell for thread-safe initialization; the embedder itself can be shared
+/// between tasks, so no lock is held while embeddings are computed
+pub static EMBEDDING_MODEL: OnceCell<CachedEmbedder> = OnceCell::const_new();
+
+/// Largest number of texts a client can embed in a single request
+pub const MAX_REQUEST_TEXTS: usize = 256;
+
+/// Largest number of characters of each text a client asks to embed
+pub const MAX_REQUEST_TEXT_CHARS: usize = 8_000;
+
+/// Returns the identifier of the configured embedding model
+///
+/// Cached vectors computed by any other model are discarded at startup.
+pub fn embedding_model_id() -> &'static str {
+    config::get().model.embedding.id()
+}
+
+/// Loads the configured BERT model wrapped in the embedding cache
+///
+/// Stale cache entries from a previously configured model are removed before
+/// the model is returned.
+///
+/// Returns the cached embedder on success or an error message on failure
+async fn load_embedding_model() -> Result<CachedEmbedder, String> {
+    let source = config::get().model.embedding;
+    let bert = Bert::builder()
+        .with_source(bert_source(source))
+        .build_with_loading_handler(|progress| {
+            let (stage, fraction) = startup::load_stage(&progress);
+            startup::update(|init| {
+                init.embedding_stage = stage;
+                init.embedding_progress = fraction;
+            });
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    prepare_embedding_cache(source.id()).await?;
+    Ok(CachedEmbedder::new(bert, source.id()))
+}
+
+/// Returns the kalosm source of a supported embedding model
+fn bert_source(source: EmbeddingModelSource) -> BertSource {
+    match source {
+        EmbeddingModelSource::SnowflakeArcticEmbedExtraSmall => BertSource::snowflake_arctic_embed_extra_small(),
+        EmbeddingModelSource::SnowflakeArcticEmbedSmall => BertSource::snowflake_arctic_embed_small(),
+        EmbeddingModelSource::SnowflakeArcticEmbedMedium => BertSource::snowflake_arctic_embed_medium(),
+        EmbeddingModelSource::SnowflakeArcticEmbedLarge => BertSource::snowflake_arctic_embed_large(),
+    }
+}
 
 /// Initializes the BERT embedding model
 ///
 /// This function:
 /// 1. Checks if the model is already initialized
-/// 2. If not, creates a new Bert model instance
+/// 2. If not, loads the configured Bert model behind the embedding cache
 /// 3. Stores the model in the global singleton
 ///
 /// The embedding model is used to convert text into vector representations
 /// that capture semantic meaning, which enables similarity-based searches.
 ///
 /// Returns Ok(()) on success or an error message on failure
+#[tracing::instrument]
 pub async fn init_embedding_model() -> Result<(), String> {
-    use kalosm::language::{Bert, BertSource, FileSource};
-
     if EMBEDDING_MODEL.get().is_none() {
-        println!("Initializing embedding model...");
-        let bert = Bert::new().await.map_err(|e| e.to_string());
-        println!("Embedding model loaded successfully");
-        EMBEDDING_MODEL.set(Mutex::new(bert?))
+        info!("Initializing embedding model...");
+        status::set_state(Component::EmbeddingModel, ComponentState::Loading);
+        let bert = status::track(Component::EmbeddingModel, load_embedding_model().await)?;
+        info!("Embedding model loaded successfully");
+        EMBEDDING_MODEL.set(bert)
             .map_err(|_| "Couldn't set embedding model".to_string())?;
+        status::set_state(Component::EmbeddingModel, ComponentState::Ready);
+        startup::update(|init| init.embedding_stage = LoadStage::Ready);
     }
     Ok(())
 }
@@ -40,7 +97,7 @@ pub async fn init_embedding_model() -> Result<(), String> {
 ///
 /// This function:
 /// 1. Accesses the global embedding model
-/// 2. Generates vector embeddings for the provided text
+/// 2. Looks the text up in the embedding cache, generating the embedding on a miss
 /// 3. Returns the vector representation
 ///
 /// The generated embeddings capture the semantic meaning of the text
@@ -51,17 +108,78 @@ pub async fn init_embedding_model() -> Result<(), String> {
 ///
 /// # Returns
 /// * `Result<Vec<f32>, String>` - The embedding vector or an error message
+#[tracing::instrument(skip_all, fields(chars = text.chars().count()))]
 pub async fn embed_text(text: &str) -> Result<Vec<f32>, String> {
     use kalosm::language::EmbedderExt;
     let embedding_model = EMBEDDING_MODEL
         .get()
-        .ok_or("Embedding model not initialized")?
-        .lock()
-        .map_err(|_| "Error locking embedding model")?;
+        .ok_or("Embedding model not initialized")?;
 
     let embeddings = embedding_model.embed(text)
         .await
         .map_err(|e| e.to_string())?;
-    println!("Embedding generated for text: {:?}", embeddings.vector().to_vec());
+    debug!(dimensions = embeddings.vector().len(), "Embedding generated");
     Ok(embeddings.vector().to_vec())
-}
\ No newline at end of file
+}
+
+/// Embeds the texts of a client request in batches of the configured size
+///
+/// Requests with more than `MAX_REQUEST_TEXTS` texts, or a text longer than
+/// `MAX_REQUEST_TEXT_CHARS` characters, are rejected. The texts are embedded
+/// `ingestion.batch_size` at a time, like the chunks of the knowledge base.
+///
+/// # Parameters
+/// * `texts` - The texts to convert into embeddings
+///
+/// # Returns
+/// * `Result<Vec<Vec<f32>>, String>` - One embedding vector per text, in order, or an error message
+pub async fn embed_requested_texts(texts: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+    check_requested_texts(&texts)?;
+
+    let mut embeddings = Vec::with_capacity(texts.len());
+    for batch in texts.chunks(config::get().ingestion.batch_size) {
+        embeddings.extend(embed_texts(batch.to_vec()).await?);
+    }
+    Ok(embeddings)
+}
+
+/// Rejects client requests with more than `MAX_REQUEST_TEXTS` texts or a text
+/// longer than `MAX_REQUEST_TEXT_CHARS` characters
+///
+/// # Parameters
+/// * `texts` - The texts of the request
+///
+/// # Returns
+/// * `Result<(), String>` - Success or the reason the request is rejected
+pub fn check_requested_texts(texts: &[String]) -> Result<(), String> {
+    if texts.len() > MAX_REQUEST_TEXTS {
+        return Err(format!("At most {} texts can be embedded per request", MAX_REQUEST_TEXTS));
+    }
+    if texts.iter().any(|text| text.chars().count() > MAX_REQUEST_TEXT_CHARS) {
+        return Err(format!("Texts can have at most {} characters", MAX_REQUEST_TEXT_CHARS));
+    }
+    Ok(())
+}
+
+/// Converts a batch of texts into vector embeddings
+///
+/// All the texts that are not cached yet are embedded by the model in a single
+/// call, which is much faster than embedding them one by one.
+///
+/// # Parameters
+/// * `texts` - The texts to convert into embeddings
+///
+/// # Returns
+/// * `Result<Vec<Vec<f32>>, String>` - One embedding vector per text, in order, or an error message
+#[tracing::instrument(skip_all, fields(texts = texts.len()))]
+pub async fn embed_texts(texts: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+    use kalosm::language::EmbedderExt;
+    let embedding_model = EMBEDDING_MODEL
+        .get()
+        .ok_or("Embedding model not initialized")?;
+
+    let embeddings = embedding_model.embed_vec(texts)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(embeddings.iter().map(|embedding| embedding.vector().to_vec()).collect())
+}
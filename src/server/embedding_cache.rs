@@ -0,0 +1,246 @@
+//! Embedding Cache Implementation
+//!
+//! This module provides a persistent cache of embedding vectors stored in the
+//! application database. Vectors are keyed by a hash of the embedding model id,
+//! the embedding variant (query or document) and the text, so re-embedding the
+//! same text, or re-ingesting identical chunks, does not run the model again.
+//!
+//! The cache is exposed as an embedder wrapping the BERT model, which lets the
+//! document table use it transparently for both chunk and query embeddings.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use kalosm::language::{Bert, Embedder, Embedding, EmbeddingInput};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::model::embedding::EmbeddingCacheStats;
+use crate::server::app_store::get_app_db;
+use tracing::error;
+
+/// Name of the table holding the cached vectors
+const CACHE_TABLE: &str = "embedding_cache";
+
+/// Number of embeddings served from the cache since the server started
+static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+
+/// Number of embeddings computed by the model since the server started
+static CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// A cached embedding vector
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedVector {
+    /// Hash of the model id, variant and text the vector was computed for
+    key: String,
+    /// Identifier of the model that computed the vector
+    model_id: String,
+    /// The embedding vector
+    vector: Vec<f32>,
+}
+
+/// Error returned by the cached embedder
+#[derive(Debug)]
+pub enum EmbeddingCacheError {
+    /// The wrapped model failed to embed the texts
+    Model(<Bert as Embedder>::Error),
+    /// The wrapped model returned fewer vectors than texts
+    MissingVectors { expected: usize, received: usize },
+}
+
+impl fmt::Display for EmbeddingCacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmbeddingCacheError::Model(e) => write!(f, "{}", e),
+            EmbeddingCacheError::MissingVectors { expected, received } => {
+                write!(f, "The embedding model returned {} vectors for {} texts", received, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EmbeddingCacheError {}
+
+/// Embedder that serves vectors from the persistent cache when possible
+///
+/// Texts missing from the cache are embedded by the wrapped BERT model in a
+/// single batch and stored for later calls. Cache failures are logged and
+/// treated as misses, so they never prevent an embedding from being produced.
+pub struct CachedEmbedder {
+    model: Bert,
+    model_id: String,
+}
+
+impl CachedEmbedder {
+    /// Wraps a BERT model identified by `model_id`
+    pub fn new(model: Bert, model_id: impl Into<String>) -> Self {
+        Self {
+            model,
+            model_id: model_id.into(),
+        }
+    }
+
+    /// Returns the identifier of the wrapped model
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+}
+
+impl Embedder for CachedEmbedder {
+    type Error = EmbeddingCacheError;
+
+    async fn embed_vec_for(&self, inputs: Vec<EmbeddingInput>) -> Result<Vec<Embedding>, Self::Error> {
+        let keys: Vec<String> = inputs.iter()
+            .map(|input| cache_key(&self.model_id, input))
+            .collect();
+
+        let mut cached = load_cached(&keys).await.unwrap_or_else(|e| {
+            error!("Error reading embedding cache: {}", e);
+            HashMap::new()
+        });
+
+        // Embed the texts that are not cached yet in a single batch
+        let missing: Vec<usize> = (0..inputs.len())
+            .filter(|&index| !cached.contains_key(&keys[index]))
+            .collect();
+        CACHE_HITS.fetch_add((inputs.len() - missing.len()) as u64, Ordering::Relaxed);
+        CACHE_MISSES.fetch_add(missing.len() as u64, Ordering::Relaxed);
+
+        if !missing.is_empty() {
+            let missing_inputs = missing.iter().map(|&index| inputs[index].clone()).collect();
+            let embeddings = self.model.embed_vec_for(missing_inputs)
+                .await
+                .map_err(EmbeddingCacheError::Model)?;
+            if embeddings.len() < missing.len() {
+                return Err(EmbeddingCacheError::MissingVectors {
+                    expected: missing.len(),
+                    received: embeddings.len(),
+                });
+            }
+
+            let new_entries: Vec<CachedVector> = missing.iter()
+                .zip(embeddings)
+                .map(|(&index, embedding)| CachedVector {
+                    key: keys[index].clone(),
+                    model_id: self.model_id.clone(),
+                    vector: embedding.vector().to_vec(),
+                })
+                .collect();
+            if let Err(e) = store_cached(&new_entries).await {
+                error!("Error writing embedding cache: {}", e);
+            }
+            cached.extend(new_entries.into_iter().map(|entry| (entry.key, entry.vector)));
+        }
+
+        keys.iter()
+            .map(|key| {
+                cached.get(key)
+                    .map(|vector| Embedding::from(vector.clone()))
+                    .ok_or(EmbeddingCacheError::MissingVectors {
+                        expected: keys.len(),
+                        received: cached.len(),
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Prepares the cache for the configured embedding model
+///
+/// Creates the cache index and removes every vector computed by a different
+/// model, so changing the configured embedding model invalidates the cache.
+///
+/// # Parameters
+/// * `model_id` - Identifier of the configured embedding model
+///
+/// # Returns
+/// * `Result<(), String>` - Success or an error message
+pub async fn prepare_embedding_cache(model_id: &str) -> Result<(), String> {
+    let db = get_app_db().await?;
+    db.query(format!(
+        "DEFINE TABLE IF NOT EXISTS {table} SCHEMALESS;
+         DEFINE INDEX IF NOT EXISTS {table}_key ON {table} FIELDS key UNIQUE;
+         DELETE {table} WHERE model_id != $model_id;",
+        table = CACHE_TABLE
+    ))
+        .bind(("model_id", model_id.to_string()))
+        .await
+        .and_then(|response| response.check())
+        .map_err(|e| {
+            error!("Error preparing embedding cache: {}", e);
+            e.to_string()
+        })?;
+    Ok(())
+}
+
+/// Returns the statistics of the embedding cache
+///
+/// # Parameters
+/// * `model_id` - Identifier of the configured embedding model
+///
+/// # Returns
+/// * `Result<EmbeddingCacheStats, String>` - The cache statistics or an error message
+pub async fn cache_stats(model_id: &str) -> Result<EmbeddingCacheStats, String> {
+    #[derive(Deserialize)]
+    struct CountRow {
+        count: usize,
+    }
+
+    let db = get_app_db().await?;
+    let count: Option<CountRow> = db
+        .query(format!("SELECT count() AS count FROM {} GROUP ALL", CACHE_TABLE))
+        .await
+        .and_then(|mut response| response.take(0))
+        .map_err(|e| e.to_string())?;
+
+    Ok(EmbeddingCacheStats {
+        model_id: model_id.to_string(),
+        entries: count.map_or(0, |row| row.count),
+        hits: CACHE_HITS.load(Ordering::Relaxed),
+        misses: CACHE_MISSES.load(Ordering::Relaxed),
+    })
+}
+
+/// Returns the number of embeddings served from the cache and computed by the
+/// model since the server started
+pub fn hit_counts() -> (u64, u64) {
+    (CACHE_HITS.load(Ordering::Relaxed), CACHE_MISSES.load(Ordering::Relaxed))
+}
+
+/// Computes the cache key of an embedding input
+///
+/// The query and document variants are hashed separately because some models
+/// embed them differently.
+fn cache_key(model_id: &str, input: &EmbeddingInput) -> String {
+    let digest = Sha256::new()
+        .chain_update(model_id)
+        .chain_update([0])
+        .chain_update(format!("{:?}", input.variant))
+        .chain_update([0])
+        .chain_update(&input.text)
+        .finalize();
+    format!("{:x}", digest)
+}
+
+/// Loads the cached vectors of the given keys
+async fn load_cached(keys: &[String]) -> Result<HashMap<String, Vec<f32>>, String> {
+    let db = get_app_db().await?;
+    let rows: Vec<CachedVector> = db
+        .query(format!("SELECT * FROM {} WHERE key IN $keys", CACHE_TABLE))
+        .bind(("keys", keys.to_vec()))
+        .await
+        .and_then(|mut response| response.take(0))
+        .map_err(|e| e.to_string())?;
+
+    Ok(rows.into_iter().map(|row| (row.key, row.vector)).collect())
+}
+
+/// Stores new vectors in the cache, ignoring keys that are already present
+async fn store_cached(entries: &[CachedVector]) -> Result<(), String> {
+    let db = get_app_db().await?;
+    db.query(format!("INSERT IGNORE INTO {} $entries", CACHE_TABLE))
+        .bind(("entries", entries.to_vec()))
+        .await
+        .and_then(|response| response.check())
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,214 @@
+//! Retrieval Evaluation
+//!
+//! This module measures the quality of the retrieval pipeline against a golden
+//! set of questions. Each question lists the documents expected to answer it
+//! and, optionally, a reference answer. The questions are searched with
+//! `database_impl::query` and answered through the chat pipeline with the
+//! retrieved context attached as in the chat, each in a new conversation. The
+//! report gives recall@k and MRR of the expected documents, over the questions
+//! that list some, and the embedding similarity between the generated and the
+//! reference answers.
+//!
+//! Golden sets are YAML or JSONL files:
+//!
+//! ```yaml
+//! - question: How do I reset my password?
+//!   expected_sources: ["Account management"]
+//!   reference_answer: Use the "Forgot password" link on the login page.
+//! ```
+//!
+//! Reports are pretty-printed JSON with stable field order, so two reports can
+//! be diffed to spot regressions.
+
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::model::collection::CollectionConfig;
+use crate::model::document::{with_context, SearchMode, SearchOptions};
+use crate::server::database_impl;
+use crate::server::embedding::{embed_texts, embedding_model_id};
+use crate::server::llm::{answer_in_new_conversation, TokenUsage};
+use crate::server::ranking::cosine_similarity;
+
+/// A question of the golden set
+#[derive(Debug, Clone, Deserialize)]
+pub struct GoldenQuestion {
+    /// The question asked to the assistant
+    pub question: String,
+    /// Titles of the documents that contain the answer
+    #[serde(default)]
+    pub expected_sources: Vec<String>,
+    /// A correct answer to compare the generated one with
+    #[serde(default)]
+    pub reference_answer: Option<String>,
+}
+
+/// Settings of an evaluation run
+#[derive(Debug, Clone, Serialize)]
+pub struct EvaluationConfig {
+    /// Number of retrieved chunks the metrics are computed on
+    pub top_k: usize,
+    /// The retrieval mode
+    pub mode: SearchMode,
+    /// Whether the candidates are reranked by the language model
+    pub rerank: bool,
+    /// Chunking configuration of every collection of the evaluated knowledge base
+    pub collections: Vec<CollectionConfig>,
+    /// Identifier of the embedding model
+    pub embedding_model: String,
+    /// Whether answers were generated and compared with the references
+    pub answers: bool,
+}
+
+/// Results of a single golden question
+#[derive(Debug, Clone, Serialize)]
+pub struct QuestionReport {
+    pub question: String,
+    pub expected_sources: Vec<String>,
+    /// Titles of the retrieved chunks, best first
+    pub retrieved_sources: Vec<String>,
+    /// Fraction of the expected documents found in the top k results,
+    /// `None` when the question lists no expected documents
+    pub recall_at_k: Option<f32>,
+    /// Inverse of the rank of the first expected document, 0 when none was found,
+    /// `None` when the question lists no expected documents
+    pub reciprocal_rank: Option<f32>,
+    pub answer: Option<String>,
+    /// Cosine similarity between the generated and the reference answers
+    pub answer_similarity: Option<f32>,
+}
+
+/// Averages of the metrics over the golden set
+#[derive(Debug, Clone, Serialize)]
+pub struct EvaluationSummary {
+    pub questions: usize,
+    /// Mean recall@k over the questions with expected documents
+    pub recall_at_k: Option<f32>,
+    /// Mean reciprocal rank over the questions with expected documents
+    pub mrr: Option<f32>,
+    /// Mean answer similarity over the questions with a reference answer
+    pub answer_similarity: Option<f32>,
+}
+
+/// Complete evaluation report
+#[derive(Debug, Clone, Serialize)]
+pub struct EvaluationReport {
+    pub config: EvaluationConfig,
+    pub summary: EvaluationSummary,
+    pub questions: Vec<QuestionReport>,
+}
+
+/// Loads a golden set from a YAML (`.yaml`, `.yml`) or JSONL file
+///
+/// # Parameters
+/// * `path` - Path of the golden set
+///
+/// # Returns
+/// * `Result<Vec<GoldenQuestion>, String>` - The questions or an error message
+pub fn load_golden_set(path: &Path) -> Result<Vec<GoldenQuestion>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("yaml") | Some("yml") => serde_yaml::from_str(&content)
+            .map_err(|e| format!("Invalid golden set: {}", e)),
+        _ => content.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(number, line)| {
+                serde_json::from_str(line).map_err(|e| format!("Invalid question on line {}: {}", number + 1, e))
+            })
+            .collect(),
+    }
+}
+
+/// Runs the golden questions through retrieval and, optionally, answer generation
+///
+/// # Parameters
+/// * `questions` - The golden set
+/// * `options` - The retrieval settings under evaluation; `top_k` is the k of recall@k
+/// * `generate_answers` - Whether answers are generated and compared with the references
+///
+/// # Returns
+/// * `Result<EvaluationReport, String>` - The report or an error message
+pub async fn evaluate(
+    questions: &[GoldenQuestion],
+    options: &SearchOptions,
+    generate_answers: bool,
+) -> Result<EvaluationReport, String> {
+    let mut reports = Vec::with_capacity(questions.len());
+    for (index, golden) in questions.iter().enumerate() {
+        println!("[{}/{}] {}", index + 1, questions.len(), golden.question);
+        reports.push(evaluate_question(golden, options, generate_answers).await?);
+    }
+
+    let summary = EvaluationSummary {
+        questions: reports.len(),
+        recall_at_k: mean(reports.iter().filter_map(|report| report.recall_at_k)),
+        mrr: mean(reports.iter().filter_map(|report| report.reciprocal_rank)),
+        answer_similarity: mean(reports.iter().filter_map(|report| report.answer_similarity)),
+    };
+
+    Ok(EvaluationReport {
+        config: EvaluationConfig {
+            top_k: options.top_k,
+            mode: options.mode,
+            rerank: options.rerank,
+            collections: database_impl::list_collections().await?,
+            embedding_model: embedding_model_id().to_string(),
+            answers: generate_answers,
+        },
+        summary,
+        questions: reports,
+    })
+}
+
+/// Evaluates a single golden question
+async fn evaluate_question(
+    golden: &GoldenQuestion,
+    options: &SearchOptions,
+    generate_answers: bool,
+) -> Result<QuestionReport, String> {
+    let results = database_impl::query(&golden.question, options, &mut TokenUsage::default()).await?;
+    let retrieved_sources: Vec<String> = results.iter().map(|result| result.title.clone()).collect();
+
+    let (recall_at_k, reciprocal_rank) = if golden.expected_sources.is_empty() {
+        (None, None)
+    } else {
+        let found = golden.expected_sources.iter()
+            .filter(|expected| retrieved_sources.contains(expected))
+            .count();
+        let reciprocal_rank = retrieved_sources.iter()
+            .position(|title| golden.expected_sources.contains(title))
+            .map_or(0.0, |rank| 1.0 / (rank + 1) as f32);
+        (Some(found as f32 / golden.expected_sources.len() as f32), Some(reciprocal_rank))
+    };
+
+    let answer = if generate_answers {
+        Some(answer_in_new_conversation(&with_context(&golden.question, &results)).await?)
+    } else {
+        None
+    };
+    let answer_similarity = match (&answer, &golden.reference_answer) {
+        (Some(answer), Some(reference)) => {
+            let embeddings = embed_texts(vec![answer.clone(), reference.clone()]).await?;
+            Some(cosine_similarity(&embeddings[0], &embeddings[1]))
+        }
+        _ => None,
+    };
+
+    Ok(QuestionReport {
+        question: golden.question.clone(),
+        expected_sources: golden.expected_sources.clone(),
+        retrieved_sources,
+        recall_at_k,
+        reciprocal_rank,
+        answer,
+        answer_similarity,
+    })
+}
+
+/// Averages values, `None` when there are none
+fn mean(values: impl Iterator<Item = f32>) -> Option<f32> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    (count > 0).then(|| sum / count as f32)
+}
@@ -0,0 +1,124 @@
+//! Generation Metrics
+//!
+//! This module measures every response streamed by the language model: time to
+//! first token, total time, prompt tokens and completion tokens. The latest
+//! measurements are kept in memory and summarized with percentiles, both for
+//! the running server and for the benchmark command.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Instant;
+use crate::model::metrics::{GenerationMetrics, GenerationSummary, Percentiles};
+use crate::server::prometheus;
+
+/// Number of recent responses whose measurements are kept
+const MAX_RECORDED_RESPONSES: usize = 1000;
+
+/// Measurements of the most recent responses, oldest first
+static RECENT_METRICS: Mutex<VecDeque<GenerationMetrics>> = Mutex::new(VecDeque::new());
+
+/// Measures a response while its tokens are streamed
+///
+/// The response counts as in progress until the timer is dropped, so responses
+/// whose stream is cancelled before `finish` are not left in progress.
+pub struct GenerationTimer {
+    start: Instant,
+    first_token: Option<Instant>,
+    prompt_tokens: usize,
+    completion_tokens: usize,
+}
+
+impl GenerationTimer {
+    /// Starts measuring a response to a prompt of `prompt_tokens` tokens
+    pub fn start(prompt_tokens: usize) -> Self {
+        prometheus::generation_started();
+        Self {
+            start: Instant::now(),
+            first_token: None,
+            prompt_tokens,
+            completion_tokens: 0,
+        }
+    }
+
+    /// Records a generated token
+    pub fn on_token(&mut self) {
+        self.first_token.get_or_insert_with(Instant::now);
+        self.completion_tokens += 1;
+    }
+
+    /// Stops measuring and records the measurements of the response
+    pub fn finish(self) -> GenerationMetrics {
+        let end = Instant::now();
+        let metrics = GenerationMetrics {
+            time_to_first_token_ms: milliseconds(self.first_token.unwrap_or(end) - self.start),
+            total_ms: milliseconds(end - self.start),
+            prompt_tokens: self.prompt_tokens,
+            completion_tokens: self.completion_tokens,
+        };
+        record(metrics.clone());
+        prometheus::generation_finished(&metrics);
+        metrics
+    }
+}
+
+impl Drop for GenerationTimer {
+    fn drop(&mut self) {
+        prometheus::generation_ended();
+    }
+}
+
+/// Returns the summary of the most recent responses
+pub fn recent_summary() -> GenerationSummary {
+    let recent = RECENT_METRICS.lock().map(|recent| recent.iter().cloned().collect::<Vec<_>>());
+    summarize(&recent.unwrap_or_default())
+}
+
+/// Summarizes measurements with their median and 95th percentile
+///
+/// # Parameters
+/// * `metrics` - The measurements of the responses
+///
+/// # Returns
+/// * `GenerationSummary` - The percentiles of every measurement
+pub fn summarize(metrics: &[GenerationMetrics]) -> GenerationSummary {
+    let percentiles = |value: fn(&GenerationMetrics) -> f64| {
+        let mut values: Vec<f64> = metrics.iter().map(value).collect();
+        values.sort_by(f64::total_cmp);
+        Percentiles {
+            p50: percentile(&values, 0.50),
+            p95: percentile(&values, 0.95),
+        }
+    };
+
+    GenerationSummary {
+        responses: metrics.len(),
+        time_to_first_token_ms: percentiles(|m| m.time_to_first_token_ms),
+        tokens_per_second: percentiles(|m| m.tokens_per_second()),
+        prompt_tokens: percentiles(|m| m.prompt_tokens as f64),
+        completion_tokens: percentiles(|m| m.completion_tokens as f64),
+    }
+}
+
+/// Stores the measurements of a response, dropping the oldest beyond the limit
+fn record(metrics: GenerationMetrics) {
+    if let Ok(mut recent) = RECENT_METRICS.lock() {
+        if recent.len() == MAX_RECORDED_RESPONSES {
+            recent.pop_front();
+        }
+        recent.push_back(metrics);
+    }
+}
+
+/// Returns the nearest-rank percentile of sorted values, 0 when there are none
+fn percentile(sorted: &[f64], fraction: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (fraction * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Converts a duration into fractional milliseconds
+fn milliseconds(duration: std::time::Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,121 @@
+//! Answer Grounding
+//!
+//! This module verifies an answer against the sources it was generated from.
+//! The answer is split into sentences, each treated as a claim. A claim whose
+//! embedding is close enough to a source chunk is supported; the others are
+//! either reported as unsupported or, when the judge is enabled, submitted to
+//! the language model, which decides whether the sources back them.
+//!
+//! Code blocks and very short sentences, which rarely state facts, are skipped.
+
+use std::ops::Range;
+use crate::model::document::SimpleDocumentResult;
+use crate::model::grounding::ClaimCheck;
+use crate::server::chunking;
+use crate::server::embedding::embed_texts;
+use crate::server::llm::{complete_constrained, TokenUsage};
+use crate::server::ranking::cosine_similarity;
+use tracing::error;
+
+/// Similarity with a source chunk above which a sentence is supported without judging it
+const SUPPORTED_SIMILARITY: f32 = 0.8;
+
+/// Minimum number of words of a sentence to be checked
+const MIN_CLAIM_WORDS: usize = 4;
+
+/// Instructions given to the language model when judging a claim
+const JUDGE_SYSTEM_PROMPT: &str = "You check whether a claim is supported by source passages. \
+    Answer yes if the passages state or directly imply the claim, and no otherwise.";
+
+/// Checks every claim of an answer against the sources
+///
+/// # Parameters
+/// * `answer` - The generated answer
+/// * `sources` - The chunks injected as context when generating the answer
+/// * `use_judge` - Whether claims below the similarity threshold are judged by the language model
+/// * `usage` - Accumulates the tokens of the judge prompts
+///
+/// # Returns
+/// * `Result<Vec<ClaimCheck>, String>` - One check per claim, in answer order, or an error message
+pub async fn check_grounding(
+    answer: &str,
+    sources: &[SimpleDocumentResult],
+    use_judge: bool,
+    usage: &mut TokenUsage,
+) -> Result<Vec<ClaimCheck>, String> {
+    let spans = claim_spans(answer);
+    if spans.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut texts: Vec<String> = spans.iter().map(|span| answer[span.clone()].to_string()).collect();
+    texts.extend(sources.iter().map(|source| source.body.clone()));
+    let embeddings = embed_texts(texts).await?;
+    let (claim_embeddings, source_embeddings) = embeddings.split_at(spans.len());
+
+    let mut checks = Vec::with_capacity(spans.len());
+    for (span, claim_embedding) in spans.into_iter().zip(claim_embeddings) {
+        let sentence = answer[span.clone()].to_string();
+        let best = source_embeddings.iter()
+            .zip(sources)
+            .map(|(source_embedding, source)| (cosine_similarity(claim_embedding, source_embedding), source))
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+        let similarity = best.map_or(0.0, |(similarity, _)| similarity);
+
+        let judge = use_judge && !sources.is_empty() && similarity < SUPPORTED_SIMILARITY;
+        let supported = if judge {
+            judge_claim(&sentence, sources, usage).await.unwrap_or_else(|e| {
+                error!("Error judging claim: {}", e);
+                false
+            })
+        } else {
+            similarity >= SUPPORTED_SIMILARITY
+        };
+
+        checks.push(ClaimCheck {
+            start: span.start,
+            end: span.end,
+            sentence,
+            supported,
+            similarity,
+            best_source: best.map(|(_, source)| source.title.clone()),
+            judged: judge,
+        });
+    }
+    Ok(checks)
+}
+
+/// Returns the byte ranges of the sentences of an answer worth checking
+///
+/// Lines are split separately so list items are checked on their own.
+fn claim_spans(answer: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut in_code_block = false;
+    let mut offset = 0;
+
+    for line in answer.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        } else if !in_code_block {
+            spans.extend(chunking::sentences(line)
+                .into_iter()
+                .filter(|sentence| sentence.text.split_whitespace().count() >= MIN_CLAIM_WORDS)
+                .map(|sentence| offset + sentence.start..offset + sentence.end));
+        }
+        offset += line.len();
+    }
+    spans
+}
+
+/// Asks the language model whether the sources support a claim
+async fn judge_claim(claim: &str, sources: &[SimpleDocumentResult], usage: &mut TokenUsage) -> Result<bool, String> {
+    let passages = sources.iter()
+        .map(|source| format!("Title: {}\n{}", source.title, source.body))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let prompt = format!("Sources:\n{}\n\nClaim: {}\n\nIs the claim supported?", passages, claim);
+
+    let (answer, judge_usage) = complete_constrained(JUDGE_SYSTEM_PROMPT, &prompt, "(yes|no)").await?;
+    *usage += judge_usage;
+    Ok(answer == "yes")
+}
@@ -0,0 +1,127 @@
+//! HTTP Server
+//!
+//! This module launches the web server. The Dioxus application and its server
+//! functions are served by an Axum router, which also exposes the plain HTTP
+//! endpoints of the server:
+//!
+//! - `/metrics` - Operational metrics in the Prometheus text format
+//! - `/healthz` - Liveness, answers as long as the server is running
+//! - `/readyz` - Readiness, answers 503 until the models and the knowledge base are loaded
+//!
+//! Every request is handled inside a tracing span with its own identifier, so
+//! the logs of a server function call can be told apart from concurrent ones,
+//! and the requests of every server function are counted for `/metrics`.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use axum::routing::get;
+use axum::Router;
+use dioxus::prelude::*;
+use tower_http::trace::TraceLayer;
+use crate::server::{logging, prometheus, startup, status};
+use tracing::info;
+
+/// Name of every server function, by the path it is served at
+static SERVER_FUNCTIONS: OnceLock<HashMap<&'static str, &'static str>> = OnceLock::new();
+
+/// Serves the application until the server is stopped
+///
+/// # Parameters
+/// * `app` - The root component of the application
+pub fn launch(app: fn() -> Element) {
+    let runtime = tokio::runtime::Runtime::new().expect("Error starting runtime");
+    runtime.block_on(async move {
+        // Load the models and build the knowledge base before the first request arrives
+        startup::start();
+
+        let address = dioxus::cli_config::fullstack_address_or_localhost();
+        let config = ServeConfig::new().expect("Error loading the application index");
+
+        let router = Router::new()
+            .route("/metrics", get(metrics))
+            .route("/healthz", get(healthz))
+            .route("/readyz", get(readyz))
+            .serve_dioxus_application(config, app)
+            .layer(middleware::from_fn(count_server_function_requests))
+            .layer(TraceLayer::new_for_http().make_span_with(request_span));
+
+        info!("Listening on http://{}", address);
+        let listener = tokio::net::TcpListener::bind(address)
+            .await
+            .expect("Error binding the server address");
+        axum::serve(listener, router.into_make_service())
+            .await
+            .expect("Error running the server");
+    });
+}
+
+/// Serves the metrics in the Prometheus text exposition format
+async fn metrics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        prometheus::render().await,
+    )
+}
+
+/// Answers liveness probes
+async fn healthz() -> impl IntoResponse {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+/// Answers readiness probes
+///
+/// The status code is 200 once every component is ready and 503 otherwise, so
+/// load balancers only route traffic to servers that can answer. The probe is
+/// not authenticated, so the state of the components, which may include error
+/// messages, is only reported by the `system_status` server function.
+async fn readyz() -> impl IntoResponse {
+    if status::system_status().await.ready {
+        (StatusCode::OK, Json(serde_json::json!({ "status": "ready" })))
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({ "status": "not ready" })))
+    }
+}
+
+/// Counts the requests received by every server function
+///
+/// Requests to other paths are passed through without being counted.
+async fn count_server_function_requests(request: Request<Body>, next: Next) -> Response {
+    if let Some(function) = server_function_name(request.uri().path()) {
+        prometheus::count_request(function);
+    }
+    next.run(request).await
+}
+
+/// Returns the name of the server function served at a path, if any
+///
+/// Server functions are served at their name followed by a numeric hash of
+/// their location, so the name is the last path segment without its trailing
+/// digits.
+fn server_function_name(path: &str) -> Option<&'static str> {
+    SERVER_FUNCTIONS
+        .get_or_init(|| {
+            server_fn::axum::server_fn_paths()
+                .map(|(path, _)| {
+                    let endpoint = path.rsplit('/').next().unwrap_or(path);
+                    (path, endpoint.trim_end_matches(|c: char| c.is_ascii_digit()))
+                })
+                .collect()
+        })
+        .get(path)
+        .copied()
+}
+
+/// Creates the span of an HTTP request
+fn request_span<B>(request: &Request<B>) -> tracing::Span {
+    tracing::info_span!(
+        "request",
+        id = logging::next_request_id(),
+        method = %request.method(),
+        path = %request.uri().path(),
+    )
+}
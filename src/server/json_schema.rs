@@ -0,0 +1,205 @@
+//! Structured JSON Output
+//!
+//! This module generates JSON documents that follow a JSON Schema. The schema is
+//! compiled into a regular expression describing every valid document, and the
+//! language model output is constrained to that expression, so the answer always
+//! parses and matches the schema.
+//!
+//! The supported subset of JSON Schema covers data extraction templates:
+//! - `type` (a single type or a list of types) with `string`, `integer`, `number`,
+//!   `boolean`, `null`, `object` and `array`
+//! - `enum` and `const`
+//! - `anyOf` and `oneOf`
+//! - `properties` and `required` of objects; properties are generated in
+//!   alphabetical order and those not listed in `required` may be left out
+//! - `items`, `minItems` and `maxItems` of arrays, bounded by `MAX_ITEMS`
+//! - `maxLength` of strings, bounded by `MAX_STRING_LENGTH`
+//!
+//! References (`$ref`) are not supported.
+
+use serde_json::Value;
+use crate::server::llm::{complete_constrained, TokenUsage};
+
+/// Maximum nesting depth of the schema
+const MAX_DEPTH: usize = 8;
+
+/// Maximum length of strings without a `maxLength`
+const DEFAULT_MAX_STRING_LENGTH: u64 = 200;
+
+/// Upper bound of `maxLength`, which keeps the compiled expression small
+const MAX_STRING_LENGTH: u64 = 2000;
+
+/// Maximum number of items of arrays without a `maxItems`
+const DEFAULT_MAX_ITEMS: u64 = 8;
+
+/// Upper bound of `minItems` and `maxItems`, which keeps the compiled expression small
+const MAX_ITEMS: u64 = 64;
+
+/// Regular expression matching a JSON integer
+const INTEGER_PATTERN: &str = r"-?(0|[1-9][0-9]{0,15})";
+
+/// Regular expression matching a JSON number
+const NUMBER_PATTERN: &str = r"-?(0|[1-9][0-9]{0,15})(\.[0-9]{1,10})?";
+
+/// Generates a JSON document matching a schema from a prompt
+///
+/// # Parameters
+/// * `schema` - The JSON Schema of the document, as JSON text
+/// * `prompt` - Describes the data to put in the document
+/// * `usage` - Accumulates the tokens of the generation
+///
+/// # Returns
+/// * `Result<Value, String>` - The generated document or an error message
+pub async fn generate_json(schema: &str, prompt: &str, usage: &mut TokenUsage) -> Result<Value, String> {
+    let schema: Value = serde_json::from_str(schema).map_err(|e| format!("Invalid JSON Schema: {}", e))?;
+    let pattern = schema_pattern(&schema, 0)?;
+
+    let system_prompt = format!(
+        "You fill in JSON documents from the user's text. Answer with a single JSON document \
+         following this JSON Schema, using only information from the text:\n{}",
+        schema
+    );
+    let (output, generation_usage) = complete_constrained(&system_prompt, prompt, &pattern).await?;
+    *usage += generation_usage;
+    serde_json::from_str(&output).map_err(|e| format!("Invalid JSON output {}: {}", output, e))
+}
+
+/// Compiles a schema into a regular expression matching its valid documents
+fn schema_pattern(schema: &Value, depth: usize) -> Result<String, String> {
+    if depth > MAX_DEPTH {
+        return Err("The schema is nested too deeply".to_string());
+    }
+    if schema.get("$ref").is_some() {
+        return Err("Schema references ($ref) are not supported".to_string());
+    }
+
+    if let Some(value) = schema.get("const") {
+        return Ok(literal_pattern(value));
+    }
+    if let Some(values) = schema.get("enum") {
+        let values = values.as_array().ok_or("enum must be an array")?;
+        return Ok(alternatives(values.iter().map(literal_pattern).collect()));
+    }
+    if let Some(options) = schema.get("anyOf").or_else(|| schema.get("oneOf")) {
+        let options = options.as_array().ok_or("anyOf and oneOf must be arrays")?;
+        let patterns = options.iter()
+            .map(|option| schema_pattern(option, depth + 1))
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(alternatives(patterns));
+    }
+
+    match schema.get("type") {
+        Some(Value::String(name)) => type_pattern(name, schema, depth),
+        Some(Value::Array(names)) => {
+            let patterns = names.iter()
+                .map(|name| type_pattern(name.as_str().ok_or("type names must be strings")?, schema, depth))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(alternatives(patterns))
+        }
+        // Objects are often declared with their properties only
+        None if schema.get("properties").is_some() => type_pattern("object", schema, depth),
+        _ => Err(format!("Unsupported schema {}", schema)),
+    }
+}
+
+/// Compiles a schema of a single type
+fn type_pattern(name: &str, schema: &Value, depth: usize) -> Result<String, String> {
+    match name {
+        "string" => {
+            let max_length = schema["maxLength"]
+                .as_u64()
+                .unwrap_or(DEFAULT_MAX_STRING_LENGTH)
+                .min(MAX_STRING_LENGTH);
+            Ok(format!(r#""([^"\\\n]|\\["\\/nt]){{0,{}}}""#, max_length))
+        }
+        "integer" => Ok(INTEGER_PATTERN.to_string()),
+        "number" => Ok(NUMBER_PATTERN.to_string()),
+        "boolean" => Ok("(true|false)".to_string()),
+        "null" => Ok("null".to_string()),
+        "object" => {
+            let properties = schema["properties"].as_object().cloned().unwrap_or_default();
+            let required: Vec<&str> = schema["required"]
+                .as_array()
+                .map(|names| names.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            let fields = properties.iter()
+                .map(|(key, property)| {
+                    let name = serde_json::to_string(key).map_err(|e| e.to_string())?;
+                    let pattern = format!("{}: {}", escape_regex(&name), schema_pattern(property, depth + 1)?);
+                    Ok((pattern, required.contains(&key.as_str())))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            Ok(format!(r"\{{{}\}}", fields_pattern(&fields)))
+        }
+        "array" => {
+            let item = match schema.get("items") {
+                Some(items) => schema_pattern(items, depth + 1)?,
+                None => return Err("Arrays must declare their items".to_string()),
+            };
+            let min_items = schema["minItems"].as_u64().unwrap_or(0).min(MAX_ITEMS);
+            let max_items = schema["maxItems"]
+                .as_u64()
+                .unwrap_or(DEFAULT_MAX_ITEMS)
+                .min(MAX_ITEMS)
+                .max(min_items.max(1));
+            let rest = format!("(, {}){{{},{}}}", item, min_items.saturating_sub(1), max_items - 1);
+            if min_items == 0 {
+                Ok(format!(r"\[({}{})?\]", item, rest))
+            } else {
+                Ok(format!(r"\[{}{}\]", item, rest))
+            }
+        }
+        _ => Err(format!("Unsupported type {}", name)),
+    }
+}
+
+/// Compiles the fields of an object, in order, into the pattern of its members
+///
+/// Each field is given as its `"key": value` pattern and whether it is required.
+/// Optional fields may be left out, and members are separated by commas
+/// whichever fields are present.
+fn fields_pattern(fields: &[(String, bool)]) -> String {
+    let Some(((pattern, required), rest)) = fields.split_first() else {
+        return String::new();
+    };
+    // Once a member has been written, every following one is preceded by a comma
+    let following: String = rest.iter()
+        .map(|(pattern, required)| {
+            if *required {
+                format!(", {}", pattern)
+            } else {
+                format!("(, {})?", pattern)
+            }
+        })
+        .collect();
+
+    if *required {
+        format!("{}{}", pattern, following)
+    } else if rest.is_empty() {
+        format!("({})?", pattern)
+    } else {
+        format!("({}{}|{})", pattern, following, fields_pattern(rest))
+    }
+}
+
+/// Regular expression matching exactly the JSON text of a value
+fn literal_pattern(value: &Value) -> String {
+    escape_regex(&value.to_string())
+}
+
+/// Joins patterns into a group matching any of them
+fn alternatives(patterns: Vec<String>) -> String {
+    format!("({})", patterns.join("|"))
+}
+
+/// Escapes the characters of a text that have a meaning in regular expressions
+fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -3,50 +3,108 @@
 //! This module provides functionality for interacting with the large language model (LLM).
 //! It manages a singleton instance of the Llama chat model and provides methods for
 //! generating responses, streaming text output, and resetting conversation state.
+//! Every user has their own chat session, so conversations are never shared.
+//! The model and the sampling parameters come from the server configuration, and
+//! new conversations start with the persona of the runtime settings.
 
+use std::collections::HashMap;
+use std::ops::AddAssign;
 use std::path::PathBuf;
 use tokio::sync::OnceCell;
 use std::sync::Mutex;
-use kalosm::language::{Chat, ChatModelExt, IntoChatMessage, Llama, ToChatMessage};
+use std::sync::atomic::{AtomicU64, Ordering};
+use kalosm::language::{Chat, ChatModelExt, IntoChatMessage, Llama, LlamaSource, ToChatMessage};
+use crate::model::settings::ModelSource;
+use crate::model::status::{ComponentState, LoadStage};
+use crate::server::{config, logging, settings};
+use crate::server::startup;
+use crate::server::status::{self, Component};
+use tracing::{debug, info};
 
-/// Global singleton for the chat session
+/// Global singleton for the chat sessions, by username
 /// Uses OnceCell and Mutex for thread-safe access and initialization
-pub static CHAT_SESSION: OnceCell<Mutex<Chat<Llama>>> = OnceCell::const_new();
+static CHAT_SESSIONS: OnceCell<Mutex<HashMap<String, UserSession>>> = OnceCell::const_new();
 
 /// Global singleton for the language model
 /// Stores the base LLM that can generate new chat sessions when needed
 pub static MODEL: OnceCell<Mutex<Llama>> = OnceCell::const_new();
 
-/// Initializes the language model and creates a chat session
+/// Last conversation identifier handed out, incremented for every new conversation
+/// Attached to the logs of every response so they can be grouped by conversation
+static CONVERSATION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Tokens read and generated by the language model for one-off prompts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    /// Tokens of the system prompts and prompts
+    pub prompt_tokens: u64,
+    /// Tokens of the answers
+    pub completion_tokens: u64,
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+    }
+}
+
+/// The chat session of a user
+struct UserSession {
+    chat: Chat<Llama>,
+    /// Identifier of the conversation, unique across users
+    conversation: u64,
+}
+
+impl UserSession {
+    /// Starts a new conversation with the given persona
+    fn new(llama: &Llama, persona: &str) -> Self {
+        Self {
+            chat: new_chat(llama, persona),
+            conversation: CONVERSATION_ID.fetch_add(1, Ordering::Relaxed) + 1,
+        }
+    }
+}
+
+/// Initializes the language model and the chat sessions
 ///
 /// This function:
 /// 1. Checks if the model is already initialized
-/// 2. If not, creates a new Llama model instance with the Qwen 2.5 7B model
-/// 3. Creates a chat session from the model
-/// 4. Stores both in their respective global singletons
+/// 2. If not, creates a new Llama model instance with the configured model
+/// 3. Stores it in its global singleton, next to an empty set of chat sessions
 ///
+/// Chat sessions are created the first time each user sends a message.
 /// Returns Ok(()) on success or an error message on failure
+#[tracing::instrument]
 pub async fn init_chat_model() -> Result<(), String> {
-    use kalosm::language::{LlamaSource, ChatModelExt, FileSource};
-
-    if CHAT_SESSION.get().is_none() {
-        println!("Initializing chat model...");
+    if MODEL.get().is_none() {
+        let source = config::get().model.source;
+        info!(model = source.id(), "Initializing chat model...");
+        status::set_state(Component::Model, ComponentState::Loading);
 
         let llama = Llama::builder()
-            .with_source(
-                LlamaSource::qwen_2_5_7b_instruct()
-            )
-            .build()
+            .with_source(llama_source(source))
+            .build_with_loading_handler(|progress| {
+                let (stage, fraction) = startup::load_stage(&progress);
+                startup::update(|init| {
+                    init.model_stage = stage;
+                    init.model_progress = fraction;
+                });
+            })
             .await
-            .map_err(|e| e.to_string())?;
+            .map_err(|e| e.to_string());
+        let llama = status::track(Component::Model, llama)?;
 
 
-        println!("Model loaded successfully");
-        let chat = llama.chat();
+        info!("Model loaded successfully");
+        status::set_state(Component::ChatSession, ComponentState::Loading);
+        CHAT_SESSIONS.set(Mutex::new(HashMap::new()))
+            .map_err(|_| "Couldn't set chat sessions".to_string())?;
         MODEL.set(Mutex::new(llama))
             .map_err(|_| "Couldn't set model".to_string())?;
-        CHAT_SESSION.set(Mutex::new(chat))
-            .map_err(|_| "Couldn't set chat session".to_string())?;
+        status::set_state(Component::Model, ComponentState::Ready);
+        status::set_state(Component::ChatSession, ComponentState::Ready);
+        startup::update(|init| init.model_stage = LoadStage::Ready);
     }
     Ok(())
 }
@@ -54,57 +112,244 @@ pub async fn init_chat_model() -> Result<(), String> {
 /// Creates a stream for generating text responses from the language model
 ///
 /// This function:
-/// 1. Retrieves the chat session from the global singleton
+/// 1. Retrieves the chat session of the user, starting one if needed
 /// 2. Sends the user's prompt to the model
-/// 3. Configures generation parameters (temperature, top_p, etc.)
+/// 3. Configures generation parameters (temperature, top_p, etc.) from the configuration
 /// 4. Returns a stream that will yield text chunks as they are generated
 ///
 /// # Parameters
+/// * `username` - The user the conversation belongs to
 /// * `prompt` - The user's input message
 ///
 /// # Returns
-/// * `Result<impl Stream<Item=String>, &'static str>` - A text generation stream or an error
-pub fn try_get_stream(prompt: &str) -> Result<impl futures::Stream<Item=String>, &'static str> {
-    use kalosm::language::{GenerationParameters};
+/// * `Result<(u64, impl Stream<Item=String>), String>` - The conversation identifier and
+///   a text generation stream, or an error
+#[tracing::instrument(skip_all, fields(username = %username))]
+pub async fn try_get_stream(username: &str, prompt: &str) -> Result<(u64, impl futures::Stream<Item=String>), String> {
+    let persona = settings::current().await?.persona;
+    let llama = get_model()?;
+    let mut sessions = CHAT_SESSIONS
+        .get()
+        .ok_or("Model couldn't be initialized.")?
+        .lock()
+        .map_err(|_| "Error locking chat sessions")?;
+
+    let session = sessions
+        .entry(username.to_string())
+        .or_insert_with(|| UserSession::new(&llama, &persona));
+    debug!(conversation = session.conversation, prompt = %logging::content(prompt), "Starting response");
+
+    let stream = (session.chat)(&prompt.into_chat_message()).with_sampler(chat_sampler());
+    Ok((session.conversation, stream))
+}
+
+/// Returns the identifier of the current conversation of a user
+///
+/// # Parameters
+/// * `username` - The user the conversation belongs to
+///
+/// # Returns
+/// * `Option<u64>` - The conversation, `None` before the user's first message
+pub fn current_conversation(username: &str) -> Option<u64> {
+    CHAT_SESSIONS
+        .get()?
+        .lock()
+        .ok()?
+        .get(username)
+        .map(|session| session.conversation)
+}
+
+/// Generates a complete chat answer to a message in a new conversation
+///
+/// The message goes through the same pipeline as the chat: a session with the
+/// current persona, sampled with the configured parameters. The session is
+/// discarded afterwards, so no conversation is read or modified.
+///
+/// # Parameters
+/// * `prompt` - The message, with any context attached as in the chat
+///
+/// # Returns
+/// * `Result<String, String>` - The generated answer or an error message
+#[tracing::instrument(skip_all)]
+pub async fn answer_in_new_conversation(prompt: &str) -> Result<String, String> {
+    let persona = settings::current().await?.persona;
+    let mut chat = new_chat(&get_model()?, &persona);
 
-    let chat_session = CHAT_SESSION
+    chat(&prompt.into_chat_message())
+        .with_sampler(chat_sampler())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Sampling parameters of the chat responses, from the configuration
+fn chat_sampler() -> kalosm::language::GenerationParameters {
+    use kalosm::language::GenerationParameters;
+
+    let sampling = config::get().sampling;
+    GenerationParameters::default()
+        .with_temperature(sampling.temperature)     // Controls randomness (higher = more random)
+        .with_top_p(sampling.top_p)                 // Nucleus sampling parameter (higher = more diverse)
+        .with_max_length(sampling.max_length)       // Maximum response length in tokens
+}
+
+/// Gets a handle to the base language model
+///
+/// The model is cheap to clone, so the lock is only held while copying the handle
+/// and is never kept across an await point.
+fn get_model() -> Result<Llama, String> {
+    MODEL
         .get()
-        .ok_or("Model couldn't be initialized.")?;
+        .ok_or("Model not initialized")?
+        .lock()
+        .map(|llama| llama.clone())
+        .map_err(|_| "Error locking model".to_string())
+}
 
-    let mut guard = chat_session
-        .try_lock()
-        .map_err(|_| "Couldn't get model lock")?;
+/// Counts the tokens of a text with the tokenizer of the language model
+///
+/// The chat template and the conversation history added around a message are
+/// not included, so this is the size of the message itself.
+///
+/// # Parameters
+/// * `text` - The text to tokenize
+///
+/// # Returns
+/// * `Result<usize, String>` - The number of tokens or an error message
+pub fn count_tokens(text: &str) -> Result<usize, String> {
+    get_model()?
+        .tokenizer()
+        .encode(text, false)
+        .map(|encoding| encoding.len())
+        .map_err(|e| e.to_string())
+}
+
+/// Generates a complete answer to a one-off prompt
+///
+/// The prompt is answered in a fresh chat session created from the base model,
+/// so the history of the user's conversation is neither read nor modified.
+/// Low temperature sampling is used because callers parse the answer.
+///
+/// # Parameters
+/// * `system_prompt` - Instructions describing the task
+/// * `prompt` - The input for the task
+/// * `max_length` - Maximum answer length in tokens
+///
+/// # Returns
+/// * `Result<(String, TokenUsage), String>` - The generated text with the tokens read and generated, or an error message
+#[tracing::instrument(skip_all, fields(max_length))]
+pub async fn complete_prompt(system_prompt: &str, prompt: &str, max_length: u32) -> Result<(String, TokenUsage), String> {
+    use kalosm::language::GenerationParameters;
+
+    let llama = get_model()?;
+    let mut chat = llama.chat().with_system_prompt(system_prompt);
+
+    let text = chat(&prompt.into_chat_message())
+        .with_sampler(GenerationParameters::default()
+            .with_temperature(0.1)
+            .with_max_length(max_length)
+        )
+        .await
+        .map_err(|e| e.to_string())?;
 
-    Ok(guard(&prompt.into_chat_message()).with_sampler(GenerationParameters::default()
-        .with_temperature(0.7)     // Controls randomness (higher = more random)
-        .with_top_p(0.9)           // Nucleus sampling parameter (higher = more diverse)
-        .with_max_length(600)      // Maximum response length in tokens
-    ))
+    let usage = TokenUsage {
+        prompt_tokens: count_prompt_tokens(system_prompt, prompt),
+        completion_tokens: count_tokens(&text).unwrap_or_default() as u64,
+    };
+    Ok((text, usage))
 }
 
-/// Resets the chat session to start a new conversation
+/// Generates an answer to a one-off prompt constrained to match a regular expression
+///
+/// Like `complete_prompt`, the prompt is answered in a fresh chat session. Tokens
+/// that would make the answer diverge from `pattern` are never sampled, so the
+/// answer always has the expected shape.
+///
+/// # Parameters
+/// * `system_prompt` - Instructions describing the task
+/// * `prompt` - The input for the task
+/// * `pattern` - Regular expression the whole answer must match
+///
+/// # Returns
+/// * `Result<(String, TokenUsage), String>` - The generated text with the tokens read and generated, or an error message
+#[tracing::instrument(skip_all)]
+pub async fn complete_constrained(system_prompt: &str, prompt: &str, pattern: &str) -> Result<(String, TokenUsage), String> {
+    use futures::StreamExt;
+    use kalosm::language::{GenerationParameters, RegexParser};
+
+    let parser = RegexParser::new(pattern).map_err(|e| e.to_string())?;
+    let llama = get_model()?;
+    let mut chat = llama.chat().with_system_prompt(system_prompt);
+
+    let mut response = chat(&prompt.into_chat_message())
+        .with_constraints(parser)
+        .with_sampler(GenerationParameters::default().with_temperature(0.1));
+
+    let mut text = String::new();
+    let mut completion_tokens = 0;
+    while let Some(token) = response.next().await {
+        text.push_str(&token);
+        completion_tokens += 1;
+    }
+
+    let usage = TokenUsage {
+        prompt_tokens: count_prompt_tokens(system_prompt, prompt),
+        completion_tokens,
+    };
+    Ok((text, usage))
+}
+
+/// Counts the tokens of the system prompt and prompt of a one-off completion
+fn count_prompt_tokens(system_prompt: &str, prompt: &str) -> u64 {
+    [system_prompt, prompt].iter()
+        .map(|text| count_tokens(text).unwrap_or_default() as u64)
+        .sum()
+}
+
+/// Resets the chat session of a user to start a new conversation
 ///
 /// This function:
 /// 1. Retrieves the base language model
-/// 2. Creates a fresh chat session
-/// 3. Replaces the existing chat session in the global singleton
+/// 2. Creates a fresh chat session with the current persona
+/// 3. Replaces the user's chat session, leaving the other users' sessions untouched
 ///
-/// This effectively clears the conversation history and starts with a clean state.
+/// This effectively clears the user's conversation history and starts with a clean state.
+///
+/// # Parameters
+/// * `username` - The user whose conversation is reset
 ///
 /// # Returns
 /// * `Result<(), String>` - Success or an error message
-pub async fn reset_chat() -> Result<(), String> {
-    let llama = MODEL
+pub async fn reset_chat(username: &str) -> Result<(), String> {
+    let persona = settings::current().await?.persona;
+    let session = UserSession::new(&get_model()?, &persona);
+    let conversation = session.conversation;
+    CHAT_SESSIONS
         .get()
-        .ok_or("Model not initialized")?
+        .ok_or("Session not initialized")?
         .lock()
-        .map_err(|_| "Error locking model")?;
-    let new_chat = llama.chat();
-    let session_mutex = CHAT_SESSION
-        .get()
-        .ok_or("Session not initialized")?;
-    *session_mutex
-        .lock()
-        .map_err(|_| "Error locking session")? = new_chat;
+        .map_err(|_| "Error locking chat sessions")?
+        .insert(username.to_string(), session);
+
+    info!(username, conversation, "Chat session reset");
     Ok(())
 }
+
+/// Creates a chat session, with the persona as system prompt unless it is empty
+fn new_chat(llama: &Llama, persona: &str) -> Chat<Llama> {
+    let chat = llama.chat();
+    if persona.is_empty() {
+        chat
+    } else {
+        chat.with_system_prompt(persona)
+    }
+}
+
+/// Returns the source the weights of a supported model are downloaded from
+fn llama_source(source: ModelSource) -> LlamaSource {
+    match source {
+        ModelSource::Qwen25_7bInstruct => LlamaSource::qwen_2_5_7b_instruct(),
+        ModelSource::Qwen25_3bInstruct => LlamaSource::qwen_2_5_3b_instruct(),
+        ModelSource::Llama31_8bChat => LlamaSource::llama_3_1_8b_chat(),
+        ModelSource::Phi35MiniInstruct => LlamaSource::phi_3_5_mini_4k_instruct(),
+    }
+}
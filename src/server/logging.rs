@@ -0,0 +1,71 @@
+//! Structured Logging
+//!
+//! This module configures the `tracing` subscriber of the server. Logs are
+//! written to standard output as human-readable text or as one JSON object per
+//! line, and are configured through environment variables:
+//!
+//! - `RUSTY_BOT_LOG` - Log filter, such as `info` or `rusty_bot=debug,surrealdb=warn` (default `info`)
+//! - `RUSTY_BOT_LOG_FORMAT` - `text` or `json` (default `text`)
+//! - `RUSTY_BOT_LOG_CONTENT` - Set to `1` to log prompts, responses and queries in full
+//!
+//! Prompts, responses and search queries may contain private data, so they are
+//! redacted from the logs unless `RUSTY_BOT_LOG_CONTENT` is set.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use tracing_subscriber::EnvFilter;
+
+/// Default log filter when `RUSTY_BOT_LOG` is not set
+const DEFAULT_FILTER: &str = "info";
+
+/// Whether user content is logged in full, read once from the environment
+static LOG_CONTENT: OnceLock<bool> = OnceLock::new();
+
+/// Identifier of the last HTTP request received
+static LAST_REQUEST_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Installs the global subscriber configured by the environment
+///
+/// Calling it more than once has no effect.
+pub fn init() {
+    let filter = EnvFilter::try_from_env("RUSTY_BOT_LOG")
+        .unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let json = std::env::var("RUSTY_BOT_LOG_FORMAT").is_ok_and(|format| format.eq_ignore_ascii_case("json"));
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    let result = if json {
+        builder.json().with_current_span(true).with_span_list(true).try_init()
+    } else {
+        builder.try_init()
+    };
+    if result.is_ok() && log_content() {
+        tracing::warn!("RUSTY_BOT_LOG_CONTENT is set, prompts and responses are logged in full");
+    }
+}
+
+/// Returns whether user content is logged in full
+pub fn log_content() -> bool {
+    *LOG_CONTENT.get_or_init(|| {
+        std::env::var("RUSTY_BOT_LOG_CONTENT").is_ok_and(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+    })
+}
+
+/// Prepares user content such as a prompt or a query for the logs
+///
+/// # Parameters
+/// * `text` - The content to log
+///
+/// # Returns
+/// * `String` - The content itself when content logging is enabled, or its length otherwise
+pub fn content(text: &str) -> String {
+    if log_content() {
+        text.to_string()
+    } else {
+        format!("<redacted, {} chars>", text.chars().count())
+    }
+}
+
+/// Returns a new identifier for an HTTP request
+pub fn next_request_id() -> u64 {
+    LAST_REQUEST_ID.fetch_add(1, Ordering::Relaxed) + 1
+}
@@ -6,3 +6,90 @@ pub mod embedding;
 
 #[cfg(feature = "server")]
 pub mod database_impl;
+
+#[cfg(feature = "server")]
+pub mod ranking;
+
+#[cfg(feature = "server")]
+pub mod rerank;
+
+#[cfg(feature = "server")]
+pub mod query_rewrite;
+
+#[cfg(feature = "server")]
+pub mod app_store;
+
+#[cfg(feature = "server")]
+pub mod collections;
+
+#[cfg(feature = "server")]
+pub mod chunking;
+
+#[cfg(feature = "server")]
+pub mod embedding_cache;
+
+#[cfg(feature = "server")]
+pub mod similarity;
+
+#[cfg(feature = "server")]
+pub mod clustering;
+
+#[cfg(feature = "server")]
+pub mod backup;
+
+#[cfg(feature = "server")]
+pub mod cli;
+
+#[cfg(feature = "server")]
+pub mod tools;
+
+#[cfg(feature = "server")]
+pub mod calculator;
+
+#[cfg(feature = "server")]
+pub mod agentic_search;
+
+#[cfg(feature = "server")]
+pub mod json_schema;
+
+#[cfg(feature = "server")]
+pub mod grounding;
+
+#[cfg(feature = "server")]
+pub mod evaluation;
+
+#[cfg(feature = "server")]
+pub mod generation_metrics;
+
+#[cfg(feature = "server")]
+pub mod benchmark;
+
+#[cfg(feature = "server")]
+pub mod prometheus;
+
+#[cfg(feature = "server")]
+pub mod http;
+
+#[cfg(feature = "server")]
+pub mod logging;
+
+#[cfg(feature = "server")]
+pub mod status;
+
+#[cfg(feature = "server")]
+pub mod startup;
+
+#[cfg(feature = "server")]
+pub mod auth;
+
+#[cfg(feature = "server")]
+pub mod api_keys;
+
+#[cfg(feature = "server")]
+pub mod usage;
+
+#[cfg(feature = "server")]
+pub mod config;
+
+#[cfg(feature = "server")]
+pub mod settings;
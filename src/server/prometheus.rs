@@ -0,0 +1,199 @@
+//! Prometheus Metrics
+//!
+//! This module collects the operational metrics of the server and renders them
+//! in the Prometheus text exposition format for the `/metrics` endpoint:
+//!
+//! - Requests received by every server function
+//! - Generation latency, time to first token and generated tokens
+//! - Responses being generated at the same time
+//! - Retrieval latency of context searches
+//! - Embedding cache hits and misses
+//! - Number of documents in the knowledge base
+//!
+//! Metrics live in memory and start from zero every time the server starts.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use crate::model::metrics::GenerationMetrics;
+use crate::server::{database_impl, embedding_cache};
+
+/// Bucket bounds of the generation histograms, in seconds
+const GENERATION_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+/// Bucket bounds of the retrieval histogram, in seconds
+const RETRIEVAL_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+/// Requests received by every server function, by function name
+static SERVER_FUNCTION_REQUESTS: Mutex<BTreeMap<&'static str, u64>> = Mutex::new(BTreeMap::new());
+
+/// Total time taken by every response
+static GENERATION_DURATION: Mutex<Histogram> = Mutex::new(Histogram::new(GENERATION_BUCKETS));
+
+/// Time until the first token of every response
+static TIME_TO_FIRST_TOKEN: Mutex<Histogram> = Mutex::new(Histogram::new(GENERATION_BUCKETS));
+
+/// Time taken by every context search
+static RETRIEVAL_DURATION: Mutex<Histogram> = Mutex::new(Histogram::new(RETRIEVAL_BUCKETS));
+
+/// Tokens generated by the language model
+static GENERATED_TOKENS: AtomicU64 = AtomicU64::new(0);
+
+/// Tokens of the prompts answered by the language model
+static PROMPT_TOKENS: AtomicU64 = AtomicU64::new(0);
+
+/// Responses currently being generated
+static GENERATIONS_IN_PROGRESS: AtomicI64 = AtomicI64::new(0);
+
+/// A cumulative histogram with fixed bucket bounds
+struct Histogram {
+    /// Upper bounds of the buckets, in increasing order
+    bounds: &'static [f64],
+    /// Number of observations falling in each bucket, allocated on the first observation
+    counts: Vec<u64>,
+    /// Sum of the observed values
+    sum: f64,
+    /// Number of observations
+    count: u64,
+}
+
+impl Histogram {
+    /// Creates an empty histogram with the given bucket bounds
+    const fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            counts: Vec::new(),
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    /// Records a value
+    fn observe(&mut self, value: f64) {
+        if self.counts.is_empty() {
+            self.counts = vec![0; self.bounds.len()];
+        }
+        if let Some(bucket) = self.bounds.iter().position(|bound| value <= *bound) {
+            self.counts[bucket] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// Writes the histogram in the exposition format
+    fn render(&self, output: &mut String, name: &str, help: &str) {
+        let _ = writeln!(output, "# HELP {} {}", name, help);
+        let _ = writeln!(output, "# TYPE {} histogram", name);
+        let mut cumulative = 0;
+        for (index, bound) in self.bounds.iter().enumerate() {
+            cumulative += self.counts.get(index).copied().unwrap_or(0);
+            let _ = writeln!(output, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulative);
+        }
+        let _ = writeln!(output, "{}_bucket{{le=\"+Inf\"}} {}", name, self.count);
+        let _ = writeln!(output, "{}_sum {}", name, self.sum);
+        let _ = writeln!(output, "{}_count {}", name, self.count);
+    }
+}
+
+/// Counts a request received by a server function
+///
+/// # Parameters
+/// * `function` - Name of the server function
+pub fn count_request(function: &'static str) {
+    if let Ok(mut requests) = SERVER_FUNCTION_REQUESTS.lock() {
+        *requests.entry(function).or_insert(0) += 1;
+    }
+}
+
+/// Marks the start of a response of the language model
+pub fn generation_started() {
+    GENERATIONS_IN_PROGRESS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Marks the end of a response of the language model, whether it completed or
+/// was cancelled
+pub fn generation_ended() {
+    GENERATIONS_IN_PROGRESS.fetch_sub(1, Ordering::Relaxed);
+}
+
+/// Records the measurements of a completed response of the language model
+///
+/// # Parameters
+/// * `metrics` - The measurements of the response
+pub fn generation_finished(metrics: &GenerationMetrics) {
+    GENERATED_TOKENS.fetch_add(metrics.completion_tokens as u64, Ordering::Relaxed);
+    PROMPT_TOKENS.fetch_add(metrics.prompt_tokens as u64, Ordering::Relaxed);
+    if let Ok(mut histogram) = GENERATION_DURATION.lock() {
+        histogram.observe(metrics.total_ms / 1000.0);
+    }
+    if let Ok(mut histogram) = TIME_TO_FIRST_TOKEN.lock() {
+        histogram.observe(metrics.time_to_first_token_ms / 1000.0);
+    }
+}
+
+/// Records the time taken by a context search
+///
+/// # Parameters
+/// * `duration` - Time taken by the search
+pub fn observe_retrieval(duration: Duration) {
+    if let Ok(mut histogram) = RETRIEVAL_DURATION.lock() {
+        histogram.observe(duration.as_secs_f64());
+    }
+}
+
+/// Renders every metric in the Prometheus text exposition format
+///
+/// The document count is only reported once the document database is connected.
+///
+/// # Returns
+/// * `String` - The metrics, ready to be served by the `/metrics` endpoint
+pub async fn render() -> String {
+    let mut output = String::new();
+
+    let _ = writeln!(output, "# HELP rusty_bot_server_function_requests_total Requests received by each server function");
+    let _ = writeln!(output, "# TYPE rusty_bot_server_function_requests_total counter");
+    if let Ok(requests) = SERVER_FUNCTION_REQUESTS.lock() {
+        for (function, count) in requests.iter() {
+            let _ = writeln!(output, "rusty_bot_server_function_requests_total{{function=\"{}\"}} {}", function, count);
+        }
+    }
+
+    if let Ok(histogram) = GENERATION_DURATION.lock() {
+        histogram.render(&mut output, "rusty_bot_generation_duration_seconds", "Total time taken to generate a response");
+    }
+    if let Ok(histogram) = TIME_TO_FIRST_TOKEN.lock() {
+        histogram.render(&mut output, "rusty_bot_time_to_first_token_seconds", "Time until the first token of a response");
+    }
+    render_value(&mut output, "rusty_bot_generated_tokens_total", "counter",
+        "Tokens generated by the language model", GENERATED_TOKENS.load(Ordering::Relaxed));
+    render_value(&mut output, "rusty_bot_prompt_tokens_total", "counter",
+        "Tokens of the prompts answered by the language model", PROMPT_TOKENS.load(Ordering::Relaxed));
+    render_value(&mut output, "rusty_bot_generations_in_progress", "gauge",
+        "Responses currently being generated", GENERATIONS_IN_PROGRESS.load(Ordering::Relaxed));
+
+    if let Ok(histogram) = RETRIEVAL_DURATION.lock() {
+        histogram.render(&mut output, "rusty_bot_retrieval_duration_seconds", "Time taken by a context search");
+    }
+
+    let (hits, misses) = embedding_cache::hit_counts();
+    render_value(&mut output, "rusty_bot_embedding_cache_hits_total", "counter",
+        "Embeddings served from the embedding cache", hits);
+    render_value(&mut output, "rusty_bot_embedding_cache_misses_total", "counter",
+        "Embeddings computed by the embedding model", misses);
+
+    if let Ok(documents) = database_impl::count_documents().await {
+        render_value(&mut output, "rusty_bot_documents", "gauge",
+            "Documents in the knowledge base", documents);
+    }
+
+    output
+}
+
+/// Writes a metric with a single unlabelled value
+fn render_value(output: &mut String, name: &str, kind: &str, help: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(output, "# HELP {} {}", name, help);
+    let _ = writeln!(output, "# TYPE {} {}", name, kind);
+    let _ = writeln!(output, "{} {}", name, value);
+}
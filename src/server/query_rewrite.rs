@@ -0,0 +1,120 @@
+//! Query Rewriting Implementation
+//!
+//! This module turns the latest user message into standalone search queries.
+//! Follow-up questions such as "and how do I disable it?" only make sense with
+//! the conversation that precedes them, so the language model is asked to
+//! resolve those references and, optionally, to produce paraphrases whose search
+//! results are merged to improve recall.
+
+use crate::model::chat::{ChatMessage, ChatRole};
+use crate::model::document::MAX_PARAPHRASES;
+use crate::server::llm::{complete_prompt, TokenUsage};
+
+/// Instructions given to the language model when rewriting a message
+const REWRITE_SYSTEM_PROMPT: &str = "You rewrite the latest message of a conversation into \
+    standalone search queries for a document search engine. Resolve pronouns and references \
+    using the conversation so each query can be understood on its own. Answer with one query \
+    per line, without numbering, quotes or explanations.";
+
+/// Maximum number of characters of each history message included in the prompt
+const HISTORY_MESSAGE_MAX_CHARS: usize = 500;
+
+/// Maximum number of tokens generated for each requested query
+const QUERY_MAX_LENGTH: u32 = 64;
+
+/// Rewrites the latest message into one or more standalone search queries
+///
+/// When there is no history and no paraphrase is requested the message is
+/// already standalone and is returned unchanged without calling the model.
+///
+/// # Parameters
+/// * `history` - The recent conversation preceding the message, oldest first
+/// * `message` - The latest user message
+/// * `count` - Number of queries to produce, the first one being the rewrite,
+///   at most one more than `MAX_PARAPHRASES`
+/// * `usage` - Accumulates the tokens of the rewriting prompt
+///
+/// # Returns
+/// * `Result<Vec<String>, String>` - The standalone queries or an error message
+pub async fn rewrite_query(
+    history: &[ChatMessage],
+    message: &str,
+    count: usize,
+    usage: &mut TokenUsage,
+) -> Result<Vec<String>, String> {
+    let count = count.clamp(1, 1 + MAX_PARAPHRASES);
+    if history.is_empty() && count == 1 {
+        return Ok(vec![message.to_string()]);
+    }
+
+    let prompt = build_rewrite_prompt(history, message, count);
+    let (answer, rewrite_usage) = complete_prompt(REWRITE_SYSTEM_PROMPT, &prompt, QUERY_MAX_LENGTH * count as u32).await?;
+    *usage += rewrite_usage;
+
+    let queries = parse_queries(&answer, count);
+    if queries.is_empty() {
+        Ok(vec![message.to_string()])
+    } else {
+        Ok(queries)
+    }
+}
+
+/// Builds the rewriting prompt from the conversation history and latest message
+fn build_rewrite_prompt(history: &[ChatMessage], message: &str, count: usize) -> String {
+    let conversation = format_conversation(history);
+
+    let request = if count == 1 {
+        "Write 1 standalone search query.".to_string()
+    } else {
+        format!("Write {} different standalone search queries with the same meaning.", count)
+    };
+
+    format!(
+        "Conversation:\n{}\n\nLatest message: {}\n\n{}",
+        conversation, message, request
+    )
+}
+
+/// Formats the conversation history as one "Speaker: text" line per message
+///
+/// Long messages are truncated to keep the prompt short.
+pub fn format_conversation(history: &[ChatMessage]) -> String {
+    history.iter()
+        .map(|msg| {
+            let speaker = match msg.role {
+                ChatRole::User => "User",
+                ChatRole::Assistant => "Assistant",
+            };
+            let content: String = msg.content.chars().take(HISTORY_MESSAGE_MAX_CHARS).collect();
+            format!("{}: {}", speaker, content)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Extracts the queries from the model answer, one per line
+///
+/// List markers and surrounding quotes are removed, duplicates are skipped and
+/// at most `count` queries are kept.
+fn parse_queries(answer: &str, count: usize) -> Vec<String> {
+    let mut queries: Vec<String> = Vec::with_capacity(count);
+
+    for line in answer.lines() {
+        let query = line
+            .trim()
+            .trim_start_matches(|c: char| c.is_ascii_digit() || matches!(c, '-' | '*' | '.' | ')'))
+            .trim()
+            .trim_matches('"')
+            .trim();
+
+        let is_duplicate = queries.iter().any(|existing| existing.eq_ignore_ascii_case(query));
+        if !query.is_empty() && !is_duplicate {
+            queries.push(query.to_string());
+        }
+        if queries.len() == count {
+            break;
+        }
+    }
+
+    queries
+}
@@ -0,0 +1,194 @@
+//! Ranking Utilities
+//!
+//! This module contains the logic used to combine the rankings produced by the
+//! different retrieval signals (embedding search and full-text search) into a
+//! single ranking using reciprocal rank fusion, to merge the results of several
+//! queries, and to diversify a ranking with maximal marginal relevance.
+
+use crate::model::document::SimpleDocumentResult;
+
+/// Smoothing constant of reciprocal rank fusion
+/// Larger values reduce the advantage of the very first positions
+const RRF_K: f32 = 60.0;
+
+/// Computes the reciprocal rank contribution of a zero-based rank
+fn reciprocal_rank(rank: usize) -> f32 {
+    1.0 / (RRF_K + rank as f32 + 1.0)
+}
+
+/// Fuses the embedding and full-text rankings with reciprocal rank fusion
+///
+/// Both signals search the same chunks, so a chunk found by both is recognized
+/// by its chunk identifier.
+///
+/// # Parameters
+/// * `vector_results` - Results of the embedding search, best first
+/// * `keyword_results` - Results of the full-text search, best first
+/// * `keyword_weight` - Weight of the full-text ranking, between 0.0 and 1.0
+///
+/// # Returns
+/// * `Vec<SimpleDocumentResult>` - The merged results sorted by fused score, with
+///   the signals of both rankings preserved
+pub fn fuse_rankings(
+    vector_results: Vec<SimpleDocumentResult>,
+    keyword_results: Vec<SimpleDocumentResult>,
+    keyword_weight: f32,
+) -> Vec<SimpleDocumentResult> {
+    let keyword_weight = keyword_weight.clamp(0.0, 1.0);
+    let vector_weight = 1.0 - keyword_weight;
+
+    let mut fused = vector_results;
+    for keyword_result in keyword_results {
+        let existing = fused.iter_mut().find(|result| {
+            result.chunk_id.is_some() && result.chunk_id == keyword_result.chunk_id
+        });
+        match existing {
+            Some(result) => {
+                result.signals.keyword_score = keyword_result.signals.keyword_score;
+                result.signals.keyword_rank = keyword_result.signals.keyword_rank;
+            }
+            None => fused.push(keyword_result),
+        }
+    }
+
+    for result in fused.iter_mut() {
+        let vector_part = result.signals.vector_rank.map_or(0.0, reciprocal_rank);
+        let keyword_part = result.signals.keyword_rank.map_or(0.0, reciprocal_rank);
+        result.score = vector_weight * vector_part + keyword_weight * keyword_part;
+    }
+
+    fused.sort_by(|a, b| b.score.total_cmp(&a.score));
+    fused
+}
+
+/// Merges the results of several queries into a single ranking
+///
+/// The scores of different queries are not on the same scale, so the rankings
+/// are combined with reciprocal rank fusion: a chunk scores the sum of its
+/// reciprocal ranks across the queries that returned it, and appears once with
+/// the signals of its best-ranked occurrence.
+///
+/// # Parameters
+/// * `result_sets` - The results of each query, best first
+/// * `top_k` - Maximum number of results to keep
+///
+/// # Returns
+/// * `Vec<SimpleDocumentResult>` - The merged results sorted by fused score
+pub fn merge_query_results(result_sets: Vec<Vec<SimpleDocumentResult>>, top_k: usize) -> Vec<SimpleDocumentResult> {
+    let mut merged: Vec<(SimpleDocumentResult, usize, f32)> = Vec::new();
+
+    for results in result_sets {
+        for (rank, result) in results.into_iter().enumerate() {
+            let existing = merged.iter_mut().find(|(existing, _, _)| same_chunk(existing, &result));
+            match existing {
+                Some((existing, best_rank, score)) => {
+                    *score += reciprocal_rank(rank);
+                    if rank < *best_rank {
+                        *existing = result;
+                        *best_rank = rank;
+                    }
+                }
+                None => merged.push((result, rank, reciprocal_rank(rank))),
+            }
+        }
+    }
+
+    merged.sort_by(|a, b| b.2.total_cmp(&a.2));
+    merged.into_iter()
+        .take(top_k)
+        .map(|(mut result, _, score)| {
+            result.score = score;
+            result
+        })
+        .collect()
+}
+
+/// Checks whether two results are the same chunk, comparing their text when
+/// they have no chunk identifier
+fn same_chunk(a: &SimpleDocumentResult, b: &SimpleDocumentResult) -> bool {
+    match (&a.chunk_id, &b.chunk_id) {
+        (Some(a), Some(b)) => a == b,
+        _ => a.title == b.title && a.body == b.body,
+    }
+}
+
+/// Computes the cosine similarity between two embedding vectors
+///
+/// Returns 0.0 when either vector has no magnitude or their lengths differ.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// Selects a diverse subset of results with maximal marginal relevance
+///
+/// Results are picked greedily. Each step takes the candidate that maximizes
+/// `lambda * sim(query, candidate) - (1 - lambda) * max sim(candidate, selected)`,
+/// so near-duplicates of already selected chunks are pushed down.
+///
+/// # Parameters
+/// * `query_embedding` - The embedding of the search query
+/// * `candidates` - The candidate results paired with their chunk embeddings
+/// * `lambda` - Trade-off between relevance (1.0) and diversity (0.0)
+/// * `k` - Maximum number of results to select
+///
+/// # Returns
+/// * `Vec<SimpleDocumentResult>` - The selected results in selection order
+pub fn maximal_marginal_relevance(
+    query_embedding: &[f32],
+    candidates: Vec<(SimpleDocumentResult, Vec<f32>)>,
+    lambda: f32,
+    k: usize,
+) -> Vec<SimpleDocumentResult> {
+    let lambda = lambda.clamp(0.0, 1.0);
+    let relevance: Vec<f32> = candidates
+        .iter()
+        .map(|(_, embedding)| cosine_similarity(query_embedding, embedding))
+        .collect();
+
+    let mut remaining: Vec<usize> = (0..candidates.len()).collect();
+    let mut selected: Vec<(usize, f32)> = Vec::with_capacity(k);
+
+    while selected.len() < k && !remaining.is_empty() {
+        let (position, mmr_score) = remaining
+            .iter()
+            .enumerate()
+            .map(|(position, &candidate)| {
+                let redundancy = selected
+                    .iter()
+                    .map(|&(chosen, _)| cosine_similarity(&candidates[candidate].1, &candidates[chosen].1))
+                    .fold(0.0_f32, f32::max);
+                (position, lambda * relevance[candidate] - (1.0 - lambda) * redundancy)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("remaining candidates is not empty");
+
+        selected.push((remaining.remove(position), mmr_score));
+    }
+
+    let mut candidates: Vec<Option<SimpleDocumentResult>> = candidates
+        .into_iter()
+        .map(|(result, _)| Some(result))
+        .collect();
+
+    selected
+        .into_iter()
+        .filter_map(|(index, mmr_score)| {
+            candidates[index].take().map(|mut result| {
+                result.signals.mmr_score = Some(mmr_score);
+                result
+            })
+        })
+        .collect()
+}
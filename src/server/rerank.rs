@@ -0,0 +1,77 @@
+//! Reranking Implementation
+//!
+//! This module provides an optional reranking stage for search results. The
+//! candidates returned by the retrieval stage are scored one by one by the local
+//! language model, which judges how relevant each chunk is to the query, and are
+//! then reordered by that judgement.
+
+use crate::model::document::SimpleDocumentResult;
+use crate::server::llm::{complete_prompt, TokenUsage};
+
+/// Instructions given to the language model when scoring a candidate
+const RERANK_SYSTEM_PROMPT: &str = "You rate how relevant a passage is to a search query. \
+    Answer with a single integer from 0 (irrelevant) to 10 (the passage fully answers the query) \
+    and nothing else.";
+
+/// Highest relevance grade the model is asked to produce
+const MAX_GRADE: f32 = 10.0;
+
+/// Maximum number of tokens generated for each relevance grade
+const GRADE_MAX_LENGTH: u32 = 4;
+
+/// Reorders search results by the relevance judged by the language model
+///
+/// Each candidate receives a rerank score between 0.0 and 1.0, which becomes its
+/// final score. The original retrieval signals are kept for debugging.
+///
+/// # Parameters
+/// * `query` - The search query text
+/// * `candidates` - The results of the retrieval stage
+/// * `usage` - Accumulates the tokens of the grading prompts
+///
+/// # Returns
+/// * `Result<Vec<SimpleDocumentResult>, String>` - The candidates sorted by rerank score or an error
+pub async fn rerank(
+    query: &str,
+    candidates: Vec<SimpleDocumentResult>,
+    usage: &mut TokenUsage,
+) -> Result<Vec<SimpleDocumentResult>, String> {
+    let mut reranked = Vec::with_capacity(candidates.len());
+
+    for mut candidate in candidates {
+        let score = grade_candidate(query, &candidate, usage).await?;
+        candidate.signals.rerank_score = Some(score);
+        candidate.score = score;
+        reranked.push(candidate);
+    }
+
+    reranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(reranked)
+}
+
+/// Asks the language model to grade a single candidate against the query
+async fn grade_candidate(query: &str, candidate: &SimpleDocumentResult, usage: &mut TokenUsage) -> Result<f32, String> {
+    let prompt = format!(
+        "Query: {}\n\nPassage:\nTitle: {}\n{}\n\nRelevance:",
+        query, candidate.title, candidate.body
+    );
+    let (answer, grade_usage) = complete_prompt(RERANK_SYSTEM_PROMPT, &prompt, GRADE_MAX_LENGTH).await?;
+    *usage += grade_usage;
+    Ok(parse_grade(&answer))
+}
+
+/// Extracts the first integer of the model answer and normalizes it to 0.0..=1.0
+///
+/// Answers without a number are treated as irrelevant.
+fn parse_grade(answer: &str) -> f32 {
+    let digits: String = answer
+        .trim_start_matches(|c: char| !c.is_ascii_digit())
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+
+    digits
+        .parse::<f32>()
+        .map(|grade| grade.min(MAX_GRADE) / MAX_GRADE)
+        .unwrap_or(0.0)
+}
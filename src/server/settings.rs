@@ -0,0 +1,85 @@
+//! Runtime Settings
+//!
+//! This module holds the settings admins change from the settings page while
+//! the server runs: the persona of new conversations, the number of passages
+//! retrieved by default and the token quotas of every role. Changes are stored
+//! in the application database, so they survive restarts, and take effect on
+//! the next request without restarting the server. Until a change is saved, the
+//! settings of the configuration file are used.
+
+use std::sync::Mutex;
+use tracing::{error, info};
+use crate::model::document::MAX_RETRIEVAL_TOP_K;
+use crate::model::settings::RuntimeSettings;
+use crate::server::app_store::get_app_db;
+use crate::server::config;
+
+/// Name of the table holding the runtime settings
+const SETTINGS_TABLE: &str = "settings";
+
+/// Id of the record holding the runtime settings
+const SETTINGS_RECORD: &str = "runtime";
+
+/// Maximum number of characters of the persona
+const MAX_PERSONA_CHARS: usize = 4000;
+
+/// Settings in effect, loaded from the application database on first use
+static CURRENT: Mutex<Option<RuntimeSettings>> = Mutex::new(None);
+
+/// Returns the settings in effect
+///
+/// # Returns
+/// * `Result<RuntimeSettings, String>` - The saved settings, those of the configuration file if none are saved, or an error message
+pub async fn current() -> Result<RuntimeSettings, String> {
+    if let Some(settings) = CURRENT.lock().map_err(|_| "Error locking settings")?.clone() {
+        return Ok(settings);
+    }
+
+    let db = get_app_db().await?;
+    let stored: Option<RuntimeSettings> = db
+        .select((SETTINGS_TABLE, SETTINGS_RECORD))
+        .await
+        .map_err(|e| {
+            error!("Error loading settings: {}", e);
+            e.to_string()
+        })?;
+    let settings = stored.unwrap_or_else(|| config::get().settings.clone());
+    *CURRENT.lock().map_err(|_| "Error locking settings")? = Some(settings.clone());
+    Ok(settings)
+}
+
+/// Saves new settings and puts them into effect
+///
+/// # Parameters
+/// * `settings` - The settings to apply
+///
+/// # Returns
+/// * `Result<(), String>` - Success, or an error message when the settings are invalid or cannot be stored
+pub async fn save(mut settings: RuntimeSettings) -> Result<(), String> {
+    settings.persona = settings.persona.trim().to_string();
+    if settings.persona.chars().count() > MAX_PERSONA_CHARS {
+        return Err(format!("The persona must be at most {} characters", MAX_PERSONA_CHARS));
+    }
+    if !(1..=MAX_RETRIEVAL_TOP_K).contains(&settings.retrieval_top_k) {
+        return Err(format!("The retrieval top-k must be between 1 and {}", MAX_RETRIEVAL_TOP_K));
+    }
+
+    let db = get_app_db().await?;
+    let _: Option<RuntimeSettings> = db
+        .upsert((SETTINGS_TABLE, SETTINGS_RECORD))
+        .content(settings.clone())
+        .await
+        .map_err(|e| {
+            error!("Error saving settings: {}", e);
+            e.to_string()
+        })?;
+
+    info!(
+        retrieval_top_k = settings.retrieval_top_k,
+        quotas = ?settings.quotas,
+        persona_chars = settings.persona.chars().count(),
+        "Runtime settings updated"
+    );
+    *CURRENT.lock().map_err(|_| "Error locking settings")? = Some(settings);
+    Ok(())
+}
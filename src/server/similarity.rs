@@ -0,0 +1,128 @@
+//! Semantic Similarity Tools
+//!
+//! This module provides similarity tools over the knowledge base, meant to help
+//! writers spot redundant or contradictory pages: comparing two texts, finding
+//! near-duplicate chunks across the corpus, and finding the documents most
+//! similar to a given chunk.
+
+use crate::model::document::{DuplicatePair, SearchMode, SearchOptions, SimpleDocumentResult};
+use crate::server::database_impl;
+use crate::server::embedding::embed_texts;
+use crate::server::llm::TokenUsage;
+use crate::server::ranking::cosine_similarity;
+use std::collections::HashSet;
+
+/// Number of candidates retrieved per requested similar document
+/// Several chunks of the same document usually rank next to each other
+const SIMILAR_CANDIDATES_FACTOR: usize = 4;
+
+/// Maximum number of similar documents returned for a chunk
+const MAX_SIMILAR_DOCUMENTS: usize = 50;
+
+/// Maximum number of chunks scanned when looking for near duplicates
+const MAX_DUPLICATE_SCAN_CHUNKS: usize = 5000;
+
+/// Number of nearest neighbours compared with each scanned chunk
+const DUPLICATE_NEIGHBOURS: usize = 8;
+
+/// Maximum number of near-duplicate pairs returned
+const MAX_DUPLICATE_PAIRS: usize = 200;
+
+/// Computes the cosine similarity between the embeddings of two texts
+///
+/// # Parameters
+/// * `first` - The first text
+/// * `second` - The second text
+///
+/// # Returns
+/// * `Result<f32, String>` - The similarity between -1.0 and 1.0 or an error message
+pub async fn text_similarity(first: String, second: String) -> Result<f32, String> {
+    let embeddings = embed_texts(vec![first, second]).await?;
+    Ok(cosine_similarity(&embeddings[0], &embeddings[1]))
+}
+
+/// Finds pairs of chunks in the knowledge base whose similarity is above a threshold
+///
+/// Each chunk is compared with its nearest neighbours found through the vector
+/// index, using the embeddings stored at ingestion, so nothing is embedded again
+/// and the cost grows linearly with the corpus. At most
+/// `MAX_DUPLICATE_SCAN_CHUNKS` chunks are scanned and `MAX_DUPLICATE_PAIRS`
+/// pairs returned.
+///
+/// # Parameters
+/// * `threshold` - Minimum cosine similarity for two chunks to be reported
+/// * `limit` - Maximum number of pairs to return
+///
+/// # Returns
+/// * `Result<Vec<DuplicatePair>, String>` - The most similar pairs first, or an error message
+pub async fn find_near_duplicates(threshold: f32, limit: usize) -> Result<Vec<DuplicatePair>, String> {
+    let limit = limit.min(MAX_DUPLICATE_PAIRS);
+    let chunks = database_impl::list_chunk_embeddings(MAX_DUPLICATE_SCAN_CHUNKS).await?;
+
+    let mut seen = HashSet::new();
+    let mut pairs = Vec::new();
+    for chunk in &chunks {
+        let id = chunk.id();
+        // The nearest neighbour of a chunk is usually the chunk itself
+        let neighbours = database_impl::nearest_chunks(chunk.embedding.clone(), DUPLICATE_NEIGHBOURS + 1).await?;
+        for (neighbour, distance) in neighbours {
+            let neighbour_id = neighbour.id();
+            let similarity = 1.0 - distance;
+            if neighbour_id == id || similarity < threshold {
+                continue;
+            }
+            let key = if id < neighbour_id { (id.clone(), neighbour_id) } else { (neighbour_id, id.clone()) };
+            if seen.insert(key) {
+                pairs.push(DuplicatePair {
+                    first: chunk.to_document_chunk(),
+                    second: neighbour.to_document_chunk(),
+                    similarity,
+                });
+            }
+        }
+    }
+
+    pairs.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    pairs.truncate(limit);
+    Ok(pairs)
+}
+
+/// Finds the documents most similar to a chunk, excluding the chunk's own document
+///
+/// Each document appears once, represented by its most similar chunk. At most
+/// `MAX_SIMILAR_DOCUMENTS` documents are returned.
+///
+/// # Parameters
+/// * `text` - The text of the chunk
+/// * `title` - The title of the document the chunk belongs to
+/// * `limit` - Maximum number of documents to return
+///
+/// # Returns
+/// * `Result<Vec<SimpleDocumentResult>, String>` - The most similar documents first, or an error message
+pub async fn find_similar_documents(text: &str, title: &str, limit: usize) -> Result<Vec<SimpleDocumentResult>, String> {
+    let limit = limit.min(MAX_SIMILAR_DOCUMENTS);
+    if limit == 0 {
+        return Ok(Vec::new());
+    }
+    let options = SearchOptions {
+        mode: SearchMode::Vector,
+        top_k: limit.saturating_mul(SIMILAR_CANDIDATES_FACTOR),
+        rerank: false,
+        mmr_lambda: None,
+        rewrite_query: false,
+        ..SearchOptions::default()
+    };
+    let candidates = database_impl::query(text, &options, &mut TokenUsage::default()).await?;
+
+    let mut documents: Vec<SimpleDocumentResult> = Vec::with_capacity(limit);
+    for candidate in candidates {
+        let already_listed = documents.iter().any(|document| document.title == candidate.title);
+        if candidate.title != title && !already_listed {
+            documents.push(candidate);
+        }
+        if documents.len() == limit {
+            break;
+        }
+    }
+    Ok(documents)
+}
@@ -0,0 +1,102 @@
+//! Server Startup
+//!
+//! This module initializes the language model, the embedding model and the
+//! knowledge base once, when the server starts, instead of waiting for the first
+//! browser to open the page. Initialization is idempotent: every caller awaits
+//! the same run, so opening several tabs never rebuilds the database.
+//!
+//! Its progress (model downloads, load stages and ingested documents) is
+//! published through a watch channel so clients can display it while they wait.
+
+use std::sync::OnceLock;
+use kalosm::language::ModelLoadingProgress;
+use tokio::sync::{watch, OnceCell};
+use tracing::{error, info};
+use crate::model::status::{InitProgress, LoadStage};
+use crate::server::{database_impl, embedding, llm, usage};
+
+/// Result of the initialization, set once it completes
+static STARTUP: OnceCell<Result<(), String>> = OnceCell::const_new();
+
+/// Latest initialization progress, shared with every subscriber
+static PROGRESS: OnceLock<watch::Sender<InitProgress>> = OnceLock::new();
+
+/// Starts initializing the server in the background
+///
+/// Must be called from within the Tokio runtime of the server.
+pub fn start() {
+    tokio::spawn(async {
+        let _ = initialize().await;
+    });
+}
+
+/// Initializes the models and the knowledge base, or waits for the initialization in progress
+///
+/// The chat model is loaded while the embedding model and the knowledge base
+/// are prepared, as they do not depend on each other.
+///
+/// # Returns
+/// * `Result<(), String>` - Success or the error that made initialization fail
+pub async fn initialize() -> Result<(), String> {
+    STARTUP
+        .get_or_init(|| async {
+            info!("Initializing the server...");
+            let (chat, knowledge) = tokio::join!(
+                llm::init_chat_model(),
+                async {
+                    usage::prepare_usage_table().await?;
+                    embedding::init_embedding_model().await?;
+                    database_impl::connect_to_database().await
+                }
+            );
+
+            let result = chat.and(knowledge);
+            match &result {
+                Ok(()) => info!("Server initialized"),
+                Err(e) => error!("Error initializing the server: {}", e),
+            }
+            update(|progress| match &result {
+                Ok(()) => progress.ready = true,
+                Err(e) => progress.error = Some(e.clone()),
+            });
+            result
+        })
+        .await
+        .clone()
+}
+
+/// Subscribes to the initialization progress
+///
+/// # Returns
+/// * `watch::Receiver<InitProgress>` - Receiver holding the latest progress
+pub fn subscribe() -> watch::Receiver<InitProgress> {
+    sender().subscribe()
+}
+
+/// Updates the initialization progress and notifies the subscribers
+///
+/// # Parameters
+/// * `modify` - Function applying the change to the progress
+pub fn update(modify: impl FnOnce(&mut InitProgress)) {
+    sender().send_modify(modify);
+}
+
+/// Converts the progress reported by a model loader into a load stage and its progress
+///
+/// # Parameters
+/// * `progress` - The progress reported by the model loader
+///
+/// # Returns
+/// * `(LoadStage, f32)` - The current stage and its progress between 0.0 and 1.0
+pub fn load_stage(progress: &ModelLoadingProgress) -> (LoadStage, f32) {
+    let stage = match progress {
+        ModelLoadingProgress::Downloading { .. } => LoadStage::Downloading,
+        ModelLoadingProgress::Loading { .. } => LoadStage::Loading,
+    };
+    (stage, progress.progress().clamp(0.0, 1.0))
+}
+
+/// Gets the sender of the progress channel, creating it on first use
+fn sender() -> &'static watch::Sender<InitProgress> {
+    PROGRESS.get_or_init(|| watch::Sender::new(InitProgress::default()))
+}
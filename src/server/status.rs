@@ -0,0 +1,112 @@
+//! System Status
+//!
+//! This module tracks the initialization state of the server components, so
+//! load balancers and the user interface can tell whether the models have
+//! finished loading and the knowledge base has been indexed. The
+//! initialization functions report their progress here.
+
+use std::sync::Mutex;
+use crate::model::status::{ComponentState, ComponentStatus, SystemStatus};
+use crate::server::config;
+use crate::server::database_impl;
+use crate::server::embedding::embedding_model_id;
+
+/// Server components whose initialization is tracked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Component {
+    /// The base language model
+    Model,
+    /// The chat session of the language model
+    ChatSession,
+    /// The embedding model behind the embedding cache
+    EmbeddingModel,
+    /// The connection to the document database
+    DbConn,
+    /// The document table and the documents ingested into it
+    DocumentTable,
+}
+
+impl Component {
+    /// Every tracked component, in initialization order
+    const ALL: [Component; 5] = [
+        Component::Model,
+        Component::ChatSession,
+        Component::EmbeddingModel,
+        Component::DbConn,
+        Component::DocumentTable,
+    ];
+
+    /// Name of the component as reported by the status endpoints
+    fn name(self) -> &'static str {
+        match self {
+            Component::Model => "MODEL",
+            Component::ChatSession => "CHAT_SESSION",
+            Component::EmbeddingModel => "EMBEDDING_MODEL",
+            Component::DbConn => "DB_CONN",
+            Component::DocumentTable => "DOCUMENT_TABLE",
+        }
+    }
+}
+
+/// Current state of every component, indexed like `Component::ALL`
+static STATES: Mutex<[ComponentState; 5]> = Mutex::new([
+    ComponentState::Uninitialized,
+    ComponentState::Uninitialized,
+    ComponentState::Uninitialized,
+    ComponentState::Uninitialized,
+    ComponentState::Uninitialized,
+]);
+
+/// Records the state of a component
+///
+/// # Parameters
+/// * `component` - The component whose state changed
+/// * `state` - The new state
+pub fn set_state(component: Component, state: ComponentState) {
+    if let Ok(mut states) = STATES.lock() {
+        states[component as usize] = state;
+    }
+}
+
+/// Marks a component as failed when an initialization step returns an error
+///
+/// # Parameters
+/// * `component` - The component being initialized
+/// * `result` - The result of the initialization step
+///
+/// # Returns
+/// * `Result<T, String>` - The result, unchanged
+pub fn track<T>(component: Component, result: Result<T, String>) -> Result<T, String> {
+    if let Err(e) = &result {
+        set_state(component, ComponentState::Failed { error: e.clone() });
+    }
+    result
+}
+
+/// Returns the state of a component
+pub fn state(component: Component) -> ComponentState {
+    STATES.lock()
+        .map(|states| states[component as usize].clone())
+        .unwrap_or_default()
+}
+
+/// Reports the state of every component, the indexed documents and the model ids
+///
+/// # Returns
+/// * `SystemStatus` - The status of the server
+pub async fn system_status() -> SystemStatus {
+    let components: Vec<ComponentStatus> = Component::ALL.iter()
+        .map(|component| ComponentStatus {
+            name: component.name().to_string(),
+            state: state(*component),
+        })
+        .collect();
+
+    SystemStatus {
+        ready: components.iter().all(|component| component.state.is_ready()),
+        components,
+        document_count: database_impl::count_documents().await.ok(),
+        chat_model: config::get().model.source.id().to_string(),
+        embedding_model: embedding_model_id().to_string(),
+    }
+}
@@ -0,0 +1,248 @@
+//! Tool Calling
+//!
+//! This module lets the assistant call tools before answering a message. Every
+//! tool declares its arguments with a JSON schema. At each step the model is
+//! shown the available tools and the results of the previous calls, and its
+//! output is constrained to a JSON tool call, which the server validates against
+//! the schema and executes. The loop ends when the model calls the special
+//! `final_answer` tool or the step limit is reached; the results are then
+//! handed to the chat session, which writes the final answer.
+//!
+//! Built-in tools:
+//! - `search_knowledge_base` - Searches the document database
+//! - `calculator` - Evaluates arithmetic expressions
+
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use crate::model::document::SearchOptions;
+use crate::model::tool::ToolStep;
+use crate::server::calculator::Calculator;
+use crate::server::database_impl;
+use crate::server::llm::{complete_constrained, TokenUsage};
+use crate::server::logging;
+use tracing::info;
+
+/// Name of the pseudo-tool the model calls when it has enough information to answer
+const FINISH_TOOL: &str = "final_answer";
+
+/// Maximum number of tool calls made for a single message
+pub const MAX_TOOL_STEPS: usize = 4;
+
+/// Maximum number of results the knowledge base tool may request
+const MAX_SEARCH_RESULTS: usize = 5;
+
+/// Regular expression matching a JSON value the model may pass as an argument
+const ARGUMENT_VALUE_PATTERN: &str = r#"("[^"\\\n]{0,200}"|-?[0-9]{1,12}(\.[0-9]{1,6})?|true|false)"#;
+
+/// A function the assistant can call
+pub trait Tool: Send + Sync {
+    /// Name the model uses to call the tool
+    fn name(&self) -> &'static str;
+
+    /// Description shown to the model, saying when the tool is useful
+    fn description(&self) -> &'static str;
+
+    /// JSON schema of the arguments object
+    fn parameters(&self) -> Value;
+
+    /// Runs the tool with arguments already validated against `parameters`
+    ///
+    /// Returns the text shown to the model, or an error message that is also
+    /// shown to it so it can correct the call.
+    fn call(&self, arguments: Value) -> BoxFuture<'_, Result<String, String>>;
+}
+
+/// A tool call emitted by the model
+#[derive(Debug, Deserialize)]
+struct ToolCall {
+    tool: String,
+    arguments: Value,
+}
+
+/// Returns the tools available to the assistant
+pub fn builtin_tools() -> Vec<Box<dyn Tool>> {
+    vec![Box::new(KnowledgeBaseSearch), Box::new(Calculator)]
+}
+
+/// Lets the model call tools until it is ready to answer a message
+///
+/// # Parameters
+/// * `message` - The message the assistant is answering
+/// * `tools` - The tools the model may call
+/// * `max_steps` - Maximum number of tool calls
+/// * `usage` - Accumulates the tokens of the tool call prompts
+///
+/// # Returns
+/// * `Result<Vec<ToolStep>, String>` - The calls made, in order, or an error message
+pub async fn run_tools(
+    message: &str,
+    tools: &[Box<dyn Tool>],
+    max_steps: usize,
+    usage: &mut TokenUsage,
+) -> Result<Vec<ToolStep>, String> {
+    let system_prompt = build_system_prompt(tools);
+    let pattern = tool_call_pattern(tools);
+
+    let mut steps: Vec<ToolStep> = Vec::new();
+    while steps.len() < max_steps {
+        let (output, step_usage) = complete_constrained(&system_prompt, &build_step_prompt(message, &steps), &pattern).await?;
+        *usage += step_usage;
+        let call: ToolCall = serde_json::from_str(&output)
+            .map_err(|e| format!("Invalid tool call {}: {}", output, e))?;
+        if call.tool == FINISH_TOOL {
+            break;
+        }
+
+        let arguments = call.arguments.to_string();
+        // A repeated call would return the same result, so the model is done
+        if steps.last().is_some_and(|step| step.tool == call.tool && step.arguments == arguments) {
+            break;
+        }
+
+        info!(tool = %call.tool, arguments = %logging::content(&arguments), "Calling tool");
+        let result = match tools.iter().find(|tool| tool.name() == call.tool) {
+            Some(tool) => match validate_arguments(&tool.parameters(), &call.arguments) {
+                Ok(()) => tool.call(call.arguments).await,
+                Err(e) => Err(e),
+            },
+            None => Err(format!("Unknown tool {}", call.tool)),
+        };
+
+        steps.push(ToolStep {
+            tool: call.tool,
+            arguments,
+            is_error: result.is_err(),
+            output: result.unwrap_or_else(|e| e),
+        });
+    }
+    Ok(steps)
+}
+
+/// Builds the instructions listing the tools and their argument schemas
+fn build_system_prompt(tools: &[Box<dyn Tool>]) -> String {
+    let descriptions = tools.iter()
+        .map(|tool| format!("- {}: {}\n  Arguments schema: {}", tool.name(), tool.description(), tool.parameters()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "You decide which tool to call to help answer the user's message. Available tools:\n\
+         {}\n\
+         - {}: Call it with no arguments once the previous results are enough to answer, or when no tool is useful.\n\n\
+         Answer with a single JSON object of the form {{\"tool\": \"<name>\", \"arguments\": {{...}}}}.",
+        descriptions,
+        FINISH_TOOL
+    )
+}
+
+/// Builds the prompt for the next step, including the results of the previous calls
+fn build_step_prompt(message: &str, steps: &[ToolStep]) -> String {
+    let mut prompt = format!("User message: {}\n", message);
+    for step in steps {
+        prompt.push_str(&format!(
+            "\nCalled {} with {}\n{}: {}\n",
+            step.tool,
+            step.arguments,
+            if step.is_error { "Error" } else { "Result" },
+            step.output
+        ));
+    }
+    prompt.push_str("\nNext tool call:");
+    prompt
+}
+
+/// Builds the regular expression constraining the model output to a tool call
+///
+/// The arguments are restricted to a flat object of strings, numbers and
+/// booleans, which covers the schemas of every built-in tool.
+fn tool_call_pattern(tools: &[Box<dyn Tool>]) -> String {
+    let names = tools.iter()
+        .map(|tool| tool.name())
+        .chain(std::iter::once(FINISH_TOOL))
+        .collect::<Vec<_>>()
+        .join("|");
+    let argument = format!(r#""[a-z_]{{1,32}}": {}"#, ARGUMENT_VALUE_PATTERN);
+
+    format!(
+        r#"\{{"tool": "({names})", "arguments": \{{({argument}(, {argument}){{0,4}})?\}}\}}"#,
+        names = names,
+        argument = argument
+    )
+}
+
+/// Checks that the arguments of a call match the schema of the tool
+///
+/// Supports the subset of JSON schema used by the tools: an object whose
+/// properties have a primitive `type`, some of them `required`.
+fn validate_arguments(schema: &Value, arguments: &Value) -> Result<(), String> {
+    let arguments = arguments.as_object().ok_or("The arguments must be an object")?;
+    let properties = schema["properties"].as_object().cloned().unwrap_or_default();
+
+    for required in schema["required"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+        if !arguments.contains_key(required) {
+            return Err(format!("Missing required argument {}", required));
+        }
+    }
+
+    for (name, value) in arguments {
+        let property = properties.get(name).ok_or(format!("Unknown argument {}", name))?;
+        let matches = match property["type"].as_str() {
+            Some("string") => value.is_string(),
+            Some("integer") => value.is_i64() || value.is_u64(),
+            Some("number") => value.is_number(),
+            Some("boolean") => value.is_boolean(),
+            _ => true,
+        };
+        if !matches {
+            return Err(format!("Argument {} must be of type {}", name, property["type"]));
+        }
+    }
+    Ok(())
+}
+
+/// Tool searching the knowledge base with the default retrieval pipeline
+struct KnowledgeBaseSearch;
+
+impl Tool for KnowledgeBaseSearch {
+    fn name(&self) -> &'static str {
+        "search_knowledge_base"
+    }
+
+    fn description(&self) -> &'static str {
+        "Searches the documents of the knowledge base and returns the most relevant passages."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "description": "What to search for" },
+                "top_k": { "type": "integer", "description": "Number of passages to return, from 1 to 5" }
+            },
+            "required": ["query"]
+        })
+    }
+
+    fn call(&self, arguments: Value) -> BoxFuture<'_, Result<String, String>> {
+        Box::pin(async move {
+            let query = arguments["query"].as_str().unwrap_or_default().to_string();
+            let top_k = arguments["top_k"].as_u64().map_or(2, |top_k| top_k as usize);
+            let options = SearchOptions {
+                top_k: top_k.clamp(1, MAX_SEARCH_RESULTS),
+                ..SearchOptions::default()
+            };
+
+            // The default options never rerank, so the search does not prompt the model
+            let results = database_impl::query(&query, &options, &mut TokenUsage::default()).await?;
+            if results.is_empty() {
+                return Ok("No results".to_string());
+            }
+            Ok(results.iter()
+                .enumerate()
+                .map(|(index, result)| format!("[{}] {}\n{}", index + 1, result.title, result.body))
+                .collect::<Vec<_>>()
+                .join("\n\n"))
+        })
+    }
+}
@@ -0,0 +1,285 @@
+//! Usage Accounting
+//!
+//! This module records the tokens consumed by every call to the language model,
+//! attributed to the user and their conversation, in the persistent application
+//! database, and enforces the daily and monthly quotas of each role, part of the
+//! runtime settings, so one heavy user cannot starve everyone else sharing the
+//! model.
+//!
+//! Days and months are counted in UTC. Conversation ids restart at 1 with the
+//! server, so the usage of a conversation is grouped by the id together with
+//! the run of the server that generated it. Calls made before the user's first
+//! chat message, such as API calls, belong to no conversation.
+
+use std::fmt;
+use std::sync::LazyLock;
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+use crate::model::auth::UserInfo;
+use crate::model::usage::{ConversationUsage, UserUsage};
+use crate::server::app_store::get_app_db;
+use crate::server::auth::{self, now_secs};
+use crate::server::settings;
+
+/// Name of the table holding one record per call to the language model
+const USAGE_TABLE: &str = "usage";
+
+/// Number of recent conversations listed with the usage of a user
+const MAX_LISTED_CONVERSATIONS: usize = 20;
+
+/// Seconds in a day, used to find the current UTC day
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Reason a request is rejected by the usage quotas of its user
+#[derive(Debug)]
+pub enum QuotaError {
+    /// The daily or monthly allowance of the user is used up
+    Exceeded(String),
+    /// The usage or the quotas could not be read
+    Internal(String),
+}
+
+impl QuotaError {
+    /// HTTP status of the rejected request
+    pub fn status(&self) -> StatusCode {
+        match self {
+            QuotaError::Exceeded(_) => StatusCode::TOO_MANY_REQUESTS,
+            QuotaError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl fmt::Display for QuotaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotaError::Exceeded(reason) => write!(f, "Quota exceeded: {}", reason),
+            QuotaError::Internal(e) => write!(f, "Error checking usage quotas: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for QuotaError {}
+
+/// Identifies the current run of the server: the time, in seconds since the
+/// Unix epoch, usage was first recorded or read by it
+static SERVER_START: LazyLock<u64> = LazyLock::new(now_secs);
+
+/// The tokens consumed by a call to the language model
+#[derive(Debug, Serialize)]
+struct UsageRecord {
+    username: String,
+    conversation: Option<u64>,
+    server_start: u64,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    created_at: u64,
+    /// Day, in days since the Unix epoch
+    day: u64,
+    /// Calendar month, formatted as `YYYY-MM`
+    month: String,
+}
+
+/// Sum of the tokens of a set of responses
+#[derive(Debug, Default, Deserialize)]
+struct TokenSums {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+}
+
+impl TokenSums {
+    fn total(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// Creates the usage table and its index, so the usage of a user is found without a full scan
+///
+/// # Returns
+/// * `Result<(), String>` - Success or an error message
+pub async fn prepare_usage_table() -> Result<(), String> {
+    let db = get_app_db().await?;
+    db.query(format!(
+        "DEFINE TABLE IF NOT EXISTS {table} SCHEMALESS;
+         DEFINE INDEX IF NOT EXISTS {table}_username ON {table} FIELDS username;",
+        table = USAGE_TABLE
+    ))
+        .await
+        .and_then(|response| response.check())
+        .map_err(|e| {
+            error!("Error preparing usage table: {}", e);
+            e.to_string()
+        })?;
+    Ok(())
+}
+
+/// Records the tokens consumed by a call to the language model
+///
+/// # Parameters
+/// * `username` - The user the call was made for
+/// * `conversation` - Identifier of the user's conversation, `None` outside conversations
+/// * `prompt_tokens` - Tokens of the prompt
+/// * `completion_tokens` - Generated tokens
+///
+/// # Returns
+/// * `Result<(), String>` - Success or an error message
+pub async fn record_usage(username: &str, conversation: Option<u64>, prompt_tokens: u64, completion_tokens: u64) -> Result<(), String> {
+    let now = now_secs();
+    let record = UsageRecord {
+        username: username.to_string(),
+        conversation,
+        server_start: *SERVER_START,
+        prompt_tokens,
+        completion_tokens,
+        created_at: now,
+        day: now / SECS_PER_DAY,
+        month: month_of(now),
+    };
+
+    let db = get_app_db().await?;
+    db.query(format!("CREATE {} CONTENT $record", USAGE_TABLE))
+        .bind(("record", record))
+        .await
+        .map_err(|e| {
+            error!("Error recording usage: {}", e);
+            e.to_string()
+        })?;
+    Ok(())
+}
+
+/// Checks that a user has enough allowance left for a request
+///
+/// # Parameters
+/// * `user` - The user making the request
+/// * `tokens` - Tokens known to be consumed by the request, such as the prompt
+///
+/// # Returns
+/// * `Result<(), QuotaError>` - Success, or the reason the request is rejected
+pub async fn check_quota(user: &UserInfo, tokens: u64) -> Result<(), QuotaError> {
+    let quota = settings::current().await.map_err(QuotaError::Internal)?.quotas.for_role(user.role);
+    if quota.tokens_per_day == 0 && quota.tokens_per_month == 0 {
+        return Ok(());
+    }
+
+    let now = now_secs();
+    let today = sum_tokens(&user.username, "day = $day", now).await.map_err(QuotaError::Internal)?.total();
+    if quota.tokens_per_day > 0 && today + tokens > quota.tokens_per_day {
+        warn!(username = %user.username, "Daily usage quota exceeded");
+        return Err(QuotaError::Exceeded(format!(
+            "daily allowance of {} tokens used ({} tokens today)",
+            quota.tokens_per_day, today
+        )));
+    }
+
+    let month = sum_tokens(&user.username, "month = $month", now).await.map_err(QuotaError::Internal)?.total();
+    if quota.tokens_per_month > 0 && month + tokens > quota.tokens_per_month {
+        warn!(username = %user.username, "Monthly usage quota exceeded");
+        return Err(QuotaError::Exceeded(format!(
+            "monthly allowance of {} tokens used ({} tokens this month)",
+            quota.tokens_per_month, month
+        )));
+    }
+    Ok(())
+}
+
+/// Gets the consumption and remaining allowance of a user
+///
+/// # Parameters
+/// * `user` - The user whose usage is returned
+///
+/// # Returns
+/// * `Result<UserUsage, String>` - The usage of the user or an error message
+pub async fn user_usage(user: &UserInfo) -> Result<UserUsage, String> {
+    let now = now_secs();
+    let quota = settings::current().await?.quotas.for_role(user.role);
+    let today = sum_tokens(&user.username, "day = $day", now).await?;
+    let month = sum_tokens(&user.username, "month = $month", now).await?;
+    let total = sum_tokens(&user.username, "true", now).await?;
+
+    Ok(UserUsage {
+        username: user.username.clone(),
+        role: user.role,
+        quota,
+        tokens_today: today.total(),
+        tokens_this_month: month.total(),
+        total_prompt_tokens: total.prompt_tokens,
+        total_completion_tokens: total.completion_tokens,
+        conversations: conversation_usage(&user.username).await?,
+    })
+}
+
+/// Gets the usage of every account
+///
+/// # Returns
+/// * `Result<Vec<UserUsage>, String>` - The usage of the accounts, by username, or an error message
+pub async fn all_usage() -> Result<Vec<UserUsage>, String> {
+    let mut usage = Vec::new();
+    for user in auth::list_users().await? {
+        usage.push(user_usage(&user).await?);
+    }
+    Ok(usage)
+}
+
+/// Sums the tokens of the responses of a user matching a condition
+///
+/// The condition may use the `$day` and `$month` parameters, set to the day and
+/// month of `now`.
+async fn sum_tokens(username: &str, condition: &str, now: u64) -> Result<TokenSums, String> {
+    let db = get_app_db().await?;
+    let sums: Option<TokenSums> = db
+        .query(format!(
+            "SELECT math::sum(prompt_tokens) AS prompt_tokens, math::sum(completion_tokens) AS completion_tokens \
+                FROM {} WHERE username = $username AND {} GROUP ALL",
+            USAGE_TABLE, condition
+        ))
+        .bind(("username", username.to_string()))
+        .bind(("day", now / SECS_PER_DAY))
+        .bind(("month", month_of(now)))
+        .await
+        .and_then(|mut response| response.take(0))
+        .map_err(|e| {
+            error!("Error summing usage: {}", e);
+            e.to_string()
+        })?;
+    Ok(sums.unwrap_or_default())
+}
+
+/// Gets the usage of the most recent conversations of a user
+async fn conversation_usage(username: &str) -> Result<Vec<ConversationUsage>, String> {
+    let db = get_app_db().await?;
+    let mut conversations: Vec<ConversationUsage> = db
+        .query(format!(
+            "SELECT conversation, server_start, count() AS responses, \
+                math::sum(prompt_tokens) AS prompt_tokens, math::sum(completion_tokens) AS completion_tokens, \
+                math::max(created_at) AS last_used_at \
+                FROM {} WHERE username = $username GROUP BY conversation, server_start",
+            USAGE_TABLE
+        ))
+        .bind(("username", username.to_string()))
+        .await
+        .and_then(|mut response| response.take(0))
+        .map_err(|e| {
+            error!("Error listing conversation usage: {}", e);
+            e.to_string()
+        })?;
+
+    conversations.sort_by(|a, b| b.last_used_at.cmp(&a.last_used_at));
+    conversations.truncate(MAX_LISTED_CONVERSATIONS);
+    Ok(conversations)
+}
+
+/// Formats the UTC calendar month of a time as `YYYY-MM`
+///
+/// Converts days since the Unix epoch into a date of the proleptic Gregorian
+/// calendar, following Howard Hinnant's `civil_from_days` algorithm.
+fn month_of(secs: u64) -> String {
+    let days = (secs / SECS_PER_DAY) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}", year, month)
+}
@@ -5,39 +5,53 @@
 
 use dioxus::prelude::{server, server_fn, ServerFnError};
 use dioxus::prelude::server_fn::codec::{StreamingText, TextStream};
+use crate::model::api_key::{ApiKeyInfo, NewApiKey};
+use crate::model::auth::{Role, UserInfo};
+use crate::model::backup::ArchiveSummary;
+use crate::model::chat::ChatMessage;
+use crate::model::collection::{ChunkPreview, ChunkingStrategy, CollectionConfig};
+use crate::model::document::{ContextSearch, DocumentDetails, DocumentInfo, DuplicatePair, SearchOptions, SimpleDocumentResult};
+use crate::model::embedding::EmbeddingCacheStats;
+use crate::model::grounding::ClaimCheck;
+use crate::model::metrics::GenerationSummary;
+use crate::model::settings::{RuntimeSettings, SettingsOverview};
+use crate::model::status::SystemStatus;
+use crate::model::tool::ToolStep;
+use crate::model::topic::TopicMap;
+use crate::model::usage::UserUsage;
 
 /// Initializes the language model for chat functionality.
 ///
-/// This server function loads and prepares the chat model for use.
+/// The server initializes itself when it starts, so this waits for that
+/// initialization to complete. Calling it again has no effect.
 /// 
 /// # Returns
 /// 
 /// * `Result<(), ServerFnError>` - Success or error with detailed message
 #[server]
 pub async fn init_llm_model() -> Result<(), ServerFnError> {
-    use crate::server::llm::init_chat_model;
-    init_chat_model().await.map_err(|e| {
-        ServerFnError::new(&format!("Error initializing model: {}", e))
-    })
+    require_admin().await?;
+    wait_for_startup().await
 }
 
 /// Initializes the embedding model for text vectorization.
 ///
-/// This server function loads and prepares the embedding model for use.
+/// The server initializes itself when it starts, so this waits for that
+/// initialization to complete. Calling it again has no effect.
 /// 
 /// # Returns
 /// 
 /// * `Result<(), ServerFnError>` - Success or error with detailed message
 #[server]
 pub async fn init_embedding_model() -> Result<(), ServerFnError> {
-    use crate::server::embedding::init_embedding_model;
-    init_embedding_model().await.map_err(|e| {
-        ServerFnError::new(&format!("Error initializing embedding model: {}", e))
-    })
+    require_admin().await?;
+    wait_for_startup().await
 }
 
 /// Generates embedding vectors for the provided text.
 ///
+/// Requests made with an API key count against its quotas.
+///
 /// # Arguments
 ///
 /// * `txt` - The text to embed
@@ -47,18 +61,71 @@ pub async fn init_embedding_model() -> Result<(), ServerFnError> {
 /// * `Result<Vec<f32>, ServerFnError>` - Embedding vector or error message
 #[server]
 pub async fn get_embedding(txt: String) -> Result<Vec<f32>, ServerFnError> {
-    let result = tokio::task::spawn_blocking(move || {
-        futures::executor::block_on(crate::server::embedding::embed_text(&txt))
-    })
+    use crate::server::embedding::{check_requested_texts, embed_text};
+    let user = require_user().await?;
+    check_requested_texts(std::slice::from_ref(&txt)).map_err(|e| ServerFnError::new(&e))?;
+    ModelRequest::admit(user, estimate_tokens(&txt)).await?;
+
+    embed_text(&txt)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error embedding text: {}", e)))
+}
+
+/// Generates embedding vectors for a batch of texts.
+///
+/// The texts are embedded in batches, which is much faster than calling
+/// `get_embedding` once per text. Requests with more than 256 texts or a text
+/// longer than 8000 characters are rejected.
+///
+/// # Arguments
+///
+/// * `texts` - The texts to embed
+///
+/// # Returns
+///
+/// * `Result<Vec<Vec<f32>>, ServerFnError>` - One embedding vector per text, in order, or error message
+#[server]
+pub async fn get_embeddings(texts: Vec<String>) -> Result<Vec<Vec<f32>>, ServerFnError> {
+    use crate::server::embedding::{check_requested_texts, embed_requested_texts};
+    let user = require_user().await?;
+    check_requested_texts(&texts).map_err(|e| ServerFnError::new(&e))?;
+    ModelRequest::admit(user, texts.iter().map(|text| estimate_tokens(text)).sum()).await?;
+    let time = std::time::Instant::now();
+    let count = texts.len();
+
+    let embeddings = embed_requested_texts(texts)
         .await
-        .map_err(|e| ServerFnError::new(&e.to_string()))?;
+        .map_err(|e| ServerFnError::new(&format!("Error embedding texts: {}", e)))?;
+
+    let elapsed = time.elapsed();
+    tracing::info!(
+        "Embedded {} texts in {:?} ({:.1} texts/s)",
+        count,
+        elapsed,
+        count as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+    );
+    Ok(embeddings)
+}
 
-    result.map_err(|e| ServerFnError::new(&format!("Error embedding text: {}", e)))
+/// Returns the hit/miss counters and size of the embedding cache.
+///
+/// # Returns
+///
+/// * `Result<EmbeddingCacheStats, ServerFnError>` - The cache statistics or error
+#[server]
+pub async fn get_embedding_cache_stats() -> Result<EmbeddingCacheStats, ServerFnError> {
+    use crate::server::{embedding::embedding_model_id, embedding_cache::cache_stats};
+    require_admin().await?;
+
+    cache_stats(embedding_model_id())
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error reading embedding cache: {}", e)))
 }
 
-/// Resets the current chat session.
+/// Resets the chat session of the signed in user.
 ///
-/// Clears conversation history and resets the chat model's state.
+/// Clears the user's conversation history; the conversations of the other
+/// users are not affected.
 ///
 /// # Returns
 ///
@@ -66,13 +133,20 @@ pub async fn get_embedding(txt: String) -> Result<Vec<f32>, ServerFnError> {
 #[server]
 pub async fn reset_chat() -> Result<(), ServerFnError> {
     use crate::server::llm::reset_chat;
-    reset_chat().await.map_err(|e| ServerFnError::new(&format!("Error trying to reset chat: {}", e)))
+    let user = require_user().await?;
+
+    reset_chat(&user.username).await.map_err(|e| ServerFnError::new(&format!("Error trying to reset chat: {}", e)))
 }
 
 /// Processes a user prompt and returns a streaming text response.
 ///
 /// This function streams model responses token by token, allowing
-/// for real-time display to users.
+/// for real-time display to users. The prompt continues the signed in user's
+/// own conversation. The time to first token, total time
+/// and token counts of every response are recorded, and the tokens are
+/// charged to the user and the conversation. Requests are rejected when the
+/// user has used the daily or monthly allowance of their role, and requests
+/// made with an API key also count against its quotas.
 ///
 /// # Arguments
 ///
@@ -83,80 +157,944 @@ pub async fn reset_chat() -> Result<(), ServerFnError> {
 /// * `Result<TextStream, ServerFnError>` - Stream of response tokens or error
 #[server(output = StreamingText)]
 pub async fn get_response(prompt: String) -> Result<TextStream, ServerFnError> {
-    use crate::server::llm;
+    use crate::server::{llm, logging};
     use futures;
     use kalosm::language::{ChatModelExt, StreamExt, TextStream};
+    use tracing::Instrument;
+    use crate::server::generation_metrics::GenerationTimer;
+    use crate::server::llm::TokenUsage;
+    let user = require_user().await?;
 
     let (tx, rx) = futures::channel::mpsc::unbounded();
 
     // Check if the model is initialized
-    if llm::CHAT_SESSION.get().is_none() {
+    if llm::MODEL.get().is_none() {
         return Err(ServerFnError::new("Model not initialized"));
     }
 
-    let time = std::time::Instant::now();
-    println!("Processing prompt: {}", prompt);
+    let prompt_tokens = llm::count_tokens(&prompt).unwrap_or_else(|e| {
+        tracing::warn!("Error counting prompt tokens: {}", e);
+        0
+    });
+    let mut request = ModelRequest::admit(user, prompt_tokens as u64).await?;
 
-    // Try to get a stream without restarting
-    let mut stream = llm::try_get_stream(&prompt).expect("Error getting stream");
+    // Continue the user's conversation
+    let (conversation, mut stream) = llm::try_get_stream(&request.user.username, &prompt)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error getting stream: {}", e)))?;
+    request.conversation = Some(conversation);
+    let span = tracing::info_span!("response", conversation);
+    span.in_scope(|| tracing::info!(prompt = %logging::content(&prompt), "Processing prompt"));
+    let mut timer = GenerationTimer::start(prompt_tokens);
 
     tokio::spawn(async move {
         let _ = tx.unbounded_send(Ok("".to_string()));
         // Consume the stream and send tokens to the channel
         while let Some(token) = stream.next().await {
+            timer.on_token();
             if tx.unbounded_send(Ok(token)).is_err() {
-                println!("Error sending token");
+                tracing::warn!("Error sending token, the client disconnected");
                 break;
             }
         }
-    });
 
-    println!("\nTotal response time: {:?}", time.elapsed());
+        // The response is complete once the stream ends
+        let metrics = timer.finish();
+        tracing::info!(
+            time_to_first_token_ms = metrics.time_to_first_token_ms,
+            total_ms = metrics.total_ms,
+            prompt_tokens = metrics.prompt_tokens,
+            completion_tokens = metrics.completion_tokens,
+            tokens_per_second = metrics.tokens_per_second(),
+            "Response generated"
+        );
+        request.finish(TokenUsage { prompt_tokens: 0, completion_tokens: metrics.completion_tokens as u64 }).await;
+    }.instrument(span));
+
     Ok(server_fn::codec::TextStream::new(rx))
 }
 
-/// Searches the database for relevant context given a query.
+/// Searches the database for relevant context given a chat message.
 ///
-/// Retrieves documents that match the query from the database.
+/// When query rewriting is enabled the message is first turned into standalone
+/// queries using the recent conversation history, optionally with paraphrases,
+/// and the results of all the queries are merged.
 ///
 /// # Arguments
 ///
-/// * `q` - The search query
+/// * `q` - The latest user message
+/// * `history` - The recent conversation preceding the message, oldest first
+/// * `options` - Retrieval mode, number of results, reranking and rewriting settings
 ///
 /// # Returns
 ///
-/// * `Result<String, ServerFnError>` - Formatted context string or error
+/// * `Result<ContextSearch, ServerFnError>` - The queries used and the matching chunks or error
 #[server]
-pub async fn search_context(q: String) -> Result<String, ServerFnError> {
-    println!("Searching context for query: {}", q);
-    let context = crate::server::database_impl::query(&q).await.map_err(|e| {
-        println!("Error querying database: {}", e);
+pub async fn search_context(
+    q: String,
+    history: Vec<ChatMessage>,
+    options: SearchOptions,
+) -> Result<ContextSearch, ServerFnError> {
+    use crate::server::{database_impl, logging, query_rewrite};
+    use crate::server::llm::TokenUsage;
+    let request = ModelRequest::admit(require_user().await?, estimate_tokens(&q) + estimate_history_tokens(&history)).await?;
+    let options = options.clamped();
+    let mut usage = TokenUsage::default();
+
+    tracing::info!(query = %logging::content(&q), mode = ?options.mode, "Searching context");
+
+    let queries = if options.rewrite_query {
+        query_rewrite::rewrite_query(&history, &q, options.query_count(), &mut usage)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Error rewriting query, using the raw message: {}", e);
+                vec![q.clone()]
+            })
+    } else {
+        vec![q]
+    };
+    tracing::debug!(queries = %logging::content(&queries.join(" | ")), "Standalone queries");
+
+    let results = database_impl::query_many(&queries, &options, &mut usage).await.map_err(|e| {
+        tracing::error!("Error querying database: {}", e);
         ServerFnError::new(&format!("Error querying database: {}", e))
-    })?.into_iter()
-        .map(|document| {
-            format!(
-                "Title: {}\nBody: {}\n",
-                document.title,
-                document.body
-            )
-        }).collect::<Vec<_>>().join("\n");
-    Ok(context)
+    })?;
+
+    // Rewriting generates the queries and reranking scores the candidates with the language model
+    if options.rewrite_query || options.rerank {
+        request.finish(usage).await;
+    }
+
+    Ok(ContextSearch { queries, results, steps: Vec::new() })
 }
 
 /// Initializes the database connection.
 ///
-/// Must be called before any database operations can be performed.
+/// The server builds the knowledge base once when it starts, so this waits for
+/// that initialization to complete instead of rebuilding the database.
 ///
 /// # Returns
 ///
 /// * `Result<(), ServerFnError>` - Success or error with detailed message
 #[server]
 pub async fn init_db() -> Result<(), ServerFnError> {
-    crate::server::database_impl::connect_to_database()
+    require_admin().await?;
+    wait_for_startup().await
+}
+
+/// Streams the progress of the server initialization.
+///
+/// Every update is sent as a JSON encoded `InitProgress` followed by a newline.
+/// The stream ends once initialization has completed or failed.
+///
+/// # Returns
+///
+/// * `Result<TextStream, ServerFnError>` - Stream of progress updates or error
+#[server(output = StreamingText)]
+pub async fn init_progress() -> Result<TextStream, ServerFnError> {
+    use crate::server::startup;
+    require_user().await?;
+
+    let mut receiver = startup::subscribe();
+    let (tx, rx) = futures::channel::mpsc::unbounded();
+
+    tokio::spawn(async move {
+        loop {
+            let progress = receiver.borrow_and_update().clone();
+            let done = progress.ready || progress.error.is_some();
+            let line = match serde_json::to_string(&progress) {
+                Ok(json) => json + "\n",
+                Err(e) => {
+                    tracing::error!("Error encoding initialization progress: {}", e);
+                    break;
+                }
+            };
+            // Stop when the client disconnects, initialization ends or the channel closes
+            if tx.unbounded_send(Ok(line)).is_err() || done || receiver.changed().await.is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok(server_fn::codec::TextStream::new(rx))
+}
+
+/// Gets the configuration of a document collection.
+///
+/// # Arguments
+///
+/// * `name` - The name of the collection
+///
+/// # Returns
+///
+/// * `Result<CollectionConfig, ServerFnError>` - The stored or default configuration or error
+#[server]
+pub async fn get_collection_config(name: String) -> Result<CollectionConfig, ServerFnError> {
+    require_admin().await?;
+    crate::server::collections::load_collection_config(&name)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error loading collection configuration: {}", e)))
+}
+
+/// Saves the configuration of a document collection.
+///
+/// The chunking strategy is applied the next time the collection is built from
+/// the context folder.
+///
+/// # Arguments
+///
+/// * `config` - The configuration to store
+///
+/// # Returns
+///
+/// * `Result<(), ServerFnError>` - Success or error with detailed message
+#[server]
+pub async fn set_collection_config(config: CollectionConfig) -> Result<(), ServerFnError> {
+    require_admin().await?;
+    crate::server::collections::save_collection_config(config)
         .await
-        .map_err(|e| {
-            eprintln!("Error: {:?}", e);
-            ServerFnError::new(e)
-        })?;
+        .map_err(|e| ServerFnError::new(&format!("Error saving collection configuration: {}", e)))
+}
+
+/// Previews how a document would be chunked before ingesting it.
+///
+/// # Arguments
+///
+/// * `strategy` - The chunking strategy to preview
+/// * `text` - The document text
+///
+/// # Returns
+///
+/// * `Result<Vec<ChunkPreview>, ServerFnError>` - The chunks with their byte ranges and breadcrumbs or error
+#[server]
+pub async fn preview_chunking(strategy: ChunkingStrategy, text: String) -> Result<Vec<ChunkPreview>, ServerFnError> {
+    ModelRequest::admit(require_admin().await?, estimate_tokens(&text)).await?;
+    crate::server::database_impl::preview_chunks(&strategy, &text)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error previewing chunks: {}", e)))
+}
+
+/// Compares two texts by the cosine similarity of their embeddings.
+///
+/// # Arguments
+///
+/// * `first` - The first text
+/// * `second` - The second text
+///
+/// # Returns
+///
+/// * `Result<f32, ServerFnError>` - Similarity between -1.0 and 1.0 or error
+#[server]
+pub async fn compare_texts(first: String, second: String) -> Result<f32, ServerFnError> {
+    ModelRequest::admit(require_user().await?, estimate_tokens(&first) + estimate_tokens(&second)).await?;
+    crate::server::similarity::text_similarity(first, second)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error comparing texts: {}", e)))
+}
+
+/// Finds near-duplicate chunks across the knowledge base.
+///
+/// # Arguments
+///
+/// * `threshold` - Minimum cosine similarity for two chunks to be reported
+/// * `limit` - Maximum number of pairs to return
+///
+/// # Returns
+///
+/// * `Result<Vec<DuplicatePair>, ServerFnError>` - The most similar pairs first or error
+#[server]
+pub async fn find_near_duplicates(threshold: f32, limit: usize) -> Result<Vec<DuplicatePair>, ServerFnError> {
+    require_admin().await?;
+    crate::server::similarity::find_near_duplicates(threshold, limit)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error finding near duplicates: {}", e)))
+}
+
+/// Finds the documents most similar to a cited chunk.
+///
+/// # Arguments
+///
+/// * `text` - The text of the cited chunk
+/// * `title` - The title of the cited document, which is excluded from the results
+/// * `limit` - Maximum number of documents to return
+///
+/// # Returns
+///
+/// * `Result<Vec<SimpleDocumentResult>, ServerFnError>` - The most similar documents first or error
+#[server]
+pub async fn find_similar_documents(text: String, title: String, limit: usize) -> Result<Vec<SimpleDocumentResult>, ServerFnError> {
+    ModelRequest::admit(require_user().await?, estimate_tokens(&text)).await?;
+    crate::server::similarity::find_similar_documents(&text, &title, limit)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error finding similar documents: {}", e)))
+}
+
+/// Gets the topics covered by the knowledge base.
+///
+/// The chunks are clustered by their embeddings and each cluster is labelled by
+/// the language model. The last topic map is reused unless a refresh is requested.
+///
+/// # Arguments
+///
+/// * `clusters` - Number of topics to find, at most 30, chosen from the corpus size when `None`;
+///   only administrators may choose it
+/// * `refresh` - Whether to rebuild the topic map, for administrators only
+///
+/// # Returns
+///
+/// * `Result<TopicMap, ServerFnError>` - The topics with their documents and example chunks or error
+#[server]
+pub async fn get_topic_map(clusters: Option<usize>, refresh: bool) -> Result<TopicMap, ServerFnError> {
+    // Recomputing the map is expensive, so only administrators may force it
+    let user = if refresh { require_admin().await? } else { require_user().await? };
+    let clusters = if user.is_admin() { clusters } else { None };
+    // Building the map labels the topics with the language model
+    let request = ModelRequest::admit(user, 0).await?;
+    let (topic_map, usage) = crate::server::clustering::get_topic_map(clusters, refresh)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error building topic map: {}", e)))?;
+
+    // Only charged when this call built the map rather than reusing the last one
+    if let Some(usage) = usage {
+        request.finish(usage).await;
+    }
+    Ok(topic_map)
+}
+
+/// Lists the documents ingested into the knowledge base.
+///
+/// # Returns
+///
+/// * `Result<Vec<DocumentInfo>, ServerFnError>` - Title, source, chunk count and ingestion time of every document or error
+#[server]
+pub async fn list_documents() -> Result<Vec<DocumentInfo>, ServerFnError> {
+    require_user().await?;
+    crate::server::database_impl::list_documents()
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error listing documents: {}", e)))
+}
+
+/// Gets a document of the knowledge base with its chunks.
+///
+/// # Arguments
+///
+/// * `id` - Identifier of the document
+///
+/// # Returns
+///
+/// * `Result<DocumentDetails, ServerFnError>` - The document and its chunks in order or error
+#[server]
+pub async fn get_document(id: usize) -> Result<DocumentDetails, ServerFnError> {
+    require_user().await?;
+    crate::server::database_impl::get_document(id)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error loading document: {}", e)))
+}
+
+/// Searches the knowledge base directly, without involving the language model.
+///
+/// Query rewriting and reranking are disabled so the results show what the
+/// retrieval stage alone returns for the query.
+///
+/// # Arguments
+///
+/// * `query` - The search query
+/// * `options` - The retrieval mode, number of results and fusion settings
+///
+/// # Returns
+///
+/// * `Result<Vec<SimpleDocumentResult>, ServerFnError>` - The matching chunks with their scores or error
+#[server]
+pub async fn search_documents(query: String, options: SearchOptions) -> Result<Vec<SimpleDocumentResult>, ServerFnError> {
+    ModelRequest::admit(require_user().await?, estimate_tokens(&query)).await?;
+    let options = SearchOptions {
+        rerank: false,
+        rewrite_query: false,
+        ..options.clamped()
+    };
+    // Without reranking the search does not prompt the language model
+    crate::server::database_impl::query(&query, &options, &mut Default::default())
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error searching documents: {}", e)))
+}
+
+/// Exports the knowledge base into a portable archive.
+///
+/// The archive is written to the backup folder of the server and contains every
+/// document with its metadata, and every chunk with its embedding vector.
+///
+/// # Returns
+///
+/// * `Result<ArchiveSummary, ServerFnError>` - The path and contents of the archive or error
+#[server]
+pub async fn export_knowledge_base() -> Result<ArchiveSummary, ServerFnError> {
+    require_admin().await?;
+    crate::server::backup::export_archive()
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error exporting knowledge base: {}", e)))
+}
+
+/// Imports a knowledge base archive and restores its documents.
+///
+/// The chunks are inserted with the vectors of the archive, and embedded again
+/// only when the archive was embedded with another model than this server. The
+/// archive is kept in the import folder, so its documents are restored again
+/// after a restart.
+///
+/// # Arguments
+///
+/// * `path` - Path of the archive on the server
+///
+/// # Returns
+///
+/// * `Result<ArchiveSummary, ServerFnError>` - The contents of the imported archive or error
+#[server]
+pub async fn import_knowledge_base(path: String) -> Result<ArchiveSummary, ServerFnError> {
+    require_admin().await?;
+    let summary = crate::server::backup::import_archive(std::path::Path::new(&path))
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error importing knowledge base: {}", e)))?;
+    crate::server::backup::restore_archive(std::path::Path::new(&summary.path))
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error restoring imported documents: {}", e)))
+}
+
+/// Lets the assistant call tools before answering a message.
+///
+/// The model picks a tool call at each step, constrained to the declared tools
+/// and their argument schemas, and sees the result of every call before picking
+/// the next one. The knowledge base search and calculator tools are available.
+///
+/// # Arguments
+///
+/// * `message` - The message the assistant is answering
+///
+/// # Returns
+///
+/// * `Result<Vec<ToolStep>, ServerFnError>` - The tool calls made with their outputs or error
+#[server]
+pub async fn run_tools(message: String) -> Result<Vec<ToolStep>, ServerFnError> {
+    use crate::server::llm::TokenUsage;
+    use crate::server::tools::{builtin_tools, MAX_TOOL_STEPS};
+    let request = ModelRequest::admit(require_user().await?, estimate_tokens(&message)).await?;
+
+    let mut usage = TokenUsage::default();
+    let steps = crate::server::tools::run_tools(&message, &builtin_tools(), MAX_TOOL_STEPS, &mut usage)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error running tools: {}", e)))?;
+
+    request.finish(usage).await;
+    Ok(steps)
+}
+
+/// Lets the model decide whether and what to search before answering a message.
+///
+/// The model may answer without searching, or search the knowledge base several
+/// times with its own queries, up to a fixed number of searches. Each search is
+/// returned as a step of the trace.
+///
+/// # Arguments
+///
+/// * `q` - The latest user message
+/// * `history` - The recent conversation preceding the message, oldest first
+/// * `options` - Retrieval mode, number of results and reranking settings of every search
+///
+/// # Returns
+///
+/// * `Result<ContextSearch, ServerFnError>` - The queries issued, the merged results and the search trace or error
+#[server]
+pub async fn auto_search_context(
+    q: String,
+    history: Vec<ChatMessage>,
+    options: SearchOptions,
+) -> Result<ContextSearch, ServerFnError> {
+    use crate::server::agentic_search::{agentic_search, MAX_SEARCH_STEPS};
+    use crate::server::llm::TokenUsage;
+    let request = ModelRequest::admit(require_user().await?, estimate_tokens(&q) + estimate_history_tokens(&history)).await?;
+    let options = options.clamped();
+
+    tracing::info!(query = %crate::server::logging::content(&q), "Letting the model search context");
+    let mut usage = TokenUsage::default();
+    let search = agentic_search(&history, &q, &options, MAX_SEARCH_STEPS, &mut usage)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error searching context: {}", e)))?;
+
+    request.finish(usage).await;
+    Ok(search)
+}
+
+/// Generates a JSON document following a JSON Schema.
+///
+/// The model output is constrained to documents matching the schema, so the
+/// result is always valid. Meant for scripts extracting data from free text,
+/// such as filling a ticket template from a description.
+///
+/// # Arguments
+///
+/// * `schema` - The JSON Schema of the document, as JSON text
+/// * `prompt` - The text to extract the data from
+///
+/// # Returns
+///
+/// * `Result<serde_json::Value, ServerFnError>` - The generated document or error
+#[server]
+pub async fn generate_structured(schema: String, prompt: String) -> Result<serde_json::Value, ServerFnError> {
+    let request = ModelRequest::admit(require_user().await?, estimate_tokens(&schema) + estimate_tokens(&prompt)).await?;
+    let mut usage = crate::server::llm::TokenUsage::default();
+    let document = crate::server::json_schema::generate_json(&schema, &prompt, &mut usage)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error generating structured output: {}", e)))?;
+
+    request.finish(usage).await;
+    Ok(document)
+}
+
+/// Checks whether the sentences of an answer are supported by its sources.
+///
+/// # Arguments
+///
+/// * `answer` - The generated answer
+/// * `sources` - The chunks injected as context when generating the answer
+/// * `use_judge` - Whether sentences not clearly matching a source are judged by the language model
+///
+/// # Returns
+///
+/// * `Result<Vec<ClaimCheck>, ServerFnError>` - One check per sentence, in answer order, or error
+#[server]
+pub async fn check_grounding(
+    answer: String,
+    sources: Vec<SimpleDocumentResult>,
+    use_judge: bool,
+) -> Result<Vec<ClaimCheck>, ServerFnError> {
+    let source_tokens: u64 = sources.iter().map(|source| estimate_tokens(&source.body)).sum();
+    let request = ModelRequest::admit(require_user().await?, estimate_tokens(&answer) + source_tokens).await?;
+    let mut usage = crate::server::llm::TokenUsage::default();
+    let checks = crate::server::grounding::check_grounding(&answer, &sources, use_judge, &mut usage)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error checking grounding: {}", e)))?;
+
+    if use_judge {
+        request.finish(usage).await;
+    }
+    Ok(checks)
+}
+
+/// Gets the performance of the language model over the recent responses.
+///
+/// # Returns
+///
+/// * `Result<GenerationSummary, ServerFnError>` - Percentiles of time to first token, tokens per second and token counts
+#[server]
+pub async fn get_generation_metrics() -> Result<GenerationSummary, ServerFnError> {
+    require_admin().await?;
+    Ok(crate::server::generation_metrics::recent_summary())
+}
+
+/// Reports the initialization state of the models, the database connection and
+/// the document table, the number of indexed documents and the model ids.
+///
+/// # Returns
+///
+/// * `Result<SystemStatus, ServerFnError>` - The status of the server
+#[server]
+pub async fn system_status() -> Result<SystemStatus, ServerFnError> {
+    require_user().await?;
+    Ok(crate::server::status::system_status().await)
+}
+
+/// Logs in with a username and password.
+///
+/// On success the session cookie is set on the response, so every following
+/// request of the browser is made as this user.
+///
+/// # Arguments
+///
+/// * `username` - The name of the account
+/// * `password` - The password of the account
+///
+/// # Returns
+///
+/// * `Result<UserInfo, ServerFnError>` - The logged in user or error
+#[server]
+pub async fn login(username: String, password: String) -> Result<UserInfo, ServerFnError> {
+    use crate::server::auth;
+
+    let (user, token) = auth::login(&username, &password)
+        .await
+        .map_err(|e| ServerFnError::new(&e))?;
+    set_session_cookie(&auth::session_cookie(&token, auth::SESSION_DURATION_SECS))?;
+    Ok(user)
+}
+
+/// Logs out, closing the session of the request and removing its cookie.
+///
+/// # Returns
+///
+/// * `Result<(), ServerFnError>` - Success or error
+#[server]
+pub async fn logout() -> Result<(), ServerFnError> {
+    use crate::server::auth;
+
+    let token = auth::session_token(&dioxus::prelude::server_context().request_parts().headers);
+    if let Some(token) = token {
+        auth::logout(&token)
+            .await
+            .map_err(|e| ServerFnError::new(&format!("Error logging out: {}", e)))?;
+    }
+    set_session_cookie(&auth::session_cookie("", 0))
+}
+
+/// Returns the user the request is made by.
+///
+/// # Returns
+///
+/// * `Result<Option<UserInfo>, ServerFnError>` - The user, `None` when not logged in, or error
+#[server]
+pub async fn current_user() -> Result<Option<UserInfo>, ServerFnError> {
+    authenticate().await
+}
+
+/// Lists the user accounts. Requires the admin role.
+///
+/// # Returns
+///
+/// * `Result<Vec<UserInfo>, ServerFnError>` - The accounts sorted by name or error
+#[server]
+pub async fn list_users() -> Result<Vec<UserInfo>, ServerFnError> {
+    require_admin().await?;
+    crate::server::auth::list_users()
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error listing users: {}", e)))
+}
+
+/// Creates a user account. Requires the admin role.
+///
+/// # Arguments
+///
+/// * `username` - The name the user logs in with
+/// * `password` - The initial password of the account
+/// * `role` - The role of the account
+///
+/// # Returns
+///
+/// * `Result<UserInfo, ServerFnError>` - The new account or error
+#[server]
+pub async fn create_user(username: String, password: String, role: Role) -> Result<UserInfo, ServerFnError> {
+    require_admin().await?;
+    crate::server::auth::create_user(&username, &password, role)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error creating user: {}", e)))
+}
+
+/// Deletes a user account and closes its sessions. Requires the admin role.
+///
+/// Admins cannot delete their own account, so there is always one admin left.
+///
+/// # Arguments
+///
+/// * `username` - The account to delete
+///
+/// # Returns
+///
+/// * `Result<(), ServerFnError>` - Success or error
+#[server]
+pub async fn delete_user(username: String) -> Result<(), ServerFnError> {
+    let admin = require_admin().await?;
+    if admin.username == username {
+        return Err(ServerFnError::new("Admins cannot delete their own account"));
+    }
+    crate::server::auth::delete_user(&username)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error deleting user: {}", e)))
+}
+
+/// Lists the API keys with their quotas and usage. Requires the admin role.
+///
+/// # Returns
+///
+/// * `Result<Vec<ApiKeyInfo>, ServerFnError>` - The keys, oldest first, or error
+#[server]
+pub async fn list_api_keys() -> Result<Vec<ApiKeyInfo>, ServerFnError> {
+    require_admin().await?;
+    crate::server::api_keys::list_keys()
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error listing API keys: {}", e)))
+}
+
+/// Creates an API key for an account. Requires the admin role.
+///
+/// The secret is only returned by this call and cannot be recovered later.
+///
+/// # Arguments
+///
+/// * `name` - Name describing what the key is used for
+/// * `username` - The account the requests made with the key are attributed to
+/// * `requests_per_minute` - Maximum generation and embedding requests per minute, 0 for no limit
+/// * `tokens_per_day` - Maximum tokens processed per day, 0 for no limit
+///
+/// # Returns
+///
+/// * `Result<NewApiKey, ServerFnError>` - The key with its secret or error
+#[server]
+pub async fn create_api_key(
+    name: String,
+    username: String,
+    requests_per_minute: u32,
+    tokens_per_day: u64,
+) -> Result<NewApiKey, ServerFnError> {
+    require_admin().await?;
+    crate::server::api_keys::create_key(&name, &username, requests_per_minute, tokens_per_day)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error creating API key: {}", e)))
+}
+
+/// Revokes an API key. Requires the admin role.
+///
+/// # Arguments
+///
+/// * `id` - Identifier of the key
+///
+/// # Returns
+///
+/// * `Result<(), ServerFnError>` - Success or error
+#[server]
+pub async fn revoke_api_key(id: String) -> Result<(), ServerFnError> {
+    require_admin().await?;
+    crate::server::api_keys::revoke_key(&id)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error revoking API key: {}", e)))
+}
+
+/// Gets the token consumption and remaining allowance of the current user.
+///
+/// # Returns
+///
+/// * `Result<UserUsage, ServerFnError>` - The usage of the user or error
+#[server]
+pub async fn get_my_usage() -> Result<UserUsage, ServerFnError> {
+    let user = require_user().await?;
+    crate::server::usage::user_usage(&user)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error getting usage: {}", e)))
+}
+
+/// Gets the token consumption of every account. Requires the admin role.
+///
+/// # Returns
+///
+/// * `Result<Vec<UserUsage>, ServerFnError>` - The usage of the accounts by username or error
+#[server]
+pub async fn list_usage() -> Result<Vec<UserUsage>, ServerFnError> {
+    require_admin().await?;
+    crate::server::usage::all_usage()
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error listing usage: {}", e)))
+}
+
+/// Gets the configuration of the server and the current runtime settings.
+/// Requires the admin role.
+///
+/// # Returns
+///
+/// * `Result<SettingsOverview, ServerFnError>` - The configuration and settings or error
+#[server]
+pub async fn get_settings() -> Result<SettingsOverview, ServerFnError> {
+    use crate::server::{config, settings};
+    require_admin().await?;
+    let runtime = settings::current()
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error loading settings: {}", e)))?;
+    Ok(SettingsOverview {
+        config_file: config::config_file(),
+        config: config::get().clone(),
+        runtime,
+    })
+}
+
+/// Saves the runtime settings. Requires the admin role.
+///
+/// The settings are stored in the application database and apply without a
+/// restart: the quotas and the retrieval top-k to the next requests, and the
+/// persona to the next conversation.
+///
+/// # Arguments
+///
+/// * `settings` - The new runtime settings
+///
+/// # Returns
+///
+/// * `Result<(), ServerFnError>` - Success or error
+#[server]
+pub async fn set_runtime_settings(settings: RuntimeSettings) -> Result<(), ServerFnError> {
+    require_admin().await?;
+    crate::server::settings::save(settings)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error saving settings: {}", e)))
+}
+
+/// Gets the search options chat messages use by default, with the number of
+/// passages set in the runtime settings.
+///
+/// # Returns
+///
+/// * `Result<SearchOptions, ServerFnError>` - The default search options or error
+#[server]
+pub async fn get_search_defaults() -> Result<SearchOptions, ServerFnError> {
+    require_user().await?;
+    let settings = crate::server::settings::current()
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error loading settings: {}", e)))?;
+    Ok(SearchOptions {
+        top_k: settings.retrieval_top_k,
+        ..SearchOptions::default()
+    })
+}
+
+/// Waits for the initialization started with the server
+#[cfg(feature = "server")]
+async fn wait_for_startup() -> Result<(), ServerFnError> {
+    crate::server::startup::initialize()
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error initializing server: {}", e)))
+}
+
+/// Identifies the user making the current request
+#[cfg(feature = "server")]
+async fn authenticate() -> Result<Option<UserInfo>, ServerFnError> {
+    let headers = dioxus::prelude::server_context().request_parts().headers.clone();
+    crate::server::auth::authenticate(&headers)
+        .await
+        .map_err(|e| ServerFnError::new(&format!("Error checking session: {}", e)))
+}
+
+/// Rejects requests that are not made by a logged in user
+#[cfg(feature = "server")]
+async fn require_user() -> Result<UserInfo, ServerFnError> {
+    authenticate().await?.ok_or_else(|| ServerFnError::new("Not logged in"))
+}
+
+/// Rejects requests that are not made by an admin
+#[cfg(feature = "server")]
+async fn require_admin() -> Result<UserInfo, ServerFnError> {
+    let user = require_user().await?;
+    if !user.is_admin() {
+        return Err(ServerFnError::new("This operation requires the admin role"));
+    }
+    Ok(user)
+}
+
+/// A request to the models, admitted under the quotas of its caller
+///
+/// Every server function calling the language or embedding model admits its
+/// request first, so the quotas apply whichever model path is taken. Requests
+/// calling the language model are then finished, which charges the tokens to
+/// the user's conversation.
+#[cfg(feature = "server")]
+struct ModelRequest {
+    user: UserInfo,
+    /// API key the request was made with, if any
+    api_key: Option<String>,
+    /// Conversation of the user the request belongs to, if any
+    conversation: Option<u64>,
+    /// Tokens known to be processed by the request when it was admitted
+    prompt_tokens: u64,
+}
+
+#[cfg(feature = "server")]
+impl ModelRequest {
+    /// Checks the quotas of the user and of the API key of the current request, if any
+    ///
+    /// Both quotas are checked before the request is counted, and the request
+    /// rate of the key is checked atomically with counting it, so a rejected
+    /// request is never recorded. Rejected requests get the 401 Unauthorized
+    /// status when the key is unknown or revoked, 429 Too Many Requests when a
+    /// quota is used up and 500 Internal Server Error when the quotas cannot be
+    /// checked.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - The authenticated user making the request
+    /// * `tokens` - Tokens known to be processed by the request, such as the prompt
+    async fn admit(user: UserInfo, tokens: u64) -> Result<Self, ServerFnError> {
+        use crate::server::{api_keys, llm, usage};
+
+        let context = dioxus::prelude::server_context();
+        let api_key = api_keys::bearer_token(&context.request_parts().headers);
+        if let Some(key) = &api_key {
+            if let Err(e) = api_keys::check_quota(key, tokens).await {
+                tracing::warn!(username = %user.username, "Model request rejected: {}", e);
+                context.response_parts_mut().status = e.status();
+                return Err(ServerFnError::new(&e.to_string()));
+            }
+        }
+        if let Err(e) = usage::check_quota(&user, tokens).await {
+            tracing::warn!(username = %user.username, "Model request rejected: {}", e);
+            context.response_parts_mut().status = e.status();
+            return Err(ServerFnError::new(&e.to_string()));
+        }
+
+        if let Some(key) = &api_key {
+            if let Err(e) = api_keys::try_acquire(key, tokens).await {
+                tracing::warn!(username = %user.username, "Model request rejected: {}", e);
+                context.response_parts_mut().status = e.status();
+                return Err(ServerFnError::new(&e.to_string()));
+            }
+        }
+        Ok(Self {
+            conversation: llm::current_conversation(&user.username),
+            user,
+            api_key,
+            prompt_tokens: tokens,
+        })
+    }
+
+    /// Records the tokens of a request that called the language model
+    ///
+    /// The admitted tokens and the tokens read and generated by the model are
+    /// charged to the user's conversation; the tokens not counted at admission
+    /// are charged to the API key. The response has already been produced, so
+    /// failures are only logged.
+    ///
+    /// # Arguments
+    ///
+    /// * `usage` - Tokens read and generated by the language model after the request was admitted
+    async fn finish(self, usage: crate::server::llm::TokenUsage) {
+        use crate::server::{api_keys, usage as user_usage};
+
+        if let Some(key) = &self.api_key {
+            if let Err(e) = api_keys::charge_tokens(key, usage.prompt_tokens + usage.completion_tokens).await {
+                tracing::error!("Error charging tokens to the API key: {}", e);
+            }
+        }
+        let prompt_tokens = self.prompt_tokens + usage.prompt_tokens;
+        if let Err(e) = user_usage::record_usage(&self.user.username, self.conversation, prompt_tokens, usage.completion_tokens).await {
+            tracing::error!("Error recording usage: {}", e);
+        }
+    }
+}
+
+/// Estimates the tokens of a text with the tokenizer of the language model,
+/// falling back to its number of words
+#[cfg(feature = "server")]
+fn estimate_tokens(text: &str) -> u64 {
+    crate::server::llm::count_tokens(text).unwrap_or_else(|_| text.split_whitespace().count()) as u64
+}
+
+/// Estimates the tokens of the messages of a conversation
+#[cfg(feature = "server")]
+fn estimate_history_tokens(history: &[ChatMessage]) -> u64 {
+    history.iter().map(|message| estimate_tokens(&message.content)).sum()
+}
+
+/// Adds a `Set-Cookie` header to the response of the current request
+#[cfg(feature = "server")]
+fn set_session_cookie(cookie: &str) -> Result<(), ServerFnError> {
+    use axum::http::{header, HeaderValue};
+
+    let value = HeaderValue::from_str(cookie).map_err(|e| ServerFnError::new(&e.to_string()))?;
+    dioxus::prelude::server_context()
+        .response_parts_mut()
+        .headers
+        .append(header::SET_COOKIE, value);
     Ok(())
-}
\ No newline at end of file
+}